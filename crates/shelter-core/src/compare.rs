@@ -0,0 +1,83 @@
+//! Constant-time checks of guesses against hidden values
+//!
+//! Lets a user confirm that a value matches what someone reported without
+//! revealing it. Equality is decided on keyed digests of both strings, so the
+//! time taken does not depend on where they first differ. The shared prefix
+//! length is only computed when the caller's policy allows it, since it lets
+//! a guesser recover a value one character at a time.
+
+use crate::document::Document;
+use crate::keyed::keyed_hash;
+
+/// Domain label for comparison digests
+const COMPARE_DOMAIN: &str = "shelter-compare-v1";
+
+/// Outcome of comparing a candidate with an entry's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Comparison {
+    /// Index of the compared entry in the document
+    pub entry_index: usize,
+    pub matched: bool,
+    /// Shared leading characters, if requested
+    pub prefix_length: Option<usize>,
+}
+
+/// Compare two digests without early exit
+#[inline]
+fn ct_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// Length of the common byte prefix, visiting every byte of the longer input
+fn ct_prefix(value: &[u8], candidate: &[u8]) -> usize {
+    let mut matching = 1usize;
+    let mut length = 0usize;
+    for i in 0..value.len().max(candidate.len()) {
+        let a = value.get(i).copied().unwrap_or(0);
+        let b = candidate.get(i).copied().unwrap_or(!a);
+        matching &= (a == b) as usize;
+        length += std::hint::black_box(matching);
+    }
+    length
+}
+
+/// Compare `candidate` with the value of `key` in `document`
+///
+/// The entry is the last uncommented one with that key, matching how dotenv
+/// loaders resolve duplicates. Returns None if there is no such entry.
+pub(crate) fn compare_value(
+    document: &Document,
+    key: &str,
+    candidate: &str,
+    report_prefix: bool,
+) -> Option<Comparison> {
+    let (entry_index, pair) = document
+        .pairs
+        .iter()
+        .enumerate()
+        .rfind(|(_, pair)| !pair.kv.is_comment && pair.kv.key == key)?;
+    let value = pair.kv.value.as_bytes();
+
+    // Digests never leave this function, so no secret key is needed
+    let matched = ct_eq(
+        &keyed_hash(&[], COMPARE_DOMAIN, value),
+        &keyed_hash(&[], COMPARE_DOMAIN, candidate.as_bytes()),
+    );
+
+    // Only the already-disclosed prefix is inspected to count characters
+    let prefix_length = report_prefix.then(|| {
+        let bytes = ct_prefix(value, candidate.as_bytes());
+        let mut end = bytes;
+        while !pair.kv.value.is_char_boundary(end) {
+            end -= 1;
+        }
+        pair.kv.value[..end].chars().count()
+    });
+
+    Some(Comparison {
+        entry_index,
+        matched,
+        prefix_length,
+    })
+}
@@ -0,0 +1,49 @@
+//! Document diagnostics
+//!
+//! Problems found while parsing or analysing a document, each with a stable
+//! code so editors can filter or suppress them.
+
+use crate::types::{ShelterDiagnostic, ShelterSeverity};
+use std::ffi::CString;
+
+/// A problem found in a document
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub severity: ShelterSeverity,
+    /// 1-based line number
+    pub line_number: usize,
+    /// Byte range of the reported text
+    pub start: usize,
+    pub end: usize,
+    /// Index of the affected entry
+    pub entry_index: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Convert to the FFI representation (strings are owned by the caller)
+    pub(crate) fn into_ffi(self) -> ShelterDiagnostic {
+        ShelterDiagnostic {
+            code: CString::new(self.code).unwrap_or_default().into_raw(),
+            message: CString::new(self.message).unwrap_or_default().into_raw(),
+            line_number: self.line_number,
+            start: self.start,
+            end: self.end,
+            entry_index: self.entry_index.unwrap_or(usize::MAX),
+            severity: self.severity as u8,
+        }
+    }
+}
+
+/// A span of time for messages: `3 days`, `1 hour`, `45 seconds`
+pub(crate) fn describe_span(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (count, unit) = match seconds {
+        s if s >= 86_400 => (s / 86_400, "day"),
+        s if s >= 3_600 => (s / 3_600, "hour"),
+        s if s >= 60 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
@@ -0,0 +1,206 @@
+//! Inline `# shelter:` directives
+//!
+//! Comments of the form `# shelter: <directive>` record masking intent in the
+//! file itself:
+//! - `mode=<name>` sets the mode of the next entry
+//! - `begin <name>` ... `end` sets the mode of the entries in between (blocks
+//!   nest)
+//! - `ignore-file` sets mode `none` for every entry in the file
+//!
+//! A `mode=` directive beats an enclosing block, which beats `ignore-file`.
+//! Directive lines are never parsed as commented-out entries. Unknown or
+//! malformed directives, unknown modes, stray `end`s and unclosed blocks are
+//! reported as diagnostics and otherwise ignored.
+
+use crate::diagnostics::Diagnostic;
+use crate::types::ShelterSeverity;
+
+/// Comment prefix that marks a directive
+const PREFIX: &str = "shelter:";
+
+const NO_ENTRY: &str = "Directive applies to no entry";
+const NO_BEGIN: &str = "`end` without a matching `begin`";
+const NO_END: &str = "`begin` without a matching `end`";
+
+/// A parsed directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Directive<'a> {
+    Mode(&'a str),
+    Begin(&'a str),
+    End,
+    IgnoreFile,
+}
+
+/// Mode override an entry gets from a directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Override<'a> {
+    pub mode: &'a str,
+    /// 1-based line of the directive
+    pub line_number: usize,
+    /// Byte range of the directive comment
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parse the text of a comment after its `#`
+///
+/// Returns None for ordinary comments, or an error message for comments that
+/// start with `shelter:` but are not a valid directive.
+pub(crate) fn parse_directive(comment: &str) -> Option<Result<Directive<'_>, String>> {
+    let body = comment.trim().strip_prefix(PREFIX)?.trim();
+    let mut words = body.split_whitespace();
+
+    Some(match (words.next(), words.next(), words.next()) {
+        (Some("ignore-file"), None, None) => Ok(Directive::IgnoreFile),
+        (Some("end"), None, None) => Ok(Directive::End),
+        (Some("begin"), Some(mode), None) => Ok(Directive::Begin(mode)),
+        (Some("begin"), None, None) => Err("`begin` needs a mode".to_owned()),
+        (Some(word), None, None) if word.starts_with("mode=") => match &word["mode=".len()..] {
+            "" => Err("`mode=` needs a mode".to_owned()),
+            mode => Ok(Directive::Mode(mode)),
+        },
+        _ => Err(format!("Unknown shelter directive `{}`", body)),
+    })
+}
+
+/// Resolves directives in document order and collects diagnostics
+#[derive(Default)]
+pub(crate) struct DirectiveState<'a> {
+    /// Modes directives may name (None = any)
+    modes: Option<Vec<String>>,
+    /// `mode=` waiting for the next entry
+    pending: Option<Override<'a>>,
+    /// Open blocks, innermost last, with whether their mode is known
+    blocks: Vec<(Override<'a>, bool)>,
+    ignore_file: Option<Override<'a>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> DirectiveState<'a> {
+    /// State that reports directives naming a mode outside `modes`
+    pub(crate) fn with_modes(modes: Option<Vec<String>>) -> Self {
+        Self {
+            modes,
+            ..Self::default()
+        }
+    }
+
+    /// Handle a comment spanning `start..end` (including its `#`)
+    ///
+    /// Returns whether the comment was a directive (valid or not).
+    pub(crate) fn comment(
+        &mut self,
+        input: &'a str,
+        start: usize,
+        end: usize,
+        line_number: usize,
+    ) -> bool {
+        let Some(directive) = input.get(start + 1..end).and_then(parse_directive) else {
+            return false;
+        };
+        let here = |mode| Override {
+            mode,
+            line_number,
+            start,
+            end,
+        };
+
+        match directive {
+            Ok(Directive::Mode(mode)) => {
+                if let Some(unused) = self.pending.take() {
+                    self.unused(unused);
+                }
+                if self.check_mode(mode, line_number, start, end) {
+                    self.pending = Some(here(mode));
+                }
+            }
+            Ok(Directive::Begin(mode)) => {
+                // Unknown blocks are still tracked so their `end` matches
+                let known = self.check_mode(mode, line_number, start, end);
+                self.blocks.push((here(mode), known));
+            }
+            Ok(Directive::End) => {
+                if self.blocks.pop().is_none() {
+                    self.warn("unmatched-end", NO_BEGIN, line_number, start, end);
+                }
+            }
+            Ok(Directive::IgnoreFile) => {
+                self.ignore_file.get_or_insert(here("none"));
+            }
+            Err(message) => self.warn("unknown-directive", &message, line_number, start, end),
+        }
+        true
+    }
+
+    /// Override for the next entry
+    pub(crate) fn next_entry(&mut self) -> Option<Override<'a>> {
+        self.pending.take().or_else(|| {
+            self.blocks
+                .iter()
+                .rev()
+                .find(|(_, known)| *known)
+                .map(|&(block, _)| block)
+        })
+    }
+
+    /// Report unclosed blocks and unused directives; returns the override of
+    /// `ignore-file` for entries without one
+    pub(crate) fn finish(&mut self) -> Option<Override<'a>> {
+        if let Some(unused) = self.pending.take() {
+            self.unused(unused);
+        }
+        for (block, _) in std::mem::take(&mut self.blocks) {
+            self.warn(
+                "unclosed-block",
+                NO_END,
+                block.line_number,
+                block.start,
+                block.end,
+            );
+        }
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.start);
+        self.ignore_file
+    }
+
+    /// Whether `mode` may be used, reporting it otherwise
+    fn check_mode(&mut self, mode: &str, line_number: usize, start: usize, end: usize) -> bool {
+        let known = self
+            .modes
+            .as_ref()
+            .is_none_or(|modes| modes.iter().any(|name| name == mode));
+        if !known {
+            let message = format!("Unknown mode `{}`", mode);
+            self.warn("unknown-directive", &message, line_number, start, end);
+        }
+        known
+    }
+
+    fn unused(&mut self, directive: Override) {
+        self.warn(
+            "unused-directive",
+            NO_ENTRY,
+            directive.line_number,
+            directive.start,
+            directive.end,
+        );
+    }
+
+    fn warn(
+        &mut self,
+        code: &'static str,
+        message: &str,
+        line_number: usize,
+        start: usize,
+        end: usize,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            code,
+            message: message.to_owned(),
+            severity: ShelterSeverity::Warning,
+            line_number,
+            start,
+            end,
+            entry_index: None,
+        });
+    }
+}
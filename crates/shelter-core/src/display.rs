@@ -0,0 +1,66 @@
+//! Display-cell arithmetic
+//!
+//! Neovim places overlays by screen cell, not by byte. These helpers compute
+//! how many cells text occupies, with tabs expanding to the next tab stop and
+//! East Asian wide characters and emoji taking two cells.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Tab stop used when the caller does not specify one
+pub(crate) const DEFAULT_TABSTOP: usize = 8;
+
+/// Cells occupied by `c` when drawn at cell `col`
+#[inline]
+pub(crate) fn char_cells(c: char, col: usize, tabstop: usize) -> usize {
+    if c == '\t' {
+        tabstop - col % tabstop
+    } else {
+        c.width().unwrap_or(0)
+    }
+}
+
+/// Cell where `text` ends when drawn starting at cell `col`
+///
+/// Runs between tabs are measured as whole strings so emoji sequences count
+/// as one glyph.
+pub(crate) fn end_cell(text: &str, col: usize, tabstop: usize) -> usize {
+    let mut runs = text.split('\t');
+    let mut col = col + runs.next().map_or(0, UnicodeWidthStr::width);
+    for run in runs {
+        col += tabstop - col % tabstop;
+        col += run.width();
+    }
+    col
+}
+
+/// Cells occupied by `text` when drawn starting at cell 0
+#[inline]
+pub(crate) fn str_cells(text: &str) -> usize {
+    end_cell(text, 0, DEFAULT_TABSTOP)
+}
+
+/// A character of a line with its byte offset and cell range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cell {
+    pub byte: usize,
+    pub len: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Lay out a single line (without its line break) into cells
+pub(crate) fn layout(line: &str, tabstop: usize) -> Vec<Cell> {
+    let mut col = 0;
+    line.char_indices()
+        .map(|(byte, c)| {
+            let start = col;
+            col += char_cells(c, col, tabstop);
+            Cell {
+                byte,
+                len: c.len_utf8(),
+                start,
+                end: col,
+            }
+        })
+        .collect()
+}
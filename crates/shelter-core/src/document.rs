@@ -0,0 +1,214 @@
+//! Parsed document model shared by the native features
+//!
+//! Wraps korni's key-value pairs with line information and stable ids, so
+//! parsing, rendering and analysis all agree on the same spans.
+
+use crate::diagnostics::Diagnostic;
+use crate::directives::{DirectiveState, Override};
+use crate::identity::IdAssigner;
+use korni::{Entry, KeyValuePair, QuoteType};
+
+/// A key-value pair with resolved line numbers and identity
+pub(crate) struct Pair<'a> {
+    pub kv: KeyValuePair<'a>,
+    /// 1-based line number where the key starts
+    pub line_number: usize,
+    /// 1-based line number where the value ends
+    pub value_end_line: usize,
+    /// Stable identity (see `identity::entry_id`)
+    pub id: u64,
+    /// Mode set by an inline directive (see `directives`)
+    pub directive: Option<Override<'a>>,
+}
+
+impl Pair<'_> {
+    /// Byte range of the raw value, including quotes
+    #[inline]
+    pub fn value_range(&self) -> (usize, usize) {
+        self.kv
+            .value_span
+            .map(|s| (s.start.offset, s.end.offset))
+            .unwrap_or((0, 0))
+    }
+
+    /// Byte range of the key
+    #[inline]
+    pub fn key_range(&self) -> (usize, usize) {
+        self.kv
+            .key_span
+            .map(|s| (s.start.offset, s.end.offset))
+            .unwrap_or((0, 0))
+    }
+
+    /// Byte range of the value content, excluding quotes
+    #[inline]
+    pub fn content_range(&self) -> (usize, usize) {
+        let (start, end) = self.value_range();
+        if self.kv.quote == QuoteType::None || end < start + 2 {
+            (start, end)
+        } else {
+            (start + 1, end - 1)
+        }
+    }
+
+    /// Map byte ranges of the value onto the raw content `raw` (offsets
+    /// relative to its start), undoing double-quote escapes
+    ///
+    /// Returns None when the value cannot be lined up with the raw text.
+    pub fn raw_ranges(&self, raw: &str, ranges: &[(usize, usize)]) -> Option<Vec<(usize, usize)>> {
+        let value = &*self.kv.value;
+        if raw == value {
+            return Some(ranges.to_vec());
+        }
+        if self.kv.quote != QuoteType::Double {
+            return None;
+        }
+
+        // Raw offset of each value byte: `\n \r \t \\ \" \$` are one value
+        // byte, any other backslash is kept as is
+        let bytes = raw.as_bytes();
+        let mut offsets = Vec::with_capacity(value.len() + 1);
+        let mut i = 0;
+        while i < bytes.len() {
+            offsets.push(i);
+            i += match (bytes[i], bytes.get(i + 1)) {
+                (b'\\', Some(b'n' | b'r' | b't' | b'\\' | b'"' | b'$')) => 2,
+                _ => 1,
+            };
+        }
+        offsets.push(raw.len());
+        if offsets.len() != value.len() + 1 {
+            return None;
+        }
+        ranges
+            .iter()
+            .map(|&(start, end)| Some((*offsets.get(start)?, *offsets.get(end)?)))
+            .collect()
+    }
+}
+
+/// A parsed document: pairs in order plus line start offsets
+pub(crate) struct Document<'a> {
+    pub pairs: Vec<Pair<'a>>,
+    /// Byte offset where each line starts (`line_starts[0] == 0`)
+    pub line_starts: Vec<usize>,
+    /// Problems with inline directives
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Binary search to find line number from byte offset
+/// Returns 1-based line number
+#[inline]
+fn offset_to_line_binary(line_starts: &[usize], offset: usize) -> usize {
+    match line_starts.binary_search(&offset) {
+        // Exact match: offset is at start of this line
+        Ok(line) => line + 1,
+        // Not found: offset is within the line before insert point
+        Err(line) => line,
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Parse EDF content
+    pub fn parse(input: &'a str, options: korni::ParseOptions) -> Self {
+        Self::parse_with_modes(input, options, None)
+    }
+
+    /// Parse EDF content, reporting directives that name a mode outside
+    /// `modes` (None = any)
+    pub fn parse_with_modes(
+        input: &'a str,
+        options: korni::ParseOptions,
+        modes: Option<Vec<String>>,
+    ) -> Self {
+        let parsed_entries = korni::parse_with_options(input, options);
+
+        // Build line_starts array: indices where each line begins
+        // Pre-allocate with estimated capacity (avg line length ~30 chars)
+        let estimated_lines = input.len() / 30 + 1;
+        let mut line_starts: Vec<usize> = Vec::with_capacity(estimated_lines);
+        line_starts.push(0); // Line 1 starts at offset 0
+
+        for (i, b) in input.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+
+        let mut pairs = Vec::with_capacity(parsed_entries.len());
+        let mut ids = IdAssigner::default();
+        let mut directives = DirectiveState::with_modes(modes);
+        // Commented-out pairs korni finds on a directive line are not entries
+        let mut directive_line = 0;
+
+        for entry in parsed_entries {
+            match entry {
+                Entry::Pair(kv) => {
+                    let line_number = kv
+                        .key_span
+                        .map(|s| offset_to_line_binary(&line_starts, s.start.offset))
+                        .unwrap_or(0);
+
+                    // End line of multi-line values, by binary search
+                    let value_end_line = kv
+                        .value_span
+                        .map(|s| {
+                            offset_to_line_binary(&line_starts, s.end.offset.saturating_sub(1))
+                        })
+                        .unwrap_or(line_number);
+
+                    if kv.is_comment && line_number == directive_line {
+                        continue;
+                    }
+
+                    let id = ids.next(&kv.key, kv.is_comment);
+                    pairs.push(Pair {
+                        kv: *kv,
+                        line_number,
+                        value_end_line,
+                        id,
+                        directive: directives.next_entry(),
+                    });
+                }
+                Entry::Comment(span) => {
+                    let (start, end) = (span.start.offset, span.end.offset);
+                    let line_number = offset_to_line_binary(&line_starts, start);
+                    if directives.comment(input, start, end, line_number) {
+                        directive_line = line_number;
+                    }
+                }
+                Entry::Error(_) => {
+                    // Silently skip parse errors - expected during editing
+                }
+            }
+        }
+
+        if let Some(ignore_file) = directives.finish() {
+            for pair in pairs.iter_mut().filter(|pair| pair.directive.is_none()) {
+                pair.directive = Some(ignore_file);
+            }
+        }
+
+        Document {
+            pairs,
+            line_starts,
+            diagnostics: directives.diagnostics,
+        }
+    }
+
+    /// 1-based line number of a byte offset
+    #[inline]
+    pub fn line_number(&self, offset: usize) -> usize {
+        offset_to_line_binary(&self.line_starts, offset)
+    }
+
+    /// Byte offset where a 1-based line starts
+    #[inline]
+    pub fn line_start(&self, line_number: usize) -> usize {
+        line_number
+            .checked_sub(1)
+            .and_then(|i| self.line_starts.get(i))
+            .copied()
+            .unwrap_or(0)
+    }
+}
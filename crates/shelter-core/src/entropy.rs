@@ -0,0 +1,109 @@
+//! Value entropy
+//!
+//! Shannon entropy over characters, in bits per character. Random tokens score
+//! high (about 6 for base64), words and repeated characters score low.
+//!
+//! Raw entropy depends on the alphabet: random hex tops out at 4 bits, random
+//! base64 at 6. The randomness score divides it by the most a value of that
+//! charset and length could have (`log2` of the charset size, or of the length
+//! for short values), giving 0 to 1 regardless of encoding.
+
+use crate::types::ShelterCharset;
+use std::collections::HashMap;
+
+/// Shortest value that can look random
+const MIN_RANDOM_LEN: usize = 16;
+
+/// Randomness score from which a value looks random
+const RANDOM_SCORE: f64 = 0.8;
+
+/// Shannon entropy of `value` in bits per character (0 for empty values)
+pub(crate) fn shannon_entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    let mut total = 0usize;
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return 0.0;
+    }
+
+    let total = total as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Smallest charset containing every character of `value`
+pub(crate) fn charset(value: &str) -> ShelterCharset {
+    let within = |allowed: fn(char) -> bool| value.chars().all(allowed);
+    if value.is_empty() {
+        ShelterCharset::None
+    } else if within(|c| c.is_ascii_digit()) {
+        ShelterCharset::Digits
+    } else if within(|c| c.is_ascii_hexdigit()) {
+        ShelterCharset::Hex
+    } else if within(|c| c.is_ascii_alphanumeric()) {
+        ShelterCharset::Alphanumeric
+    } else if within(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_')) {
+        ShelterCharset::Base64
+    } else {
+        ShelterCharset::Other
+    }
+}
+
+/// Number of characters in a charset (printable ASCII for `Other`)
+fn charset_size(charset: ShelterCharset) -> f64 {
+    match charset {
+        ShelterCharset::None => 1.0,
+        ShelterCharset::Digits => 10.0,
+        ShelterCharset::Hex => 16.0,
+        ShelterCharset::Alphanumeric => 62.0,
+        ShelterCharset::Base64 => 64.0,
+        ShelterCharset::Other => 95.0,
+    }
+}
+
+/// Entropy of a value relative to its charset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Randomness {
+    /// Shannon entropy in bits per character
+    pub entropy: f64,
+    pub charset: ShelterCharset,
+    /// Entropy as a fraction of the most possible for the charset and length
+    pub score: f64,
+    /// Long enough, token-like charset and a high score
+    pub looks_random: bool,
+}
+
+/// Score how random `value` looks
+pub(crate) fn randomness(value: &str) -> Randomness {
+    let entropy = shannon_entropy(value);
+    let charset = charset(value);
+    let len = value.chars().count();
+
+    let max = charset_size(charset).log2().min((len as f64).log2());
+    let score = if max > 0.0 {
+        (entropy / max).min(1.0)
+    } else {
+        0.0
+    };
+    let looks_random = len >= MIN_RANDOM_LEN
+        && matches!(
+            charset,
+            ShelterCharset::Hex | ShelterCharset::Alphanumeric | ShelterCharset::Base64
+        )
+        && score >= RANDOM_SCORE;
+
+    Randomness {
+        entropy,
+        charset,
+        score,
+        looks_random,
+    }
+}
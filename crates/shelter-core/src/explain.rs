@@ -0,0 +1,186 @@
+//! Mode decision traces
+//!
+//! Replays mode selection for one entry and records every rule it considers:
+//! its inline directive, policy rules in the order they are tried, then key
+//! patterns, then source patterns, then a public key prefix, then the default
+//! mode. Every rule is evaluated, so the trace also
+//! shows rules that matched but lost; exactly one step is marked as chosen,
+//! and it is the one `RenderSettings::select` acts on.
+
+use crate::classify::{classify, Classification};
+use crate::document::Pair;
+use crate::entropy::{randomness, Randomness};
+use crate::patterns::ShelterPatternSet;
+use crate::public::{Exposure, PUBLIC_MODE};
+use crate::render::RenderSettings;
+use crate::types::ShelterTraceStage;
+
+/// One rule considered for an entry
+#[derive(Debug, Clone)]
+pub(crate) struct TraceStep {
+    pub stage: ShelterTraceStage,
+    /// Index of the rule within its stage (None for the default and directives)
+    pub rule_index: Option<usize>,
+    /// Pattern, policy rule summary, directive line or default mode name
+    pub rule: String,
+    pub mode: String,
+    /// Policy priority or pattern specificity
+    pub rank: i64,
+    pub matched: bool,
+    pub chosen: bool,
+    /// Why a policy rule did not match (the failing condition), why a
+    /// directive was ignored, or why a public prefix did not apply
+    pub detail: Option<&'static str>,
+}
+
+/// Full trace of a mode decision
+#[derive(Debug, Clone)]
+pub(crate) struct Explanation {
+    pub steps: Vec<TraceStep>,
+    /// Name of the chosen mode as configured
+    pub mode: String,
+    pub stage: ShelterTraceStage,
+    /// Whether the chosen mode is not registered (rendering falls back to full)
+    pub unknown_mode: bool,
+    /// Classifier input seen by `secret_type`, `min_confidence`,
+    /// `min_entropy` and `looks_random` conditions
+    pub classification: Option<Classification>,
+    pub randomness: Randomness,
+}
+
+/// Steps for every pattern of a set, marking the winner for `text`
+fn pattern_steps(
+    stage: ShelterTraceStage,
+    set: &ShelterPatternSet,
+    text: Option<&str>,
+    decided: bool,
+) -> (Vec<TraceStep>, Option<String>) {
+    let matching = text.map(|text| set.matching(text)).unwrap_or_default();
+    let best = text
+        .and_then(|text| set.best_index(text))
+        .filter(|_| !decided);
+
+    let steps = set
+        .rules()
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| TraceStep {
+            stage,
+            rule_index: Some(index),
+            rule: rule.pattern.clone(),
+            mode: rule.mode.clone(),
+            rank: rule.specificity as i64,
+            matched: matching.contains(&index),
+            chosen: best == Some(index),
+            detail: None,
+        })
+        .collect();
+    (steps, best.map(|index| set.rules()[index].mode.clone()))
+}
+
+/// Trace the mode decision for an entry
+pub(crate) fn explain(pair: &Pair, settings: &RenderSettings) -> Explanation {
+    let source = settings.source.as_deref();
+    let mut steps = Vec::new();
+    let mut decision: Option<(String, ShelterTraceStage)> = None;
+
+    if let Some(directive) = pair.directive {
+        steps.push(TraceStep {
+            stage: ShelterTraceStage::Directive,
+            rule_index: None,
+            rule: format!("line {}", directive.line_number),
+            mode: directive.mode.to_owned(),
+            rank: 0,
+            matched: true,
+            chosen: settings.directives,
+            detail: (!settings.directives).then_some("directives ignored"),
+        });
+        if settings.directives {
+            decision = Some((directive.mode.to_owned(), ShelterTraceStage::Directive));
+        }
+    }
+
+    if let Some(policy) = settings.policy {
+        for &index in policy.order() {
+            let rule = &policy.rules()[index];
+            let check = rule.check(pair, source);
+            let chosen = check.is_ok() && decision.is_none();
+            if chosen {
+                decision = Some((rule.mode.clone(), ShelterTraceStage::Policy));
+            }
+            steps.push(TraceStep {
+                stage: ShelterTraceStage::Policy,
+                rule_index: Some(index),
+                rule: rule.summary(),
+                mode: rule.mode.clone(),
+                rank: rule.priority() as i64,
+                matched: check.is_ok(),
+                chosen,
+                detail: check.err(),
+            });
+        }
+    }
+
+    for (stage, set, text) in [
+        (
+            ShelterTraceStage::KeyPattern,
+            &settings.key_rules,
+            Some(pair.kv.key.as_ref()),
+        ),
+        (
+            ShelterTraceStage::SourcePattern,
+            &settings.source_rules,
+            source,
+        ),
+    ] {
+        let (stage_steps, mode) = pattern_steps(stage, set, text, decision.is_some());
+        steps.extend(stage_steps);
+        if let Some(mode) = mode {
+            decision = Some((mode, stage));
+        }
+    }
+
+    if let Some(prefix) = settings.public_prefixes.matching(&pair.kv.key) {
+        let exposure = settings
+            .public_prefixes
+            .exposure(&pair.kv.key, &pair.kv.value);
+        let matched = exposure == Exposure::Public;
+        let chosen = matched && decision.is_none();
+        if chosen {
+            decision = Some((PUBLIC_MODE.to_owned(), ShelterTraceStage::PublicPrefix));
+        }
+        steps.push(TraceStep {
+            stage: ShelterTraceStage::PublicPrefix,
+            rule_index: None,
+            rule: prefix.to_owned(),
+            mode: PUBLIC_MODE.to_owned(),
+            rank: 0,
+            matched,
+            chosen,
+            detail: (!matched).then_some("value is secret"),
+        });
+    }
+
+    let default = decision.is_none();
+    steps.push(TraceStep {
+        stage: ShelterTraceStage::Default,
+        rule_index: None,
+        rule: settings.default_mode.clone(),
+        mode: settings.default_mode.clone(),
+        rank: 0,
+        matched: true,
+        chosen: default,
+        detail: None,
+    });
+    let (mode, stage) =
+        decision.unwrap_or_else(|| (settings.default_mode.clone(), ShelterTraceStage::Default));
+
+    Explanation {
+        steps,
+        unknown_mode: settings.modes.get(&mode).is_none(),
+        mode,
+        stage,
+        classification: classify(&pair.kv.value),
+        randomness: randomness(&pair.kv.value),
+    }
+}
@@ -2,10 +2,41 @@
 //!
 //! These functions are exposed via the C ABI for LuaJIT FFI.
 
-use crate::types::{ShelterEntry, ShelterParseOptions, ShelterResult};
-use korni::Entry;
+use crate::classify::classify;
+use crate::compare::compare_value;
+use crate::diagnostics::Diagnostic;
+use crate::document::Document;
+use crate::entropy::randomness;
+use crate::explain::explain;
+use crate::identity::diff_entries;
+use crate::infer::infer_type;
+use crate::jwt::{decode as decode_jwt, expiry_diagnostic};
+use crate::keyed::fingerprint;
+use crate::modes::{Mode, ShelterModeTable};
+use crate::patterns::{PatternOptions, ShelterPatternSet};
+use crate::payload::inspect;
+use crate::pem::{
+    blocks as pem_blocks, comment_blocks, expiry_diagnostic as certificate_diagnostic, Pem,
+    DEFAULT_EXPIRY_WINDOW_DAYS,
+};
+use crate::policy::{PolicyRule, ShelterPolicy};
+use crate::public::{leak_diagnostic, Exposure, PublicPrefixes};
+use crate::render::{render_masked, render_plan, rules_from_ffi, RenderSettings};
+use crate::reveal::Reveal;
+use crate::structured::structure;
+use crate::types::{
+    ShelterAnalysisOptions, ShelterConfidence, ShelterDiff, ShelterEntry, ShelterExplanation,
+    ShelterLeakReport, ShelterLineSpan, ShelterMaskedDocument, ShelterModeOptions, ShelterOverlay,
+    ShelterParseOptions, ShelterPatternOptions, ShelterPatternRule, ShelterPayload, ShelterPemKind,
+    ShelterPolicyRule, ShelterRenderConfig, ShelterRenderPlan, ShelterResult, ShelterReveal,
+    ShelterSecretSpans, ShelterTraceStep, ShelterVerifyPolicy, ShelterVerifyResult,
+};
+use crate::verify::{verify_overlays, PlacedOverlay};
+use crate::weak::{weak_value_diagnostic, DEFAULT_MIN_SECRET_LENGTH};
 use std::ffi::{c_char, CString};
+use std::ptr;
 use std::slice;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Library version string
 const VERSION: &[u8] = b"0.1.0\0";
@@ -14,18 +45,6 @@ const VERSION: &[u8] = b"0.1.0\0";
 //  Parsing Functions
 // =============================================================================
 
-/// Binary search to find line number from byte offset
-/// Returns 1-based line number
-#[inline]
-fn offset_to_line_binary(line_starts: &[usize], offset: usize) -> usize {
-    match line_starts.binary_search(&offset) {
-        // Exact match: offset is at start of this line
-        Ok(line) => line + 1,
-        // Not found: offset is within the line before insert point
-        Err(line) => line,
-    }
-}
-
 /// Parse EDF content and return entries
 ///
 /// # Safety
@@ -37,6 +56,26 @@ pub unsafe extern "C" fn shelter_parse(
     input: *const c_char,
     input_len: usize,
     options: ShelterParseOptions,
+) -> *mut ShelterResult {
+    shelter_parse_with(input, input_len, options, ptr::null())
+}
+
+/// Parse EDF content and analyse each entry
+///
+/// Same as `shelter_parse`, plus the per-entry metadata requested in
+/// `analysis` (null = none).
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string
+/// - `input_len` must be the exact length of the string
+/// - `analysis` must be null or point to a valid `ShelterAnalysisOptions`
+/// - Caller must free the result using `shelter_free_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_parse_with(
+    input: *const c_char,
+    input_len: usize,
+    options: ShelterParseOptions,
+    analysis: *const ShelterAnalysisOptions,
 ) -> *mut ShelterResult {
     // Validate input
     if input.is_null() {
@@ -50,52 +89,199 @@ pub unsafe extern "C" fn shelter_parse(
         Err(e) => return ShelterResult::err(&format!("Invalid UTF-8: {}", e)),
     };
 
-    // Parse using korni
-    let korni_opts = korni::ParseOptions::from(options);
-    let parsed_entries = korni::parse_with_options(input_str, korni_opts);
-
-    // Build line_starts array: indices where each line begins
-    // Pre-allocate with estimated capacity (avg line length ~30 chars)
-    let estimated_lines = input_len / 30 + 1;
-    let mut line_starts: Vec<usize> = Vec::with_capacity(estimated_lines);
-    line_starts.push(0); // Line 1 starts at offset 0
-
-    for (i, b) in input_str.bytes().enumerate() {
-        if b == b'\n' {
-            line_starts.push(i + 1);
+    let analysis = if analysis.is_null() {
+        ShelterAnalysisOptions::default()
+    } else {
+        *analysis
+    };
+    let fingerprint_key = bytes_from_raw(analysis.fingerprint_key, analysis.fingerprint_key_len);
+    let public_prefixes =
+        match PublicPrefixes::from_raw(analysis.public_prefixes, analysis.public_prefixes_len) {
+            Ok(prefixes) => prefixes,
+            Err(e) => return ShelterResult::err(&e),
+        };
+    let mode_names = if analysis.mode_names.is_null() {
+        None
+    } else {
+        match str_from_raw(analysis.mode_names, analysis.mode_names_len) {
+            Some(names) => Some(names.lines().map(str::trim).map(str::to_owned).collect()),
+            None => return ShelterResult::err("Mode names must be valid UTF-8"),
         }
-    }
-
-    // Convert entries - pre-allocate based on parsed count
-    let mut entries = Vec::with_capacity(parsed_entries.len());
+    };
+    let settings = if analysis.render.is_null() {
+        None
+    } else {
+        match RenderSettings::from_ffi(analysis.render) {
+            Ok(settings) => Some(settings),
+            Err(e) => return ShelterResult::err(&e),
+        }
+    };
 
-    for entry in parsed_entries {
-        match entry {
-            Entry::Pair(kv) => {
-                let line_number = kv
-                    .key_span
-                    .map(|s| offset_to_line_binary(&line_starts, s.start.offset))
-                    .unwrap_or(0);
+    // Parse using korni
+    let korni_opts = korni::ParseOptions::from(options);
+    let mut document = Document::parse_with_modes(input_str, korni_opts, mode_names);
 
-                // Calculate end line for multi-line values using binary search
-                let value_end_line = kv
-                    .value_span
-                    .map(|s| offset_to_line_binary(&line_starts, s.end.offset.saturating_sub(1)))
-                    .unwrap_or(line_number);
+    let now = if analysis.now != 0 {
+        analysis.now
+    } else {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64)
+    };
+    let window = match analysis.expiry_window_days {
+        0 => DEFAULT_EXPIRY_WINDOW_DAYS,
+        days => days,
+    };
+    let window = i64::try_from(window)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(86_400);
+    let min_secret_length = match analysis.min_secret_length {
+        0 => DEFAULT_MIN_SECRET_LENGTH,
+        length => length,
+    };
+    let mut diagnostics = std::mem::take(&mut document.diagnostics);
 
-                entries.push(ShelterEntry::from_korni(&kv, line_number, value_end_line));
+    // Convert entries (ids and line numbers were resolved by the document)
+    let entries = document
+        .pairs
+        .iter()
+        .enumerate()
+        .map(|(index, pair)| {
+            let mut entry = ShelterEntry::with_directive(
+                &pair.kv,
+                pair.line_number,
+                pair.value_end_line,
+                pair.id,
+                pair.directive.as_ref(),
+            );
+            if let Some(settings) = &settings {
+                let selection = settings.select(pair);
+                entry.masked_length = selection.mode.mask_cells(&pair.kv.value);
+                entry.policy_rule = selection.policy_rule.unwrap_or(usize::MAX);
+            }
+            if analysis.fingerprints != 0 {
+                entry.fingerprint = fingerprint(fingerprint_key, pair.kv.value.as_bytes());
+            }
+            if analysis.classify != 0 {
+                entry.set_classification(classify(&pair.kv.value).as_ref());
+            }
+            if analysis.entropy != 0 {
+                entry.set_randomness(&randomness(&pair.kv.value));
+            }
+            if analysis.value_types != 0 {
+                entry.value_type = infer_type(&pair.kv.value) as u8;
             }
-            Entry::Comment(_) => {
-                // Skip comments for now, we only care about key-value pairs
+            if analysis.payloads != 0 {
+                entry.set_payload(inspect(&pair.kv.value).as_ref());
             }
-            Entry::Error(_) => {
-                // Silently skip parse errors - expected during editing
+            if analysis.jwts != 0 {
+                let jwt = decode_jwt(&pair.kv.value);
+                if let Some(jwt) = &jwt {
+                    diagnostics.extend(expiry_diagnostic(
+                        jwt,
+                        now,
+                        pair.line_number,
+                        pair.value_range(),
+                        index,
+                    ));
+                }
+                entry.set_jwt(jwt.as_ref());
+            }
+            if analysis.pems != 0 {
+                let value = &*pair.kv.value;
+                let blocks = pem_blocks(value);
+                let (content_start, content_end) = pair.content_range();
+                let raw = input_str
+                    .get(content_start..content_end)
+                    .unwrap_or_default();
+                for block in &blocks {
+                    let Some(certificate) = block.certificate(value) else {
+                        continue;
+                    };
+                    // Report on the BEGIN marker, or the whole value when the
+                    // marker cannot be lined up with the raw text
+                    let (start, end) = match pair.raw_ranges(raw, &[block.begin]) {
+                        Some(ranges) => (content_start + ranges[0].0, content_start + ranges[0].1),
+                        None => pair.value_range(),
+                    };
+                    diagnostics.extend(certificate_diagnostic(
+                        &certificate,
+                        now,
+                        window,
+                        document.line_number(start),
+                        (start, end),
+                        Some(index),
+                    ));
+                }
+                entry.set_pem(Pem::from_blocks(value, &blocks).as_ref());
+            }
+            if analysis.exposure != 0 && !pair.kv.is_comment {
+                let key = pair.kv.key.as_ref();
+                let exposure = public_prefixes.exposure(key, &pair.kv.value);
+                if let (Exposure::Leaked(secret), Some(prefix)) =
+                    (exposure, public_prefixes.matching(key))
+                {
+                    diagnostics.push(leak_diagnostic(
+                        key,
+                        prefix,
+                        secret,
+                        pair.line_number,
+                        pair.key_range(),
+                        index,
+                    ));
+                }
+                entry.exposure = exposure.to_ffi() as u8;
+            }
+            if analysis.weak_values != 0 && !pair.kv.is_comment {
+                // Report on the value, or on the key when the value is empty
+                // or spans several lines
+                let range = match pair.value_range() {
+                    (start, end) if end > start && pair.value_end_line == pair.line_number => {
+                        (start, end)
+                    }
+                    _ => pair.key_range(),
+                };
+                diagnostics.extend(weak_value_diagnostic(
+                    &pair.kv.key,
+                    &pair.kv.value,
+                    min_secret_length,
+                    pair.line_number,
+                    range,
+                    index,
+                ));
+            }
+            entry
+        })
+        .collect();
+
+    let mut comment_keys = Vec::new();
+    if analysis.pems != 0 {
+        for block in comment_blocks(input_str, &document) {
+            if block.kind == ShelterPemKind::PrivateKey {
+                comment_keys.extend(block.body.iter().map(|&(start, end)| ShelterLineSpan {
+                    line_number: document.line_number(start),
+                    start,
+                    end,
+                }));
+            }
+            if let Some(certificate) = block.certificate(input_str) {
+                diagnostics.extend(certificate_diagnostic(
+                    &certificate,
+                    now,
+                    window,
+                    document.line_number(block.begin.0),
+                    block.begin,
+                    None,
+                ));
             }
         }
     }
 
+    diagnostics.sort_by_key(|d| (d.line_number, d.start));
+    let diagnostics = diagnostics.into_iter().map(Diagnostic::into_ffi).collect();
+
     // Return entries and line_starts together - Lua gets pre-computed offsets
-    ShelterResult::ok(entries, line_starts)
+    ShelterResult::ok(entries, document.line_starts, diagnostics, comment_keys)
 }
 
 /// Free a parse result
@@ -115,16 +301,55 @@ pub unsafe extern "C" fn shelter_free_result(result: *mut ShelterResult) {
     if !result.entries.is_null() && result.count > 0 {
         let entries = Vec::from_raw_parts(result.entries, result.count, result.count);
         for entry in entries {
-            // Free key and value strings
-            if !entry.key.is_null() {
-                drop(CString::from_raw(entry.key));
+            // Free key, value, directive, secret type and payload strings, JWT
+            // claims and PEM fields
+            for text in [
+                entry.key,
+                entry.value,
+                entry.directive_mode,
+                entry.secret_type,
+                entry.payload,
+            ] {
+                if !text.is_null() {
+                    drop(CString::from_raw(text));
+                }
             }
-            if !entry.value.is_null() {
-                drop(CString::from_raw(entry.value));
+            if !entry.jwt.is_null() {
+                let jwt = Box::from_raw(entry.jwt);
+                jwt.free_strings();
+            }
+            if !entry.pem.is_null() {
+                let pem = Box::from_raw(entry.pem);
+                pem.free_strings();
             }
         }
     }
 
+    // Free diagnostics
+    if !result.diagnostics.is_null() && result.diagnostic_count > 0 {
+        let diagnostics = Vec::from_raw_parts(
+            result.diagnostics,
+            result.diagnostic_count,
+            result.diagnostic_count,
+        );
+        for diagnostic in diagnostics {
+            for text in [diagnostic.code, diagnostic.message] {
+                if !text.is_null() {
+                    drop(CString::from_raw(text));
+                }
+            }
+        }
+    }
+
+    // Free comment key spans
+    if !result.comment_keys.is_null() && result.comment_key_count > 0 {
+        drop(Vec::from_raw_parts(
+            result.comment_keys,
+            result.comment_key_count,
+            result.comment_key_count,
+        ));
+    }
+
     // Free line_offsets array
     if !result.line_offsets.is_null() && result.line_count > 0 {
         drop(Vec::from_raw_parts(
@@ -140,6 +365,956 @@ pub unsafe extern "C" fn shelter_free_result(result: *mut ShelterResult) {
     }
 }
 
+// =============================================================================
+//  Diffing Functions
+// =============================================================================
+
+/// View the entries and line offsets of a parse result
+///
+/// # Safety
+/// `result` must be a valid, non-null pointer returned by `shelter_parse`
+#[inline]
+unsafe fn result_parts<'a>(result: *const ShelterResult) -> (&'a [ShelterEntry], &'a [usize]) {
+    let result = &*result;
+    let entries = if result.entries.is_null() {
+        &[][..]
+    } else {
+        slice::from_raw_parts(result.entries, result.count)
+    };
+    let line_offsets = if result.line_offsets.is_null() {
+        &[][..]
+    } else {
+        slice::from_raw_parts(result.line_offsets, result.line_count)
+    };
+    (entries, line_offsets)
+}
+
+/// Diff two parse results by stable entry id
+///
+/// Reports added, removed, moved (line changed) and value-changed entries.
+/// Unchanged entries are omitted.
+///
+/// # Safety
+/// - `old` and `new` must be valid pointers returned by `shelter_parse`
+/// - Caller must free the result using `shelter_free_diff`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_diff_results(
+    old: *const ShelterResult,
+    new: *const ShelterResult,
+) -> *mut ShelterDiff {
+    if old.is_null() || new.is_null() {
+        return ShelterDiff::err("Result is null");
+    }
+
+    let (old_entries, old_offsets) = result_parts(old);
+    let (new_entries, new_offsets) = result_parts(new);
+
+    ShelterDiff::ok(diff_entries(
+        old_entries,
+        old_offsets,
+        new_entries,
+        new_offsets,
+    ))
+}
+
+/// Free a diff result
+///
+/// # Safety
+/// - `diff` must be a valid pointer returned by `shelter_diff_results`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_diff(diff: *mut ShelterDiff) {
+    if diff.is_null() {
+        return;
+    }
+
+    let diff = Box::from_raw(diff);
+
+    if !diff.changes.is_null() && diff.count > 0 {
+        drop(Vec::from_raw_parts(diff.changes, diff.count, diff.count));
+    }
+
+    if !diff.error.is_null() {
+        drop(CString::from_raw(diff.error));
+    }
+}
+
+// =============================================================================
+//  Masking Functions
+// =============================================================================
+
+/// Borrow a UTF-8 string from a pointer and length (null is treated as empty)
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes
+#[inline]
+pub(crate) unsafe fn str_from_raw<'a>(ptr: *const c_char, len: usize) -> Option<&'a str> {
+    if ptr.is_null() {
+        return Some("");
+    }
+    std::str::from_utf8(slice::from_raw_parts(ptr as *const u8, len)).ok()
+}
+
+/// Byte slice from a raw pointer and length (null = empty)
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes
+#[inline]
+pub(crate) unsafe fn bytes_from_raw<'a>(ptr: *const c_char, len: usize) -> &'a [u8] {
+    if ptr.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr as *const u8, len)
+    }
+}
+
+/// Create a mode table containing the built-in modes (full, partial, none,
+/// fingerprint, smart, structured)
+///
+/// Caller must free the table using `shelter_mode_table_free`.
+#[no_mangle]
+pub extern "C" fn shelter_mode_table_new() -> *mut ShelterModeTable {
+    Box::into_raw(Box::default())
+}
+
+/// Register or replace a mode in a mode table
+///
+/// Returns 1 on success, 0 if the arguments or options are invalid.
+///
+/// # Safety
+/// - `table` must be a valid pointer returned by `shelter_mode_table_new`
+/// - `name` must be valid for `name_len` bytes
+/// - `options` must point to a valid `ShelterModeOptions`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mode_table_set(
+    table: *mut ShelterModeTable,
+    name: *const c_char,
+    name_len: usize,
+    options: *const ShelterModeOptions,
+) -> u8 {
+    if table.is_null() || name.is_null() || options.is_null() {
+        return 0;
+    }
+    let Some(name) = str_from_raw(name, name_len) else {
+        return 0;
+    };
+    match Mode::from_ffi(&*options) {
+        Ok(mode) => {
+            (*table).set(name, mode);
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Set the key used by keyed lengths and fingerprints in a mode table
+///
+/// The key applies to every mode in the table, including ones added later.
+///
+/// # Safety
+/// - `table` must be a valid pointer returned by `shelter_mode_table_new`
+/// - `key` must be null (empty key) or valid for `key_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mode_table_set_key(
+    table: *mut ShelterModeTable,
+    key: *const c_char,
+    key_len: usize,
+) {
+    if table.is_null() {
+        return;
+    }
+    (*table).set_key(bytes_from_raw(key, key_len));
+}
+
+/// Free a mode table
+///
+/// # Safety
+/// - `table` must be a valid pointer returned by `shelter_mode_table_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mode_table_free(table: *mut ShelterModeTable) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+/// Mask a value with a named mode from a mode table
+///
+/// Returns null if the table or mode is missing, the input is not UTF-8 or
+/// the masked value holds a NUL byte.
+///
+/// # Safety
+/// - `table` must be a valid pointer returned by `shelter_mode_table_new`
+/// - `mode` and `value` must be valid for their lengths
+/// - Caller must free the returned string using `shelter_free_string`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mask_value(
+    table: *const ShelterModeTable,
+    mode: *const c_char,
+    mode_len: usize,
+    value: *const c_char,
+    value_len: usize,
+) -> *mut c_char {
+    if table.is_null() {
+        return ptr::null_mut();
+    }
+    let (Some(mode), Some(value)) = (str_from_raw(mode, mode_len), str_from_raw(value, value_len))
+    else {
+        return ptr::null_mut();
+    };
+    match (*table)
+        .get(mode)
+        .map(|mode| CString::new(mode.apply(value)))
+    {
+        Some(Ok(masked)) => masked.into_raw(),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Mask a value with a named mode, revealing part of it
+///
+/// Same as `shelter_mask_value`; `reveal` (null = hidden) selects how much of
+/// the value to show. Its `id` is ignored. Also returns null for an unknown
+/// reveal kind.
+///
+/// # Safety
+/// - Same as `shelter_mask_value`
+/// - `reveal` must be null or point to a valid `ShelterReveal`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mask_value_revealed(
+    table: *const ShelterModeTable,
+    mode: *const c_char,
+    mode_len: usize,
+    value: *const c_char,
+    value_len: usize,
+    reveal: *const ShelterReveal,
+) -> *mut c_char {
+    if table.is_null() {
+        return ptr::null_mut();
+    }
+    let (Some(mode), Some(value)) = (str_from_raw(mode, mode_len), str_from_raw(value, value_len))
+    else {
+        return ptr::null_mut();
+    };
+    let reveal = if reveal.is_null() {
+        Reveal::Hidden
+    } else {
+        match Reveal::from_ffi(&*reveal) {
+            Ok(reveal) => reveal,
+            Err(_) => return ptr::null_mut(),
+        }
+    };
+    match (*table)
+        .get(mode)
+        .map(|mode| CString::new(reveal.apply(mode, value)))
+    {
+        Some(Ok(masked)) => masked.into_raw(),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Find the credentials of a URL, connection string or JSON value
+///
+/// Returns the byte ranges the `structured` mode masks with its default
+/// options: URL passwords and token userinfo, secret query parameters,
+/// `Key=Value;` properties and JSON strings under secret keys (named by their
+/// key path). Other values come back with kind `None` and no spans.
+///
+/// # Safety
+/// - `value` must be valid for `value_len` bytes
+/// - Caller must free the result using `shelter_free_secret_spans`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_secret_spans(
+    value: *const c_char,
+    value_len: usize,
+) -> *mut ShelterSecretSpans {
+    match str_from_raw(value, value_len) {
+        Some(value) => ShelterSecretSpans::ok(structure(value)),
+        None => ShelterSecretSpans::err("Value is not valid UTF-8"),
+    }
+}
+
+/// Free a secret span result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_secret_spans`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_secret_spans(result: *mut ShelterSecretSpans) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    if !result.spans.is_null() && result.count > 0 {
+        let spans = Vec::from_raw_parts(result.spans, result.count, result.count);
+        for span in spans {
+            if !span.component.is_null() {
+                drop(CString::from_raw(span.component));
+            }
+        }
+    }
+
+    if !result.error.is_null() {
+        drop(CString::from_raw(result.error));
+    }
+}
+
+/// Inspect an encoded value
+///
+/// Detects base64 and base64url values, decodes them in memory and reports
+/// what they carry (see `ShelterPayload`). The decoded bytes are returned only
+/// when `include_decoded` is non-zero; values that are not encoded come back
+/// with a null summary.
+///
+/// # Safety
+/// - `value` must be valid for `value_len` bytes
+/// - Caller must free the result using `shelter_free_payload`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_inspect_payload(
+    value: *const c_char,
+    value_len: usize,
+    include_decoded: u8,
+) -> *mut ShelterPayload {
+    match str_from_raw(value, value_len) {
+        Some(value) => ShelterPayload::ok(inspect(value), include_decoded != 0),
+        None => ShelterPayload::err("Value is not valid UTF-8"),
+    }
+}
+
+/// Free a payload result, wiping the decoded bytes
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_inspect_payload`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_payload(result: *mut ShelterPayload) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    if !result.decoded.is_null() {
+        let mut decoded = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            result.decoded,
+            result.decoded_len,
+        ));
+        for byte in decoded.iter_mut() {
+            ptr::write_volatile(byte, 0);
+        }
+    }
+
+    for text in [result.summary, result.secret, result.error] {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+}
+
+/// Free a string returned by shelter-core
+///
+/// # Safety
+/// - `s` must be null or a string returned by a shelter-core function
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+// =============================================================================
+//  Fingerprint Functions
+// =============================================================================
+
+/// Keyed fingerprint of a value
+///
+/// Equal values under the same key have equal fingerprints, so secrets can be
+/// compared without revealing them. Display the leading hex digits of the
+/// big-endian value (e.g. `a1f3`).
+///
+/// # Safety
+/// - `value` must be null (empty value) or valid for `value_len` bytes
+/// - `key` must be null (empty key) or valid for `key_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_fingerprint(
+    value: *const c_char,
+    value_len: usize,
+    key: *const c_char,
+    key_len: usize,
+) -> u64 {
+    fingerprint(
+        bytes_from_raw(key, key_len),
+        bytes_from_raw(value, value_len),
+    )
+}
+
+// =============================================================================
+//  Pattern Set Functions
+// =============================================================================
+
+/// Convert FFI pattern options (null = defaults)
+///
+/// # Safety
+/// `options` must be null or point to a valid `ShelterPatternOptions`
+unsafe fn pattern_options(options: *const ShelterPatternOptions) -> PatternOptions {
+    let options = if options.is_null() {
+        ShelterPatternOptions::default()
+    } else {
+        *options
+    };
+    PatternOptions {
+        case_insensitive: options.case_insensitive != 0,
+        paths: options.paths != 0,
+    }
+}
+
+/// Compile glob patterns into a set for repeated matching
+///
+/// All patterns are matched in a single pass. When several match, the most
+/// specific wins, then the one with fewer `*` wildcards, then the earlier one.
+/// Returns null if a pattern is invalid (see `shelter_pattern_validate`).
+///
+/// # Safety
+/// - `rules` must be null or valid for `count` rules with valid strings
+/// - `options` must be null (defaults) or point to a valid
+///   `ShelterPatternOptions`
+/// - Caller must free the set using `shelter_pattern_set_free`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_new(
+    rules: *const ShelterPatternRule,
+    count: usize,
+    options: *const ShelterPatternOptions,
+) -> *mut ShelterPatternSet {
+    match rules_from_ffi(rules, count, pattern_options(options)) {
+        Ok(set) => Box::into_raw(Box::new(set)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Index of the winning rule for `text`
+///
+/// Returns `usize::MAX` if no pattern matches or the input is invalid.
+///
+/// # Safety
+/// - `set` must be a valid pointer returned by `shelter_pattern_set_new`
+/// - `text` must be null (empty text) or valid for `text_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_match(
+    set: *const ShelterPatternSet,
+    text: *const c_char,
+    text_len: usize,
+) -> usize {
+    if set.is_null() {
+        return usize::MAX;
+    }
+    str_from_raw(text, text_len)
+        .and_then(|text| (*set).best_index(text))
+        .unwrap_or(usize::MAX)
+}
+
+/// Check a glob pattern
+///
+/// Returns null if the pattern is valid, otherwise an error message.
+///
+/// # Safety
+/// - `pattern` must be valid for `pattern_len` bytes
+/// - `options` must be null (defaults) or point to a valid
+///   `ShelterPatternOptions`
+/// - Caller must free the returned string using `shelter_free_string`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_validate(
+    pattern: *const c_char,
+    pattern_len: usize,
+    options: *const ShelterPatternOptions,
+) -> *mut c_char {
+    let result = match str_from_raw(pattern, pattern_len) {
+        Some(pattern) => ShelterPatternSet::validate(pattern, pattern_options(options)),
+        None => Err("Pattern is not valid UTF-8".to_owned()),
+    };
+    match result {
+        Ok(()) => ptr::null_mut(),
+        Err(e) => CString::new(e).unwrap_or_default().into_raw(),
+    }
+}
+
+/// Free a pattern set
+///
+/// # Safety
+/// - `set` must be a valid pointer returned by `shelter_pattern_set_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_free(set: *mut ShelterPatternSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+// =============================================================================
+//  Policy Functions
+// =============================================================================
+
+/// Compile policy rules for use in a `ShelterRenderConfig`
+///
+/// Rules are tried by descending priority, ties in the order given; the first
+/// whose conditions all hold chooses the entry's mode and highlight. Returns
+/// null if a rule is invalid (see `shelter_policy_validate`).
+///
+/// # Safety
+/// - `rules` must be null or valid for `count` rules with valid strings
+/// - Caller must free the policy using `shelter_policy_free`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_policy_new(
+    rules: *const ShelterPolicyRule,
+    count: usize,
+) -> *mut ShelterPolicy {
+    match ShelterPolicy::from_ffi(rules, count) {
+        Ok(policy) => Box::into_raw(Box::new(policy)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Check a policy rule
+///
+/// Returns null if the rule is valid, otherwise an error message.
+///
+/// # Safety
+/// - `rule` must point to a valid `ShelterPolicyRule`
+/// - Caller must free the returned string using `shelter_free_string`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_policy_validate(rule: *const ShelterPolicyRule) -> *mut c_char {
+    if rule.is_null() {
+        return CString::new("Rule is null").unwrap_or_default().into_raw();
+    }
+    match PolicyRule::from_ffi(&*rule) {
+        Ok(_) => ptr::null_mut(),
+        Err(e) => CString::new(e).unwrap_or_default().into_raw(),
+    }
+}
+
+/// Free a policy
+///
+/// # Safety
+/// - `policy` must be a valid pointer returned by `shelter_policy_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_policy_free(policy: *mut ShelterPolicy) {
+    if !policy.is_null() {
+        drop(Box::from_raw(policy));
+    }
+}
+
+// =============================================================================
+//  Rendering Functions
+// =============================================================================
+
+/// Parse content and build ready-to-apply overlays in a single call
+///
+/// Selects a mode per entry, applies it and splits multi-line values into
+/// per-line chunks. Quotes stay visible, and masks narrower than the value are
+/// padded with spaces so no value character shows through.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `config` must be null (defaults) or point to a valid `ShelterRenderConfig`
+/// - Caller must free the result using `shelter_free_render_plan`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_render_plan(
+    input: *const c_char,
+    input_len: usize,
+    config: *const ShelterRenderConfig,
+) -> *mut ShelterRenderPlan {
+    if input.is_null() {
+        return ShelterRenderPlan::err("Input is null");
+    }
+
+    let input_slice = slice::from_raw_parts(input as *const u8, input_len);
+    let input_str = match std::str::from_utf8(input_slice) {
+        Ok(s) => s,
+        Err(e) => return ShelterRenderPlan::err(&format!("Invalid UTF-8: {}", e)),
+    };
+
+    let settings = match RenderSettings::from_ffi(config) {
+        Ok(settings) => settings,
+        Err(e) => return ShelterRenderPlan::err(&e),
+    };
+
+    let document = Document::parse(input_str, korni::ParseOptions::full());
+    let plan = render_plan(input_str, &document, &settings);
+    // Overlay text is read back with its length, so it must not hold a NUL
+    if plan.iter().any(|overlay| overlay.text.contains('\0')) {
+        return ShelterRenderPlan::err("Overlay text contains a NUL byte");
+    }
+    let overlays = plan
+        .into_iter()
+        .map(|overlay| ShelterOverlay {
+            text_len: overlay.text.len(),
+            text: CString::new(overlay.text).unwrap_or_default().into_raw(),
+            row: overlay.row,
+            col: overlay.col,
+            end_col: overlay.end_col,
+            entry_index: overlay.entry_index,
+            hl_id: overlay.hl_id,
+            is_continuation: overlay.is_continuation as u8,
+        })
+        .collect();
+
+    ShelterRenderPlan::ok(overlays)
+}
+
+/// Free a render plan
+///
+/// # Safety
+/// - `plan` must be a valid pointer returned by `shelter_render_plan`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_render_plan(plan: *mut ShelterRenderPlan) {
+    if plan.is_null() {
+        return;
+    }
+
+    let plan = Box::from_raw(plan);
+
+    if !plan.overlays.is_null() && plan.count > 0 {
+        let overlays = Vec::from_raw_parts(plan.overlays, plan.count, plan.count);
+        for overlay in overlays {
+            if !overlay.text.is_null() {
+                drop(CString::from_raw(overlay.text));
+            }
+        }
+    }
+
+    if !plan.error.is_null() {
+        drop(CString::from_raw(plan.error));
+    }
+}
+
+/// Render a fully masked copy of a document
+///
+/// Every value is replaced according to its mode; comments, quotes, `export`
+/// prefixes and line structure are preserved byte-for-byte. The returned spans
+/// map replaced values back to their original offsets.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `config` must be null (defaults) or point to a valid `ShelterRenderConfig`
+/// - Caller must free the result using `shelter_free_masked_document`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_render_masked(
+    input: *const c_char,
+    input_len: usize,
+    config: *const ShelterRenderConfig,
+) -> *mut ShelterMaskedDocument {
+    if input.is_null() {
+        return ShelterMaskedDocument::err("Input is null");
+    }
+
+    let input_slice = slice::from_raw_parts(input as *const u8, input_len);
+    let input_str = match std::str::from_utf8(input_slice) {
+        Ok(s) => s,
+        Err(e) => return ShelterMaskedDocument::err(&format!("Invalid UTF-8: {}", e)),
+    };
+
+    let settings = match RenderSettings::from_ffi(config) {
+        Ok(settings) => settings,
+        Err(e) => return ShelterMaskedDocument::err(&e),
+    };
+
+    let document = Document::parse(input_str, korni::ParseOptions::full());
+    let (text, spans) = render_masked(input_str, &document, &settings);
+    ShelterMaskedDocument::ok(text, spans)
+}
+
+/// Free a masked document
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_render_masked`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_masked_document(doc: *mut ShelterMaskedDocument) {
+    if doc.is_null() {
+        return;
+    }
+
+    let doc = Box::from_raw(doc);
+
+    if !doc.text.is_null() {
+        drop(CString::from_raw(doc.text));
+    }
+
+    if !doc.spans.is_null() && doc.span_count > 0 {
+        drop(Vec::from_raw_parts(
+            doc.spans,
+            doc.span_count,
+            doc.span_count,
+        ));
+    }
+
+    if !doc.error.is_null() {
+        drop(CString::from_raw(doc.error));
+    }
+}
+
+// =============================================================================
+//  Verification Functions
+// =============================================================================
+
+/// Check planned overlays for secret bytes that would remain visible
+///
+/// Simulates each displayed line (tabs expand to `tabstop`, or the config's tab
+/// width when 0; wide characters take two cells) with the overlays drawn on
+/// top, and reports every visible byte of a value whose mode hides it. Mode
+/// selection follows `config`, so pass the same config used to build the
+/// overlays.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `overlays` must be null or valid for `overlay_count` elements with valid
+///   text
+/// - `config` must be null (defaults) or point to a valid `ShelterRenderConfig`
+/// - Caller must free the result using `shelter_free_leak_report`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_verify_overlays(
+    input: *const c_char,
+    input_len: usize,
+    overlays: *const ShelterOverlay,
+    overlay_count: usize,
+    config: *const ShelterRenderConfig,
+    tabstop: usize,
+) -> *mut ShelterLeakReport {
+    if input.is_null() {
+        return ShelterLeakReport::err("Input is null");
+    }
+
+    let input_slice = slice::from_raw_parts(input as *const u8, input_len);
+    let input_str = match std::str::from_utf8(input_slice) {
+        Ok(s) => s,
+        Err(e) => return ShelterLeakReport::err(&format!("Invalid UTF-8: {}", e)),
+    };
+
+    let settings = match RenderSettings::from_ffi(config) {
+        Ok(settings) => settings,
+        Err(e) => return ShelterLeakReport::err(&e),
+    };
+
+    let raw_overlays = if overlays.is_null() || overlay_count == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(overlays, overlay_count)
+    };
+    let mut placed = Vec::with_capacity(raw_overlays.len());
+    for overlay in raw_overlays {
+        let Some(text) = str_from_raw(overlay.text, overlay.text_len) else {
+            return ShelterLeakReport::err("Overlay text is not valid UTF-8");
+        };
+        placed.push(PlacedOverlay {
+            row: overlay.row,
+            col: overlay.col,
+            text,
+        });
+    }
+
+    let tabstop = if tabstop == 0 {
+        settings.tabstop
+    } else {
+        tabstop
+    };
+
+    let document = Document::parse(input_str, korni::ParseOptions::full());
+    let leaks = verify_overlays(input_str, &document, &settings, &placed, tabstop);
+    ShelterLeakReport::ok(leaks)
+}
+
+/// Free a leak report
+///
+/// # Safety
+/// - `report` must be a valid pointer returned by `shelter_verify_overlays`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_leak_report(report: *mut ShelterLeakReport) {
+    if report.is_null() {
+        return;
+    }
+
+    let report = Box::from_raw(report);
+
+    if !report.leaks.is_null() && report.count > 0 {
+        drop(Vec::from_raw_parts(
+            report.leaks,
+            report.count,
+            report.count,
+        ));
+    }
+
+    if !report.error.is_null() {
+        drop(CString::from_raw(report.error));
+    }
+}
+
+/// Check a candidate against the value of `key` without revealing it
+///
+/// Compares against the last uncommented entry with that key, in time that
+/// does not depend on where the strings differ. The shared prefix length is
+/// only reported when `policy` allows it.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `key` and `candidate` must be valid UTF-8 for their lengths
+/// - `policy` must be null (disclose nothing extra) or point to a valid
+///   `ShelterVerifyPolicy`
+/// - Caller must free the result using `shelter_free_verify_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_verify_value(
+    input: *const c_char,
+    input_len: usize,
+    key: *const c_char,
+    key_len: usize,
+    candidate: *const c_char,
+    candidate_len: usize,
+    policy: *const ShelterVerifyPolicy,
+) -> *mut ShelterVerifyResult {
+    if input.is_null() || key.is_null() {
+        return ShelterVerifyResult::err("Input is null");
+    }
+    let Some(input_str) = str_from_raw(input, input_len) else {
+        return ShelterVerifyResult::err("Input is not valid UTF-8");
+    };
+    let (Some(key), Some(candidate)) = (
+        str_from_raw(key, key_len),
+        str_from_raw(candidate, candidate_len),
+    ) else {
+        return ShelterVerifyResult::err("Key or candidate is not valid UTF-8");
+    };
+    let policy = if policy.is_null() {
+        ShelterVerifyPolicy::default()
+    } else {
+        *policy
+    };
+
+    let document = Document::parse(input_str, korni::ParseOptions::full());
+    match compare_value(&document, key, candidate, policy.report_prefix != 0) {
+        Some(c) => ShelterVerifyResult::ok(Some(c.entry_index), c.matched, c.prefix_length),
+        None => ShelterVerifyResult::ok(None, false, None),
+    }
+}
+
+/// Free a verify result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_verify_value`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_verify_result(result: *mut ShelterVerifyResult) {
+    if result.is_null() {
+        return;
+    }
+    let result = Box::from_raw(result);
+    if !result.error.is_null() {
+        drop(CString::from_raw(result.error));
+    }
+}
+
+// =============================================================================
+//  Explain Functions
+// =============================================================================
+
+/// Trace why an entry gets its mode
+///
+/// Evaluates every policy rule, key pattern and source pattern against the
+/// entry and reports each one with whether it matched and its priority or
+/// specificity, the deciding step, and the classifier input (secret type and
+/// entropy) that policy conditions see.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `config` must be null (defaults) or point to a valid `ShelterRenderConfig`
+/// - Caller must free the result using `shelter_free_explanation`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_explain(
+    input: *const c_char,
+    input_len: usize,
+    entry_index: usize,
+    config: *const ShelterRenderConfig,
+) -> *mut ShelterExplanation {
+    if input.is_null() {
+        return ShelterExplanation::err("Input is null");
+    }
+    let Some(input_str) = str_from_raw(input, input_len) else {
+        return ShelterExplanation::err("Input is not valid UTF-8");
+    };
+    let settings = match RenderSettings::from_ffi(config) {
+        Ok(settings) => settings,
+        Err(e) => return ShelterExplanation::err(&e),
+    };
+
+    let document = Document::parse(input_str, korni::ParseOptions::full());
+    let Some(pair) = document.pairs.get(entry_index) else {
+        return ShelterExplanation::err("Entry index out of range");
+    };
+
+    let explanation = explain(pair, &settings);
+    let c_string = |s: &str| CString::new(s).unwrap_or_default().into_raw();
+    let steps = explanation
+        .steps
+        .iter()
+        .map(|step| ShelterTraceStep {
+            rule: c_string(&step.rule),
+            mode: c_string(&step.mode),
+            detail: step.detail.map_or(ptr::null_mut(), c_string),
+            rule_index: step.rule_index.unwrap_or(usize::MAX),
+            rank: step.rank,
+            stage: step.stage as u8,
+            matched: step.matched as u8,
+            chosen: step.chosen as u8,
+        })
+        .collect();
+
+    ShelterExplanation::ok(
+        steps,
+        &explanation.mode,
+        explanation.stage,
+        explanation.unknown_mode,
+        explanation.classification.map(|c| c.id),
+        explanation
+            .classification
+            .map_or(ShelterConfidence::None, |c| c.confidence),
+        &explanation.randomness,
+    )
+}
+
+/// Free an explanation
+///
+/// # Safety
+/// - `explanation` must be a valid pointer returned by `shelter_explain`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_explanation(explanation: *mut ShelterExplanation) {
+    if explanation.is_null() {
+        return;
+    }
+
+    let explanation = Box::from_raw(explanation);
+
+    if !explanation.steps.is_null() && explanation.count > 0 {
+        let steps = Vec::from_raw_parts(explanation.steps, explanation.count, explanation.count);
+        for step in steps {
+            for text in [step.rule, step.mode, step.detail] {
+                if !text.is_null() {
+                    drop(CString::from_raw(text));
+                }
+            }
+        }
+    }
+
+    for text in [explanation.mode, explanation.secret_type, explanation.error] {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+}
+
 // =============================================================================
 //  Utility Functions
 // =============================================================================
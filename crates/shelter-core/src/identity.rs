@@ -0,0 +1,134 @@
+//! Stable entry identities and result diffing
+//!
+//! An entry's identity is derived from its key, whether it lives in a comment,
+//! and its occurrence index among entries sharing that key. Edits elsewhere in
+//! the file therefore never change it, which lets callers diff two parse
+//! results and touch only the entries that actually changed.
+
+use crate::types::{ShelterChange, ShelterChangeKind, ShelterEntry};
+use std::collections::HashMap;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// FNV-1a over a sequence of byte chunks
+#[inline]
+fn fnv1a(chunks: &[&[u8]]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for chunk in chunks {
+        for &b in *chunk {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+/// Compute the stable id for the `occurrence`-th entry (0-based) with `key`
+#[inline]
+pub fn entry_id(key: &str, occurrence: usize, is_comment: bool) -> u64 {
+    let occurrence = (occurrence as u64).to_le_bytes();
+    let flag = [is_comment as u8];
+    fnv1a(&[key.as_bytes(), &[0], &flag, &occurrence])
+}
+
+/// Assigns ids to entries in document order, counting key occurrences
+#[derive(Default)]
+pub(crate) struct IdAssigner {
+    seen: HashMap<(String, bool), usize>,
+}
+
+impl IdAssigner {
+    /// Id for the next entry with `key`
+    pub(crate) fn next(&mut self, key: &str, is_comment: bool) -> u64 {
+        let occurrence = self.seen.entry((key.to_owned(), is_comment)).or_insert(0);
+        let id = entry_id(key, *occurrence, is_comment);
+        *occurrence += 1;
+        id
+    }
+}
+
+/// Column of the value start within its line
+#[inline]
+fn value_column(entry: &ShelterEntry, line_offsets: &[usize]) -> usize {
+    let line_start = entry
+        .line_number
+        .checked_sub(1)
+        .and_then(|i| line_offsets.get(i))
+        .copied()
+        .unwrap_or(0);
+    entry.value_start.saturating_sub(line_start)
+}
+
+/// Diff two entry lists by stable id
+///
+/// Unchanged entries are omitted. An entry whose value, quoting, value column,
+/// line span or directive mode changed is reported as `ValueChanged`, even if
+/// it also moved.
+///
+/// # Safety
+/// Entry string pointers must be valid (as produced by `shelter_parse`).
+pub(crate) unsafe fn diff_entries(
+    old: &[ShelterEntry],
+    old_offsets: &[usize],
+    new: &[ShelterEntry],
+    new_offsets: &[usize],
+) -> Vec<ShelterChange> {
+    let mut old_by_id: HashMap<u64, usize> = HashMap::with_capacity(old.len());
+    for (i, entry) in old.iter().enumerate() {
+        old_by_id.entry(entry.id).or_insert(i);
+    }
+
+    let mut matched = vec![false; old.len()];
+    let mut changes = Vec::new();
+    let mut updates = Vec::new();
+
+    for (new_index, entry) in new.iter().enumerate() {
+        let Some(&old_index) = old_by_id.get(&entry.id) else {
+            updates.push(ShelterChange {
+                id: entry.id,
+                old_index: usize::MAX,
+                new_index,
+                kind: ShelterChangeKind::Added as u8,
+            });
+            continue;
+        };
+        matched[old_index] = true;
+
+        let prev = &old[old_index];
+        let value_changed = prev.value_bytes() != entry.value_bytes()
+            || prev.quote_type != entry.quote_type
+            || value_column(prev, old_offsets) != value_column(entry, new_offsets)
+            || prev.value_end_line - prev.line_number != entry.value_end_line - entry.line_number
+            || prev.directive_bytes() != entry.directive_bytes();
+
+        let kind = if value_changed {
+            ShelterChangeKind::ValueChanged
+        } else if prev.line_number != entry.line_number {
+            ShelterChangeKind::Moved
+        } else {
+            continue;
+        };
+
+        updates.push(ShelterChange {
+            id: entry.id,
+            old_index,
+            new_index,
+            kind: kind as u8,
+        });
+    }
+
+    for (old_index, entry) in old.iter().enumerate() {
+        if !matched[old_index] {
+            changes.push(ShelterChange {
+                id: entry.id,
+                old_index,
+                new_index: usize::MAX,
+                kind: ShelterChangeKind::Removed as u8,
+            });
+        }
+    }
+
+    changes.extend(updates);
+    changes
+}
@@ -0,0 +1,184 @@
+//! Value type inference
+//!
+//! Guesses what kind of value an entry holds from its text alone, trying the
+//! most specific types first: booleans and numbers, UUIDs and durations, PEM
+//! blocks and JSON documents, emails, connection strings, URLs, paths and
+//! base64 blobs. Anything else is opaque.
+
+use crate::json::is_json_document;
+use crate::smart::{is_path, BOOLEANS};
+use crate::structured::split_unquoted;
+use crate::types::ShelterValueType;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// URI schemes of databases, caches and message brokers
+const CONNECTION_SCHEMES: &[&str] = &[
+    "postgres",
+    "postgresql",
+    "mysql",
+    "mariadb",
+    "mongodb",
+    "mongodb+srv",
+    "redis",
+    "rediss",
+    "amqp",
+    "amqps",
+    "mssql",
+    "sqlserver",
+    "oracle",
+    "sqlite",
+    "clickhouse",
+    "cassandra",
+    "couchbase",
+    "neo4j",
+    "nats",
+    "kafka",
+    "memcached",
+];
+
+/// Keys that mark a `Key=Value;` string as a connection string
+const CONNECTION_KEYS: &[&str] = &[
+    "server",
+    "data source",
+    "host",
+    "hostname",
+    "address",
+    "database",
+    "initial catalog",
+    "user id",
+    "uid",
+    "username",
+    "password",
+    "pwd",
+    "port",
+    "endpoint",
+    "accountname",
+    "accountkey",
+    "sharedaccesskey",
+    "sharedaccesskeyname",
+    "driver",
+];
+
+/// Compiled shape regexes
+struct Shapes {
+    float: Regex,
+    uuid: Regex,
+    duration: Regex,
+    iso_duration: Regex,
+    email: Regex,
+    url: Regex,
+}
+
+fn shapes() -> &'static Shapes {
+    static SHAPES: OnceLock<Shapes> = OnceLock::new();
+    SHAPES.get_or_init(|| {
+        let regex = |pattern| Regex::new(pattern).expect("valid value shape");
+        Shapes {
+            float: regex(r"^[+-]?(\d+\.\d*|\.\d+|\d+(\.\d*)?[eE][+-]?\d+)$"),
+            uuid: regex(
+                r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$",
+            ),
+            duration: regex(r"^(\d+(\.\d+)?(ns|us|µs|ms|s|m|h|d|w))+$"),
+            iso_duration: regex(
+                r"^P(\d+Y)?(\d+M)?(\d+W)?(\d+D)?(T(\d+H)?(\d+M)?(\d+(\.\d+)?S)?)?$",
+            ),
+            email: regex(r"^[A-Za-z0-9.!#$%&'*+=?^_`{|}~-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+$"),
+            url: regex(r"^[A-Za-z][A-Za-z0-9+.-]*://\S+$"),
+        }
+    })
+}
+
+/// Optionally signed digits
+fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// ISO 8601 duration with at least one component
+fn is_iso_duration(value: &str) -> bool {
+    value.len() > 1 && !value.ends_with('T') && shapes().iso_duration.is_match(value)
+}
+
+/// `Key=Value;` pairs with at least one well-known connection key
+fn is_key_value_connection(value: &str) -> bool {
+    let pairs: Vec<_> = split_unquoted(value, &[';'])
+        .into_iter()
+        .filter(|(_, part)| !part.trim().is_empty())
+        .map(|(_, part)| part.split_once('='))
+        .collect();
+    pairs.len() >= 2
+        && pairs.iter().all(Option::is_some)
+        && pairs.iter().flatten().any(|(key, _)| {
+            let key = key.trim();
+            CONNECTION_KEYS
+                .iter()
+                .any(|known| key.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Database or broker URI, JDBC URL or `Key=Value;` connection string
+fn is_connection_string(value: &str) -> bool {
+    if value.len() > 5
+        && value
+            .get(..5)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("jdbc:"))
+    {
+        return true;
+    }
+    if let Some((scheme, _)) = value.split_once("://") {
+        return CONNECTION_SCHEMES
+            .iter()
+            .any(|known| scheme.eq_ignore_ascii_case(known));
+    }
+    is_key_value_connection(value)
+}
+
+/// Standard base64 of at least 16 characters, padded to a multiple of 4, that
+/// uses `+`, `/` or padding or is long enough not to be a plain word or token
+fn is_base64(value: &str) -> bool {
+    let body = value.trim_end_matches('=');
+    let padding = value.len() - body.len();
+    value.len() >= 16
+        && value.len().is_multiple_of(4)
+        && padding <= 2
+        && body
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/'))
+        && !body.bytes().all(|b| b.is_ascii_hexdigit())
+        && (padding > 0 || body.contains(['+', '/']) || value.len() >= 64)
+}
+
+/// Infer the type of a value
+pub(crate) fn infer_type(value: &str) -> ShelterValueType {
+    let shapes = shapes();
+    if value.is_empty() {
+        ShelterValueType::None
+    } else if BOOLEANS.iter().any(|word| value.eq_ignore_ascii_case(word)) {
+        ShelterValueType::Boolean
+    } else if is_integer(value) {
+        ShelterValueType::Integer
+    } else if shapes.float.is_match(value) {
+        ShelterValueType::Float
+    } else if shapes.uuid.is_match(value) {
+        ShelterValueType::Uuid
+    } else if shapes.duration.is_match(value) || is_iso_duration(value) {
+        ShelterValueType::Duration
+    } else if value.trim_start().starts_with("-----BEGIN ") {
+        ShelterValueType::Pem
+    } else if is_json_document(value) {
+        ShelterValueType::Json
+    } else if shapes.email.is_match(value) {
+        ShelterValueType::Email
+    } else if is_connection_string(value) {
+        ShelterValueType::ConnectionString
+    } else if shapes.url.is_match(value) {
+        ShelterValueType::Url
+    } else if is_path(value) {
+        ShelterValueType::Path
+    } else if is_base64(value) {
+        ShelterValueType::Base64
+    } else {
+        ShelterValueType::Opaque
+    }
+}
@@ -0,0 +1,304 @@
+//! JSON recognition
+//!
+//! A small recursive-descent reader over the value bytes (RFC 8259). Nesting is
+//! limited so hostile values cannot exhaust the stack.
+//!
+//! The reader can also collect the scalar leaves of a document with their key
+//! paths (`credentials.private_key`, `keys[0].secret`), which the structured
+//! mode uses to mask single JSON values while keys and braces stay readable.
+
+/// Deepest nesting accepted
+const MAX_DEPTH: usize = 128;
+
+/// What a leaf holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LeafKind {
+    String,
+    Number,
+    /// `true`, `false` or `null`
+    Literal,
+}
+
+/// A scalar inside a JSON document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Leaf {
+    /// Byte range of the leaf (inside the quotes for strings)
+    pub start: usize,
+    pub end: usize,
+    /// Object keys joined by `.`, array elements as `[index]`
+    pub path: String,
+    pub kind: LeafKind,
+}
+
+/// Reader position over a JSON text
+struct Reader<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+    /// Key path of the current value (only kept while collecting leaves)
+    path: String,
+    /// Leaves read so far, if collecting
+    leaves: Option<Vec<Leaf>>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(text: &'a str, collect: bool) -> Self {
+        Self {
+            text,
+            bytes: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+            path: String::new(),
+            leaves: collect.then(Vec::new),
+        }
+    }
+
+    /// Record a leaf at `start..end` when collecting
+    fn leaf(&mut self, start: usize, end: usize, kind: LeafKind) {
+        if let Some(leaves) = &mut self.leaves {
+            leaves.push(Leaf {
+                start,
+                end,
+                path: self.path.clone(),
+                kind,
+            });
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn literal(&mut self, word: &[u8]) -> bool {
+        let found = self.bytes[self.pos..].starts_with(word);
+        if found {
+            self.pos += word.len();
+        }
+        found
+    }
+
+    fn digits(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn number(&mut self) -> bool {
+        self.eat(b'-');
+        if !self.eat(b'0') && !self.digits() {
+            return false;
+        }
+        if self.eat(b'.') && !self.digits() {
+            return false;
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            let _ = self.eat(b'+') || self.eat(b'-');
+            return self.digits();
+        }
+        true
+    }
+
+    fn string(&mut self) -> bool {
+        if !self.eat(b'"') {
+            return false;
+        }
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'"' => return true,
+                b'\\' => match self.peek() {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => self.pos += 1,
+                    Some(b'u') => {
+                        let hex = self.bytes.get(self.pos + 1..self.pos + 5);
+                        if !hex.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                            return false;
+                        }
+                        self.pos += 5;
+                    }
+                    _ => return false,
+                },
+                0x00..=0x1f => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Comma-separated items up to `close`, each read by `item` with its index
+    fn items(&mut self, close: u8, item: fn(&mut Self, usize) -> bool) -> bool {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return false;
+        }
+        self.whitespace();
+        if !self.eat(close) {
+            for index in 0.. {
+                if !item(self, index) {
+                    return false;
+                }
+                self.whitespace();
+                if self.eat(close) {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return false;
+                }
+                self.whitespace();
+            }
+        }
+        self.depth -= 1;
+        true
+    }
+
+    /// Read the value at `part` of the current path (`.key` or `[index]`)
+    fn nested(&mut self, part: impl FnOnce(&mut String)) -> bool {
+        if self.leaves.is_none() {
+            return self.value();
+        }
+        let len = self.path.len();
+        part(&mut self.path);
+        let valid = self.value();
+        self.path.truncate(len);
+        valid
+    }
+
+    fn member(&mut self, _index: usize) -> bool {
+        let start = self.pos;
+        if !self.string() {
+            return false;
+        }
+        let key = &self.text[start + 1..self.pos - 1];
+        self.whitespace();
+        if !self.eat(b':') {
+            return false;
+        }
+        self.whitespace();
+        self.nested(|path| {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(key);
+        })
+    }
+
+    fn element(&mut self, index: usize) -> bool {
+        self.nested(|path| {
+            path.push('[');
+            path.push_str(&index.to_string());
+            path.push(']');
+        })
+    }
+
+    fn value(&mut self) -> bool {
+        let start = self.pos;
+        let (valid, kind) = match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                return self.items(b'}', Self::member);
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                return self.items(b']', Self::element);
+            }
+            Some(b'"') => {
+                let valid = self.string();
+                if valid {
+                    self.leaf(start + 1, self.pos - 1, LeafKind::String);
+                }
+                return valid;
+            }
+            Some(b't') => (self.literal(b"true"), LeafKind::Literal),
+            Some(b'f') => (self.literal(b"false"), LeafKind::Literal),
+            Some(b'n') => (self.literal(b"null"), LeafKind::Literal),
+            Some(_) => (self.number(), LeafKind::Number),
+            None => return false,
+        };
+        if valid {
+            self.leaf(start, self.pos, kind);
+        }
+        valid
+    }
+}
+
+/// Read a whole JSON object or array
+fn document(text: &str, collect: bool) -> Option<Reader<'_>> {
+    let mut reader = Reader::new(text, collect);
+    reader.whitespace();
+    if !matches!(reader.peek(), Some(b'{' | b'[')) {
+        return None;
+    }
+    let valid = reader.value();
+    reader.whitespace();
+    (valid && reader.pos == reader.bytes.len()).then_some(reader)
+}
+
+/// Whether `text` is a JSON object or array
+pub(crate) fn is_json_document(text: &str) -> bool {
+    document(text, false).is_some()
+}
+
+/// Scalar leaves of a JSON object or array in document order, or None when
+/// `text` is not one
+pub(crate) fn leaves(text: &str) -> Option<Vec<Leaf>> {
+    document(text, true).and_then(|reader| reader.leaves)
+}
+
+/// Text of a string leaf with its escapes resolved, or None when an escape
+/// is invalid
+pub(crate) fn unescape(raw: &str) -> Option<String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = hex4(&mut chars)?;
+                if (0xD800..0xDC00).contains(&high) && chars.as_str().starts_with("\\u") {
+                    chars.nth(1);
+                    let low = hex4(&mut chars)?.checked_sub(0xDC00)?;
+                    char::from_u32(0x10000 + ((high - 0xD800) << 10) + low)?
+                } else {
+                    char::from_u32(high)?
+                }
+            }
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+/// Four hex digits of a `\u` escape
+fn hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let digits = chars.as_str().get(..4)?;
+    let unit = u32::from_str_radix(digits, 16).ok()?;
+    chars.nth(3);
+    Some(unit)
+}
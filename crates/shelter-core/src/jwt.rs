@@ -0,0 +1,108 @@
+//! JSON Web Token claims
+//!
+//! Decodes the header and payload of a JWT (`header.payload.signature`, both
+//! base64url JSON objects) to read its algorithm, issuer, audience and
+//! timestamps. Signatures are not verified: the claims only describe the
+//! token, they are never trusted. The decoded payload is wiped after reading.
+
+use crate::diagnostics::{describe_span, Diagnostic};
+use crate::json::{leaves, unescape, LeafKind};
+use crate::payload::decode_body;
+use crate::types::ShelterSeverity;
+
+/// Claims read from a JWT
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Jwt {
+    /// Signing algorithm from the header, e.g. `RS256`
+    pub alg: Option<String>,
+    pub iss: Option<String>,
+    /// Audiences, joined by `, ` when there are several
+    pub aud: Option<String>,
+    /// Expiry, in Unix seconds
+    pub exp: Option<i64>,
+    /// Issue time, in Unix seconds
+    pub iat: Option<i64>,
+}
+
+/// String and number members of a base64url JSON object, by key path
+fn members(part: &str) -> Option<Vec<(String, String)>> {
+    let decoded = decode_body(part.as_bytes())?;
+    let text = decoded.text()?;
+    if !text.trim_start().starts_with('{') {
+        return None;
+    }
+    let members = leaves(text)?
+        .into_iter()
+        .filter_map(|leaf| {
+            let raw = &text[leaf.start..leaf.end];
+            let value = match leaf.kind {
+                LeafKind::String => unescape(raw)?,
+                LeafKind::Number => raw.to_owned(),
+                LeafKind::Literal => return None,
+            };
+            Some((leaf.path, value))
+        })
+        .collect();
+    Some(members)
+}
+
+/// A NumericDate claim (may be fractional)
+fn timestamp(value: &str) -> Option<i64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|t| t.is_finite())
+        .map(|t| t as i64)
+}
+
+/// Decode the claims of a JWT, or None when `value` is not one
+pub(crate) fn decode(value: &str) -> Option<Jwt> {
+    let mut parts = value.trim().split('.');
+    let (header, payload, _signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || !header.starts_with("eyJ") {
+        return None;
+    }
+    let header = members(header)?;
+    let payload = members(payload)?;
+
+    let mut jwt = Jwt {
+        alg: header
+            .into_iter()
+            .find(|(path, _)| path == "alg")
+            .map(|(_, alg)| alg),
+        ..Default::default()
+    };
+    let mut audiences = Vec::new();
+    for (path, value) in payload {
+        match path.as_str() {
+            "iss" => jwt.iss = Some(value),
+            "exp" => jwt.exp = timestamp(&value),
+            "iat" => jwt.iat = timestamp(&value),
+            "aud" => audiences.push(value),
+            path if path.starts_with("aud[") => audiences.push(value),
+            _ => {}
+        }
+    }
+    jwt.aud = (!audiences.is_empty()).then(|| audiences.join(", "));
+    Some(jwt)
+}
+
+/// An `expired-token` warning for a JWT whose `exp` is before `now`
+pub(crate) fn expiry_diagnostic(
+    jwt: &Jwt,
+    now: i64,
+    line_number: usize,
+    (start, end): (usize, usize),
+    entry_index: usize,
+) -> Option<Diagnostic> {
+    let exp = jwt.exp.filter(|&exp| exp <= now)?;
+    Some(Diagnostic {
+        code: "expired-token",
+        message: format!("JWT expired {} ago", describe_span(now.saturating_sub(exp))),
+        severity: ShelterSeverity::Warning,
+        line_number,
+        start,
+        end,
+        entry_index: Some(entry_index),
+    })
+}
@@ -0,0 +1,39 @@
+//! Keyed hashing
+//!
+//! HMAC-SHA256 under a per-user key. Each use passes its own domain label, so
+//! a digest computed for one purpose can never be replayed for another.
+//!
+//! Fingerprints use the same construction, so two values can be compared by
+//! fingerprint without either being revealed. Without the key, a fingerprint
+//! cannot be checked against guesses.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Domain label for value fingerprints
+const FINGERPRINT_DOMAIN: &str = "shelter-fingerprint-v1";
+
+/// HMAC-SHA256 of `value` under `key`, separated by `domain`
+pub(crate) fn keyed_hash(key: &[u8], domain: &str, value: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(domain.as_bytes());
+    mac.update(&[0]);
+    mac.update(value);
+    mac.finalize().into_bytes().into()
+}
+
+/// 64-bit keyed fingerprint of a value
+///
+/// The digest prefix is read big-endian, so the hex form of the fingerprint
+/// is the hex prefix of the digest.
+pub(crate) fn fingerprint(key: &[u8], value: &[u8]) -> u64 {
+    let digest = keyed_hash(key, FINGERPRINT_DOMAIN, value);
+    u64::from_be_bytes(digest[..8].try_into().expect("8-byte prefix"))
+}
+
+/// Leading `digits` hex digits of a fingerprint (1..=16)
+pub(crate) fn fingerprint_hex(fingerprint: u64, digits: usize) -> String {
+    let mut hex = format!("{:016x}", fingerprint);
+    hex.truncate(digits);
+    hex
+}
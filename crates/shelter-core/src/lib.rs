@@ -2,8 +2,37 @@
 //!
 //! Provides EDF-compliant dotenv parsing via C FFI for LuaJIT.
 
+mod classify;
+mod compare;
+mod diagnostics;
+mod directives;
+mod display;
+mod document;
+mod entropy;
+mod explain;
 mod ffi;
+mod identity;
+mod infer;
+mod json;
+mod jwt;
+mod keyed;
+mod modes;
+mod patterns;
+mod payload;
+mod pem;
+mod policy;
+mod public;
+mod render;
+mod reveal;
+mod smart;
+mod structured;
 mod types;
+mod verify;
+mod weak;
 
 pub use ffi::*;
+pub use identity::entry_id;
+pub use modes::ShelterModeTable;
+pub use patterns::ShelterPatternSet;
+pub use policy::ShelterPolicy;
 pub use types::*;
@@ -0,0 +1,534 @@
+//! Native masking modes
+//!
+//! Mirrors the Lua built-in modes (`full`, `partial`, `none`, `fingerprint`,
+//! `smart`, `structured`) with identical options, so previewers and other
+//! consumers can mask without Lua. Modes are registered by name in a
+//! `ShelterModeTable`, which starts out with the built-ins at their default
+//! options.
+//!
+//! Masks are measured in display cells and values are split on grapheme
+//! clusters, so the mask of `пароль` is six cells wide, partial masking never
+//! cuts a character in half, and double-width glyphs like `＊` are allowed.
+//!
+//! A length strategy can decouple the mask width from the value: bucketing
+//! rounds the width up, and the keyed strategy derives a width from an HMAC of
+//! the value under the table's key. Both are deterministic, so a changed value
+//! usually shows a changed mask while its true length stays hidden. Until the
+//! table has a key, keyed masks take the longest width.
+//!
+//! Fingerprint modes append a short keyed fingerprint (`••••[a1f3]`), so equal
+//! values in different files can be recognised without unmasking them.
+//!
+//! The smart mode leaves obviously non-secret values visible and masks the
+//! rest like `full` (see `smart`).
+//!
+//! Modes fail closed on encoded payloads: a base64 value whose decoded
+//! content holds a secret is masked even where the smart mode or a `none`
+//! fallback would leave it visible (see `payload`).
+//!
+//! PEM private keys are masked like `full` by every mode, including `none`,
+//! with the mode's own glyph and length policy (see `pem`).
+//!
+//! The structured mode masks only the credentials of URLs and connection
+//! strings and the secret leaves of JSON values (see `structured`), keeping
+//! hosts, databases and JSON keys readable, and uses its fallback for other
+//! values.
+
+use crate::display::str_cells;
+use crate::ffi::str_from_raw;
+use crate::keyed::{fingerprint, fingerprint_hex, keyed_hash};
+use crate::patterns::{PatternOptions, ShelterPatternSet};
+use crate::payload::hides_secret;
+use crate::pem::holds_private_key;
+use crate::smart::{is_safe, ALL_SHAPES};
+use crate::structured::{structure_with, JsonRules};
+use crate::types::{ShelterJsonLeaves, ShelterModeKind, ShelterModeOptions};
+use std::collections::HashMap;
+use std::slice;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Domain label for keyed mask lengths
+const LENGTH_DOMAIN: &str = "shelter-length-v1";
+
+/// Mask glyphs drawn before a fingerprint unless `fixed_length` is set
+const FINGERPRINT_MASK: usize = 4;
+
+/// Mode to use when a value is too short for partial masking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fallback {
+    Full,
+    None,
+}
+
+/// How many mask cells to draw for a hidden segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LengthPolicy {
+    Preserve,
+    Bucket(usize),
+    Keyed { min: usize, max: usize },
+}
+
+/// Validated, owned mode options
+#[derive(Debug, Clone)]
+pub(crate) struct Mode {
+    pub kind: ShelterModeKind,
+    pub mask_char: String,
+    /// Display cells taken by one `mask_char` (1 or 2)
+    pub mask_width: usize,
+    pub fixed_length: Option<usize>,
+    pub show_start: usize,
+    pub show_end: usize,
+    pub min_mask: usize,
+    pub fallback: Fallback,
+    pub length: LengthPolicy,
+    /// Hex digits of the fingerprint shown (fingerprint)
+    pub fingerprint_length: usize,
+    /// Safe shapes as a bitmask of `1 << ShelterSafeShape` (smart)
+    pub safe_shapes: u8,
+    /// Values left visible (smart)
+    pub safe_values: Option<ShelterPatternSet>,
+    /// JSON key paths whose strings are masked (structured)
+    pub secret_keys: Option<ShelterPatternSet>,
+    /// Which JSON leaves are masked (structured)
+    pub json_leaves: ShelterJsonLeaves,
+    /// Key for keyed lengths and fingerprints (shared with the owning table)
+    key: Arc<[u8]>,
+}
+
+impl Mode {
+    /// Built-in mode with the same defaults as the Lua definitions
+    pub(crate) fn builtin(kind: ShelterModeKind) -> Self {
+        Mode {
+            kind,
+            mask_char: Self::default_mask_char(kind).to_owned(),
+            mask_width: 1,
+            fixed_length: None,
+            show_start: 3,
+            show_end: 3,
+            min_mask: 3,
+            fallback: Fallback::Full,
+            length: LengthPolicy::Preserve,
+            fingerprint_length: 4,
+            safe_shapes: ALL_SHAPES,
+            safe_values: None,
+            secret_keys: None,
+            json_leaves: ShelterJsonLeaves::Secret,
+            key: Arc::from(&[][..]),
+        }
+    }
+
+    /// Mask character used when none is configured
+    #[inline]
+    fn default_mask_char(kind: ShelterModeKind) -> &'static str {
+        match kind {
+            ShelterModeKind::Fingerprint => "•",
+            _ => "*",
+        }
+    }
+
+    /// Validate FFI options, mirroring the Lua mode validators
+    ///
+    /// # Safety
+    /// `opts.mask_char`, `opts.safe_values` and `opts.secret_keys` must be
+    /// null or valid for their lengths
+    pub(crate) unsafe fn from_ffi(opts: &ShelterModeOptions) -> Result<Self, String> {
+        let kind = match opts.kind {
+            0 => ShelterModeKind::Full,
+            1 => ShelterModeKind::Partial,
+            2 => ShelterModeKind::None,
+            3 => ShelterModeKind::Fingerprint,
+            4 => ShelterModeKind::Smart,
+            5 => ShelterModeKind::Structured,
+            other => return Err(format!("Unknown mode kind: {}", other)),
+        };
+
+        let mask_char = if opts.mask_char.is_null() || opts.mask_char_len == 0 {
+            Self::default_mask_char(kind).to_owned()
+        } else {
+            let bytes = slice::from_raw_parts(opts.mask_char as *const u8, opts.mask_char_len);
+            std::str::from_utf8(bytes)
+                .map_err(|_| "mask_char must be valid UTF-8".to_owned())?
+                .to_owned()
+        };
+        if mask_char.graphemes(true).count() != 1 {
+            return Err("mask_char must be a single character".to_owned());
+        }
+        let mask_width = str_cells(&mask_char);
+        if !(1..=2).contains(&mask_width) {
+            return Err("mask_char must be one or two cells wide".to_owned());
+        }
+
+        let fallback = match opts.fallback_mode {
+            0 => Fallback::Full,
+            2 => Fallback::None,
+            _ => return Err("fallback_mode must be full or none".to_owned()),
+        };
+
+        if kind == ShelterModeKind::Partial && opts.min_mask == 0 {
+            return Err("min_mask must be >= 1".to_owned());
+        }
+
+        let length = match opts.length_strategy {
+            0 => LengthPolicy::Preserve,
+            1 if opts.bucket_size == 0 => return Err("bucket_size must be >= 1".to_owned()),
+            1 => LengthPolicy::Bucket(opts.bucket_size),
+            2 if opts.min_length == 0 || opts.max_length < opts.min_length => {
+                return Err("length range must satisfy 1 <= min_length <= max_length".to_owned())
+            }
+            2 => LengthPolicy::Keyed {
+                min: opts.min_length,
+                max: opts.max_length,
+            },
+            other => return Err(format!("Unknown length strategy: {}", other)),
+        };
+
+        if !(1..=16).contains(&opts.fingerprint_length) {
+            return Err("fingerprint_length must be between 1 and 16".to_owned());
+        }
+
+        if opts.safe_shapes > ALL_SHAPES {
+            return Err(format!("Unknown safe shapes: {:#b}", opts.safe_shapes));
+        }
+        let safe_values = glob_lines(
+            str_from_raw(opts.safe_values, opts.safe_values_len)
+                .ok_or("safe_values must be valid UTF-8")?,
+        )?;
+        let secret_keys = glob_lines(
+            str_from_raw(opts.secret_keys, opts.secret_keys_len)
+                .ok_or("secret_keys must be valid UTF-8")?,
+        )?;
+        let json_leaves = match opts.json_leaves {
+            0 => ShelterJsonLeaves::Secret,
+            1 => ShelterJsonLeaves::Strings,
+            other => return Err(format!("Unknown json_leaves: {}", other)),
+        };
+
+        // `preserve_length` is accepted for parity with Lua, where it is
+        // implied whenever `fixed_length` is unset
+        Ok(Mode {
+            kind,
+            mask_char,
+            mask_width,
+            fixed_length: (opts.fixed_length > 0).then_some(opts.fixed_length),
+            show_start: opts.show_start,
+            show_end: opts.show_end,
+            min_mask: opts.min_mask,
+            fallback,
+            length,
+            fingerprint_length: opts.fingerprint_length,
+            safe_shapes: opts.safe_shapes,
+            safe_values,
+            secret_keys,
+            json_leaves,
+            key: Arc::from(&[][..]),
+        })
+    }
+
+    /// Whether the `none` fallback leaves a `value` this mode cannot mask
+    /// visible (not when it is an encoded secret)
+    #[inline]
+    fn falls_back_visible(&self, value: &str) -> bool {
+        self.fallback == Fallback::None && !hides_secret(value)
+    }
+
+    /// Whether `value` is masked in full whatever this mode would do (PEM
+    /// private keys)
+    #[inline]
+    fn forces_full(&self, value: &str) -> bool {
+        self.kind != ShelterModeKind::Full && holds_private_key(value)
+    }
+
+    /// JSON leaf rules for structured masking
+    #[inline]
+    fn json_rules(&self) -> JsonRules<'_> {
+        JsonRules {
+            keys: self.secret_keys.as_ref(),
+            all_strings: self.json_leaves == ShelterJsonLeaves::Strings,
+        }
+    }
+
+    /// Whether this mode leaves `value` unmasked
+    #[inline]
+    pub(crate) fn leaves_visible(&self, value: &str) -> bool {
+        if self.forces_full(value) {
+            return false;
+        }
+        match self.kind {
+            ShelterModeKind::None => true,
+            ShelterModeKind::Smart => {
+                is_safe(value, self.safe_shapes, self.safe_values.as_ref()) && !hides_secret(value)
+            }
+            ShelterModeKind::Structured => match structure_with(value, self.json_rules()) {
+                Some(structure) => structure.spans.is_empty(),
+                None => self.falls_back_visible(value),
+            },
+            _ => false,
+        }
+    }
+
+    /// Byte ranges of `value` this mode masks, when it masks only parts of it
+    /// (structured values); None when the whole value is masked
+    pub(crate) fn hidden_ranges(&self, value: &str) -> Option<Vec<(usize, usize)>> {
+        if self.kind != ShelterModeKind::Structured || self.forces_full(value) {
+            return None;
+        }
+        structure_with(value, self.json_rules()).map(|structure| {
+            structure
+                .spans
+                .iter()
+                .map(|span| (span.start, span.end))
+                .collect()
+        })
+    }
+
+    /// Mask a value according to this mode
+    pub(crate) fn apply(&self, value: &str) -> String {
+        if self.forces_full(value) {
+            return self.mask_full(value);
+        }
+        match self.kind {
+            ShelterModeKind::None => value.to_owned(),
+            ShelterModeKind::Smart if self.leaves_visible(value) => value.to_owned(),
+            ShelterModeKind::Full | ShelterModeKind::Smart => self.mask_full(value),
+            ShelterModeKind::Partial => self.mask_partial(value),
+            ShelterModeKind::Fingerprint => self.mask_fingerprint(value),
+            ShelterModeKind::Structured => self.mask_structured(value),
+        }
+    }
+
+    /// Mask a value that occupies `cells` on screen
+    ///
+    /// Same as `apply`, except a length-preserving full mask takes the given
+    /// width, which can differ from the value's own width when it has tabs.
+    pub(crate) fn apply_with_width(&self, value: &str, cells: usize) -> String {
+        match (self.kind, self.fixed_length, self.length) {
+            (ShelterModeKind::Full, None, LengthPolicy::Preserve) => self.fill(cells),
+            (_, None, LengthPolicy::Preserve) if self.forces_full(value) => self.fill(cells),
+            (ShelterModeKind::Smart, None, LengthPolicy::Preserve)
+                if !self.leaves_visible(value) =>
+            {
+                self.fill(cells)
+            }
+            _ => self.apply(value),
+        }
+    }
+
+    /// Number of mask cells drawn for `value` (0 if it stays visible)
+    pub(crate) fn mask_cells(&self, value: &str) -> usize {
+        if self.forces_full(value) {
+            return self.full_cells(value);
+        }
+        match self.kind {
+            ShelterModeKind::None => 0,
+            ShelterModeKind::Smart if self.leaves_visible(value) => 0,
+            ShelterModeKind::Full | ShelterModeKind::Smart => self.full_cells(value),
+            ShelterModeKind::Partial => match self.split_partial(value) {
+                Some((_, hidden, _)) => self.hidden_cells(&hidden),
+                None if self.falls_back_visible(value) => 0,
+                None => self.hidden_cells(value),
+            },
+            ShelterModeKind::Fingerprint => {
+                self.fingerprint_glyphs() * self.mask_width + self.fingerprint_length + 2
+            }
+            ShelterModeKind::Structured => match self.hidden_ranges(value) {
+                Some(ranges) => ranges
+                    .iter()
+                    .map(|&(start, end)| self.hidden_cells(&value[start..end]))
+                    .sum(),
+                None if self.falls_back_visible(value) => 0,
+                None => self.full_cells(value),
+            },
+        }
+    }
+
+    /// Mask cells of a full mask of `value`
+    #[inline]
+    fn full_cells(&self, value: &str) -> usize {
+        match self.fixed_length {
+            Some(length) => length * self.mask_width,
+            None => self.hidden_cells(value),
+        }
+    }
+
+    /// Mask cells for a hidden segment under the length policy
+    fn hidden_cells(&self, hidden: &str) -> usize {
+        let cells = str_cells(hidden);
+        if cells == 0 {
+            return 0;
+        }
+        match self.length {
+            LengthPolicy::Preserve => cells,
+            LengthPolicy::Bucket(size) => cells.div_ceil(size) * size,
+            // A hash under an empty key would let anyone confirm a guessed
+            // value, so keyed masks take the longest width until a key is set
+            LengthPolicy::Keyed { max, .. } if self.key.is_empty() => max,
+            LengthPolicy::Keyed { min, max } => {
+                let digest = keyed_hash(&self.key, LENGTH_DOMAIN, hidden.as_bytes());
+                let n = u64::from_le_bytes(digest[..8].try_into().expect("8-byte prefix"));
+                min + (n % (max - min + 1) as u64) as usize
+            }
+        }
+    }
+
+    /// Mask for a hidden segment, sized by the length policy
+    #[inline]
+    pub(crate) fn mask_hidden(&self, hidden: &str) -> String {
+        self.fill(self.hidden_cells(hidden))
+    }
+
+    /// Mask covering exactly `cells` display cells
+    ///
+    /// A double-width glyph cannot fill an odd width, so the last cell is a
+    /// space.
+    pub(crate) fn fill(&self, cells: usize) -> String {
+        let mut out = self.mask_char.repeat(cells / self.mask_width);
+        out.extend(std::iter::repeat_n(' ', cells % self.mask_width));
+        out
+    }
+
+    /// Full masking: `fixed_length` glyphs, otherwise per the length policy
+    fn mask_full(&self, value: &str) -> String {
+        match self.fixed_length {
+            Some(length) => self.mask_char.repeat(length),
+            None => self.mask_hidden(value),
+        }
+    }
+
+    /// Mask glyphs drawn before a fingerprint
+    #[inline]
+    fn fingerprint_glyphs(&self) -> usize {
+        self.fixed_length.unwrap_or(FINGERPRINT_MASK)
+    }
+
+    /// Fingerprint masking: a fixed mask followed by `[<hex digits>]`
+    ///
+    /// The mask never depends on the value's length.
+    fn mask_fingerprint(&self, value: &str) -> String {
+        let digits = fingerprint_hex(
+            fingerprint(&self.key, value.as_bytes()),
+            self.fingerprint_length,
+        );
+        format!(
+            "{}[{}]",
+            self.mask_char.repeat(self.fingerprint_glyphs()),
+            digits
+        )
+    }
+
+    /// Split a value into shown start, hidden middle and shown end
+    ///
+    /// Returns None when the value is too short for partial masking.
+    fn split_partial(&self, value: &str) -> Option<(String, String, String)> {
+        let graphemes: Vec<&str> = value.graphemes(true).collect();
+        let len = graphemes.len();
+        let shortest = self
+            .show_start
+            .saturating_add(self.show_end)
+            .saturating_add(self.min_mask);
+        if len < shortest {
+            return None;
+        }
+        Some((
+            graphemes[..self.show_start].concat(),
+            graphemes[self.show_start..len - self.show_end].concat(),
+            graphemes[len - self.show_end..].concat(),
+        ))
+    }
+
+    /// Partial masking: show `show_start`/`show_end` graphemes around at least
+    /// `min_mask` masked graphemes, or use the fallback for short values
+    fn mask_partial(&self, value: &str) -> String {
+        match self.split_partial(value) {
+            Some((start, hidden, end)) => start + &self.mask_hidden(&hidden) + &end,
+            None if self.falls_back_visible(value) => value.to_owned(),
+            None => self.mask_hidden(value),
+        }
+    }
+
+    /// Structured masking: mask the credential components of URLs, connection
+    /// strings and JSON values, or use the fallback for other values
+    fn mask_structured(&self, value: &str) -> String {
+        match self.hidden_ranges(value) {
+            Some(ranges) => {
+                let mut out = String::with_capacity(value.len());
+                let mut cursor = 0;
+                for (start, end) in ranges {
+                    out.push_str(&value[cursor..start]);
+                    out.push_str(&self.mask_hidden(&value[start..end]));
+                    cursor = end;
+                }
+                out.push_str(&value[cursor..]);
+                out
+            }
+            None if self.falls_back_visible(value) => value.to_owned(),
+            None => self.mask_full(value),
+        }
+    }
+}
+
+/// Case-insensitive glob set from newline-separated patterns (None = none)
+fn glob_lines(text: &str) -> Result<Option<ShelterPatternSet>, String> {
+    let globs: Vec<&str> = text.lines().filter(|g| !g.is_empty()).collect();
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let options = PatternOptions {
+        case_insensitive: true,
+        paths: false,
+    };
+    ShelterPatternSet::new(globs.into_iter().map(|g| (g, "")), options).map(Some)
+}
+
+/// Named, configurable set of masking modes (opaque to C)
+pub struct ShelterModeTable {
+    modes: HashMap<String, Mode>,
+    key: Arc<[u8]>,
+}
+
+impl Default for ShelterModeTable {
+    fn default() -> Self {
+        let mut modes = HashMap::new();
+        modes.insert("full".to_owned(), Mode::builtin(ShelterModeKind::Full));
+        modes.insert(
+            "partial".to_owned(),
+            Mode::builtin(ShelterModeKind::Partial),
+        );
+        modes.insert("none".to_owned(), Mode::builtin(ShelterModeKind::None));
+        modes.insert(
+            "fingerprint".to_owned(),
+            Mode::builtin(ShelterModeKind::Fingerprint),
+        );
+        modes.insert("smart".to_owned(), Mode::builtin(ShelterModeKind::Smart));
+        modes.insert(
+            "structured".to_owned(),
+            Mode::builtin(ShelterModeKind::Structured),
+        );
+        ShelterModeTable {
+            modes,
+            key: Arc::from(&[][..]),
+        }
+    }
+}
+
+impl ShelterModeTable {
+    /// Register or replace a mode
+    pub(crate) fn set(&mut self, name: &str, mut mode: Mode) {
+        mode.key = Arc::clone(&self.key);
+        self.modes.insert(name.to_owned(), mode);
+    }
+
+    /// Look up a mode by name
+    #[inline]
+    pub(crate) fn get(&self, name: &str) -> Option<&Mode> {
+        self.modes.get(name)
+    }
+
+    /// Set the key used by keyed lengths and fingerprints of all modes
+    pub(crate) fn set_key(&mut self, key: &[u8]) {
+        self.key = Arc::from(key);
+        for mode in self.modes.values_mut() {
+            mode.key = Arc::clone(&self.key);
+        }
+    }
+}
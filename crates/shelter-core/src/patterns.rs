@@ -0,0 +1,260 @@
+//! Compiled key and source pattern sets
+//!
+//! Globs are translated to regular expressions and compiled into a single
+//! `RegexSet`, so a key is tested against every pattern in one pass.
+//!
+//! Glob syntax:
+//! - `*` matches any run of characters (within one path component for paths)
+//! - `?` matches one character (not `/` for paths)
+//! - `[abc]`, `[a-z]` and `[!abc]` (or `[^abc]`) match one character of a class
+//! - `**` matches across path components; `**/` also matches no directory
+//! - `\` makes the next character literal
+//!
+//! Path sets (sources) match patterns without a `/` against the file name and
+//! patterns with one against trailing path components; a leading `/` anchors
+//! the pattern at the start of the path.
+//!
+//! When several patterns match, the winner is decided by, in order:
+//! 1. highest specificity (characters pinned down by the pattern: literals,
+//!    `?` and classes count one each, `*` and `**` nothing)
+//! 2. fewest `*`/`**` wildcards
+//! 3. earliest pattern in the set
+
+use regex::{RegexSet, RegexSetBuilder};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+
+/// Compile-time options for a pattern set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PatternOptions {
+    pub case_insensitive: bool,
+    /// Match file paths (`*` and `?` stop at `/`, `**` crosses it)
+    pub paths: bool,
+}
+
+/// A compiled pattern with its mode and ranking
+#[derive(Debug, Clone)]
+pub(crate) struct PatternRule {
+    pub pattern: String,
+    pub mode: String,
+    pub specificity: usize,
+    pub wildcards: usize,
+}
+
+/// Glob translated to a regex, with its ranking
+struct Translated {
+    regex: String,
+    specificity: usize,
+    wildcards: usize,
+}
+
+/// Translate a glob into an anchored regex
+fn translate(glob: &str, paths: bool) -> Result<Translated, String> {
+    let any = if paths { "[^/]" } else { "." };
+    let mut regex = String::with_capacity(glob.len() * 2 + 8);
+    let (mut specificity, mut wildcards) = (0, 0);
+
+    regex.push_str(match (paths, glob.starts_with('/')) {
+        (true, false) => "(?:^|/)",
+        _ => "^",
+    });
+
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                wildcards += 1;
+                if paths && chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => {
+                wildcards += 1;
+                regex.push_str(any);
+                regex.push('*');
+            }
+            '?' => {
+                specificity += 1;
+                regex.push_str(any);
+            }
+            '[' => {
+                specificity += 1;
+                translate_class(&mut chars, paths, &mut regex)
+                    .ok_or_else(|| format!("Unclosed character class in pattern '{}'", glob))?;
+            }
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| format!("Trailing escape in pattern '{}'", glob))?;
+                specificity += 1;
+                regex.push_str(&regex::escape(escaped.encode_utf8(&mut [0; 4])));
+            }
+            c => {
+                specificity += 1;
+                regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+            }
+        }
+    }
+
+    regex.push('$');
+    Ok(Translated {
+        regex,
+        specificity,
+        wildcards,
+    })
+}
+
+/// Translate a character class after its `[`, returning None if unclosed
+fn translate_class(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    paths: bool,
+    out: &mut String,
+) -> Option<()> {
+    out.push('[');
+    if matches!(chars.peek(), Some('!' | '^')) {
+        chars.next();
+        out.push('^');
+        if paths {
+            out.push('/');
+        }
+    }
+
+    let mut first = true;
+    loop {
+        let c = chars.next()?;
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+
+        // Range, unless the `-` is the last character of the class
+        if chars.peek() == Some(&'-') {
+            let mut ahead = chars.clone();
+            ahead.next();
+            if let Some(end) = ahead.next().filter(|&end| end != ']') {
+                *chars = ahead;
+                out.push('-');
+                out.push_str(&regex::escape(end.encode_utf8(&mut [0; 4])));
+            }
+        }
+    }
+    out.push(']');
+    Some(())
+}
+
+/// Compiled set of glob patterns (opaque to C)
+#[derive(Debug, Clone)]
+pub struct ShelterPatternSet {
+    rules: Vec<PatternRule>,
+    set: RegexSet,
+    paths: bool,
+}
+
+impl Default for ShelterPatternSet {
+    fn default() -> Self {
+        ShelterPatternSet {
+            rules: Vec::new(),
+            set: RegexSet::empty(),
+            paths: false,
+        }
+    }
+}
+
+impl ShelterPatternSet {
+    /// Compile `(pattern, mode)` pairs, in precedence order for ties
+    pub(crate) fn new<'a>(
+        rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+        options: PatternOptions,
+    ) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        let mut regexes = Vec::new();
+        for (pattern, mode) in rules {
+            let translated = translate(pattern, options.paths)?;
+            regexes.push(translated.regex);
+            compiled.push(PatternRule {
+                pattern: pattern.to_owned(),
+                mode: mode.to_owned(),
+                specificity: translated.specificity,
+                wildcards: translated.wildcards,
+            });
+        }
+
+        let set = RegexSetBuilder::new(regexes)
+            .case_insensitive(options.case_insensitive)
+            .dot_matches_new_line(true)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {}", e))?;
+
+        Ok(ShelterPatternSet {
+            rules: compiled,
+            set,
+            paths: options.paths,
+        })
+    }
+
+    /// Check that a glob is valid
+    pub(crate) fn validate(pattern: &str, options: PatternOptions) -> Result<(), String> {
+        Self::new([(pattern, "")], options).map(|_| ())
+    }
+
+    /// Rules in the order they were given
+    #[inline]
+    pub(crate) fn rules(&self) -> &[PatternRule] {
+        &self.rules
+    }
+
+    /// Path separators normalised to `/` for path sets
+    fn normalise<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.paths && text.contains('\\') {
+            Cow::Owned(text.replace('\\', "/"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Indices of every rule matching `text`, in order
+    pub(crate) fn matching(&self, text: &str) -> Vec<usize> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        self.set
+            .matches(&self.normalise(text))
+            .into_iter()
+            .collect()
+    }
+
+    /// Index of the winning rule for `text` (see the module docs)
+    pub(crate) fn best_index(&self, text: &str) -> Option<usize> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        self.set
+            .matches(&self.normalise(text))
+            .iter()
+            .max_by_key(|&index| self.rank(index))
+    }
+
+    /// Sort key of a rule: higher wins
+    #[inline]
+    fn rank(&self, index: usize) -> (usize, Reverse<usize>, Reverse<usize>) {
+        let rule = &self.rules[index];
+        (rule.specificity, Reverse(rule.wildcards), Reverse(index))
+    }
+
+    /// Whether any pattern matches `text`
+    #[inline]
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        self.best_index(text).is_some()
+    }
+
+    /// Winning rule for `text`
+    #[inline]
+    pub(crate) fn best_match(&self, text: &str) -> Option<&PatternRule> {
+        self.best_index(text).map(|index| &self.rules[index])
+    }
+}
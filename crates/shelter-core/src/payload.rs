@@ -0,0 +1,219 @@
+//! Encoded payload inspection
+//!
+//! Detects base64 and base64url values, decodes them in memory and looks at
+//! what they carry: JSON with secret keys, PEM private keys, known tokens, or
+//! another encoded layer. Only a summary such as `base64 → JSON with
+//! private_key` leaves this module unless the decoded bytes are explicitly
+//! requested (`shelter_inspect_payload`); decoded buffers are wiped when
+//! dropped.
+//!
+//! A value counts as encoded when its decoded bytes are printable text, or
+//! when its shape alone marks it as base64 (padding, `+`/`/`, or length; see
+//! `infer`). Random tokens that merely use the base64 alphabet decode to
+//! binary noise and are left alone.
+
+use crate::classify::classify;
+use crate::infer::infer_type;
+use crate::structured::structure;
+use crate::types::{ShelterEncoding, ShelterValueType};
+use std::ptr;
+
+/// Shortest encoded value inspected
+const MIN_LEN: usize = 16;
+
+/// Longest encoded value inspected (1 MiB)
+const MAX_LEN: usize = 1 << 20;
+
+/// Most encoded layers peeled off (`base64 → base64 → JSON`)
+const MAX_LAYERS: usize = 3;
+
+/// Decoded bytes, zeroed when dropped
+pub(crate) struct Decoded(Vec<u8>);
+
+impl Decoded {
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Printable UTF-8 text (line breaks and tabs allowed), if the bytes are
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.0)
+            .ok()
+            .filter(|text| !text.is_empty())
+            .filter(|text| {
+                text.chars()
+                    .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+            })
+    }
+}
+
+impl Drop for Decoded {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // Volatile so the wipe is not optimised away
+            unsafe { ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
+/// Decode a base64 or base64url value (line-wrapped output allowed)
+fn decode(value: &str) -> Option<(ShelterEncoding, Decoded)> {
+    let value = value.trim();
+    if !(MIN_LEN..=MAX_LEN).contains(&value.len()) {
+        return None;
+    }
+    let compact: Vec<u8> = value
+        .bytes()
+        .filter(|b| !matches!(b, b'\n' | b'\r'))
+        .collect();
+    let body_len = compact
+        .iter()
+        .rposition(|&b| b != b'=')
+        .map_or(0, |i| i + 1);
+    let (body, padding) = compact.split_at(body_len);
+    if padding.len() > 2
+        || (!padding.is_empty() && !compact.len().is_multiple_of(4))
+        || body.len() % 4 == 1
+        || body.iter().all(u8::is_ascii_hexdigit)
+    {
+        return None;
+    }
+
+    let standard = body.iter().any(|b| matches!(b, b'+' | b'/'));
+    let url = body.iter().any(|b| matches!(b, b'-' | b'_'));
+    let encoding = match (standard, url) {
+        (true, true) => return None,
+        (false, true) => ShelterEncoding::Base64Url,
+        _ => ShelterEncoding::Base64,
+    };
+
+    Some((encoding, decode_body(body)?))
+}
+
+/// Decode unpadded base64 or base64url characters (alphabets may mix)
+pub(crate) fn decode_body(body: &[u8]) -> Option<Decoded> {
+    let mut decoded = Decoded(Vec::with_capacity(body.len() / 4 * 3 + 2));
+    let (mut bits, mut count) = (0u32, 0);
+    for &b in body {
+        let sextet = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6 | sextet as u32) & 0xFFFF;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            decoded.0.push((bits >> count) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// What an encoded value carries
+pub(crate) struct Payload {
+    /// Encodings from the outside in
+    pub layers: Vec<ShelterEncoding>,
+    /// Type of the innermost payload (None when it is binary)
+    pub kind: ShelterValueType,
+    /// What makes the payload secret: a secret type id, or the JSON key path,
+    /// URL component or parameter holding a credential
+    pub secret: Option<String>,
+    /// Innermost decoded bytes
+    pub decoded: Decoded,
+}
+
+impl Payload {
+    /// One-line description, e.g. `base64 → JSON with private_key`
+    pub fn summary(&self) -> String {
+        let mut summary: Vec<&str> = self
+            .layers
+            .iter()
+            .map(|encoding| match encoding {
+                ShelterEncoding::Base64Url => "base64url",
+                _ => "base64",
+            })
+            .collect();
+        summary.push(match self.kind {
+            ShelterValueType::None => "binary",
+            ShelterValueType::Json => "JSON",
+            ShelterValueType::Pem => "PEM",
+            ShelterValueType::Url => "URL",
+            ShelterValueType::ConnectionString => "connection string",
+            _ => "text",
+        });
+        let summary = summary.join(" → ");
+        match &self.secret {
+            Some(secret) => format!("{} with {}", summary, secret),
+            None => summary,
+        }
+    }
+}
+
+/// The credential inside decoded text, if any
+fn secret_in(text: &str, kind: ShelterValueType) -> Option<String> {
+    if let Some(c) = classify(text.trim()).filter(|c| c.secret) {
+        return Some(c.id.to_owned());
+    }
+    match kind {
+        ShelterValueType::Json | ShelterValueType::Url | ShelterValueType::ConnectionString => {
+            structure(text.trim())?
+                .spans
+                .into_iter()
+                .next()
+                .map(|span| span.component.into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Decode an encoded value and inspect what it carries
+///
+/// Returns None for values that are not encoded.
+pub(crate) fn inspect(value: &str) -> Option<Payload> {
+    let (encoding, mut decoded) = decode(value)?;
+    if decoded.text().is_none() && infer_type(value.trim()) != ShelterValueType::Base64 {
+        return None;
+    }
+
+    let mut layers = vec![encoding];
+    while layers.len() < MAX_LAYERS {
+        let inner = decoded
+            .text()
+            .and_then(decode)
+            .filter(|(_, inner)| inner.text().is_some());
+        match inner {
+            Some((encoding, inner)) => {
+                layers.push(encoding);
+                decoded = inner;
+            }
+            None => break,
+        }
+    }
+
+    let (kind, secret) = match decoded.text() {
+        Some(text) => {
+            let kind = infer_type(text.trim());
+            (kind, secret_in(text, kind))
+        }
+        None => (ShelterValueType::None, None),
+    };
+    Some(Payload {
+        layers,
+        kind,
+        secret,
+        decoded,
+    })
+}
+
+/// Whether `value` is an encoded payload carrying a secret
+///
+/// Modes that would leave such a value visible mask it instead (fail closed).
+#[inline]
+pub(crate) fn hides_secret(value: &str) -> bool {
+    inspect(value).is_some_and(|payload| payload.secret.is_some())
+}
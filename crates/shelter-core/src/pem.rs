@@ -0,0 +1,484 @@
+//! PEM blocks
+//!
+//! Finds `-----BEGIN <label>-----` … `-----END <label>-----` blocks in values
+//! (split on real or escaped `\n`) and in runs of comment lines. Private keys
+//! are masked whatever the mode (see `modes` and `render`); certificates and
+//! certificate requests are decoded just enough to read their subject, issuer
+//! and expiry. Nothing is verified: the fields only describe the block.
+
+use crate::diagnostics::{describe_span, Diagnostic};
+use crate::document::Document;
+use crate::payload::decode_body;
+use crate::types::{ShelterPemKind, ShelterSeverity};
+
+/// Certificates expiring within this many days are reported by default
+pub(crate) const DEFAULT_EXPIRY_WINDOW_DAYS: usize = 30;
+
+// DER tags
+const INTEGER: u8 = 0x02;
+const OID: u8 = 0x06;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const BMP_STRING: u8 = 0x1E;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+/// `[0] EXPLICIT Version` of a certificate
+const VERSION: u8 = 0xA0;
+
+/// A PEM block; offsets are relative to the scanned text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Block {
+    pub label: String,
+    pub kind: ShelterPemKind,
+    /// Byte range of the `-----BEGIN <label>-----` marker
+    pub begin: (usize, usize),
+    /// Byte ranges of the base64 lines (without comment markers)
+    pub body: Vec<(usize, usize)>,
+    /// Whether the matching END marker was found
+    pub complete: bool,
+}
+
+impl Block {
+    /// Subject, issuer and expiry of a certificate or certificate request
+    pub fn certificate(&self, text: &str) -> Option<Certificate> {
+        if !self.complete {
+            return None;
+        }
+        let body: String = self
+            .body
+            .iter()
+            .map(|&(start, end)| &text[start..end])
+            .collect();
+        let der = decode_body(body.trim_end_matches('=').as_bytes())?;
+        match self.kind {
+            ShelterPemKind::Certificate => parse_certificate(der.as_bytes()),
+            ShelterPemKind::CertificateRequest => parse_request(der.as_bytes()),
+            _ => None,
+        }
+    }
+}
+
+/// What a block holds, from its label
+fn kind(label: &str) -> ShelterPemKind {
+    match label {
+        l if l.ends_with("PRIVATE KEY") || l.ends_with("PRIVATE KEY BLOCK") => {
+            ShelterPemKind::PrivateKey
+        }
+        "CERTIFICATE" | "TRUSTED CERTIFICATE" | "X509 CERTIFICATE" => ShelterPemKind::Certificate,
+        "CERTIFICATE REQUEST" | "NEW CERTIFICATE REQUEST" => ShelterPemKind::CertificateRequest,
+        l if l.ends_with("PUBLIC KEY") || l.ends_with("PUBLIC KEY BLOCK") => {
+            ShelterPemKind::PublicKey
+        }
+        _ => ShelterPemKind::Other,
+    }
+}
+
+/// Label of a `-----BEGIN <label>-----` or `-----END <label>-----` line
+fn marker<'a>(line: &'a str, word: &str) -> Option<&'a str> {
+    line.strip_prefix("-----")?
+        .strip_prefix(word)?
+        .strip_prefix(' ')?
+        .strip_suffix("-----")
+        .filter(|label| {
+            !label.is_empty()
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b' ')
+        })
+}
+
+/// Collects blocks from lines fed in order
+#[derive(Default)]
+struct Scanner {
+    blocks: Vec<Block>,
+    open: Option<Block>,
+}
+
+impl Scanner {
+    /// Feed the line starting at `offset`; leading `#` and blanks are skipped
+    fn line(&mut self, offset: usize, line: &str) {
+        let content = line.trim_start_matches([' ', '\t', '#']);
+        let start = offset + line.len() - content.len();
+        let content = content.trim_end();
+        let end = start + content.len();
+
+        if let Some(block) = &mut self.open {
+            if let Some(label) = marker(content, "END") {
+                block.complete = label == block.label;
+                self.close();
+                return;
+            }
+            if marker(content, "BEGIN").is_none() {
+                // Encapsulated headers (`Proc-Type: ...`) are not body
+                if !content.is_empty() && !content.contains(':') {
+                    block.body.push((start, end));
+                }
+                return;
+            }
+            self.close();
+        }
+        if let Some(label) = marker(content, "BEGIN") {
+            self.open = Some(Block {
+                label: label.to_owned(),
+                kind: kind(label),
+                begin: (start, end),
+                body: Vec::new(),
+                complete: false,
+            });
+        }
+    }
+
+    /// End an open block (unterminated blocks are kept)
+    fn close(&mut self) {
+        self.blocks.extend(self.open.take());
+    }
+
+    fn finish(mut self) -> Vec<Block> {
+        self.close();
+        self.blocks
+    }
+}
+
+/// PEM blocks in a value, whose lines may be separated by escaped `\n`
+pub(crate) fn blocks(value: &str) -> Vec<Block> {
+    if !value.contains("-----BEGIN ") {
+        return Vec::new();
+    }
+    let mut scanner = Scanner::default();
+    let mut offset = 0;
+    loop {
+        let rest = &value[offset..];
+        let (len, separator) = match (rest.find('\n'), rest.find("\\n")) {
+            (Some(real), Some(escaped)) if escaped < real => (escaped, 2),
+            (Some(real), _) => (real, 1),
+            (None, Some(escaped)) => (escaped, 2),
+            (None, None) => (rest.len(), 0),
+        };
+        scanner.line(offset, &rest[..len]);
+        if separator == 0 {
+            break;
+        }
+        offset += len + separator;
+    }
+    scanner.finish()
+}
+
+/// PEM blocks in runs of comment lines, with offsets into `input`
+///
+/// Lines inside multi-line values are not comments, even when they start
+/// with `#`.
+pub(crate) fn comment_blocks(input: &str, doc: &Document) -> Vec<Block> {
+    if !input.contains("-----BEGIN ") {
+        return Vec::new();
+    }
+    let mut in_value = vec![false; doc.line_starts.len() + 1];
+    for pair in &doc.pairs {
+        let last = pair.value_end_line.min(doc.line_starts.len());
+        if let Some(lines) = in_value.get_mut(pair.line_number + 1..=last) {
+            lines.fill(true);
+        }
+    }
+
+    let mut scanner = Scanner::default();
+    for (i, &start) in doc.line_starts.iter().enumerate() {
+        let end = doc
+            .line_starts
+            .get(i + 1)
+            .map_or(input.len(), |&next| next - 1);
+        let line = &input[start..end];
+        if !in_value[i + 1] && line.trim_start().starts_with('#') {
+            scanner.line(start, line);
+        } else {
+            scanner.close();
+        }
+    }
+    scanner.finish()
+}
+
+/// Whether a value holds a PEM private key (terminated or not)
+pub(crate) fn holds_private_key(value: &str) -> bool {
+    value.contains("PRIVATE KEY")
+        && blocks(value)
+            .iter()
+            .any(|block| block.kind == ShelterPemKind::PrivateKey)
+}
+
+/// Fields of a certificate or certificate request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Certificate {
+    /// Distinguished name, e.g. `C=US, O=Example, CN=auth.example.com`
+    pub subject: Option<String>,
+    /// Issuer name (certificates only)
+    pub issuer: Option<String>,
+    /// Expiry in Unix seconds (certificates only)
+    pub not_after: Option<i64>,
+    /// Common name of the subject, for messages
+    common_name: Option<String>,
+}
+
+/// What a value's PEM blocks hold: a private key if there is one, else the
+/// first certificate or request (the leaf of a chain), else the first block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pem {
+    pub kind: ShelterPemKind,
+    pub label: String,
+    pub certificate: Option<Certificate>,
+    /// Number of blocks in the value
+    pub blocks: usize,
+}
+
+impl Pem {
+    pub fn from_blocks(value: &str, blocks: &[Block]) -> Option<Self> {
+        let rank = |block: &&Block| match block.kind {
+            ShelterPemKind::PrivateKey => 0,
+            ShelterPemKind::Certificate | ShelterPemKind::CertificateRequest => 1,
+            _ => 2,
+        };
+        let block = blocks.iter().min_by_key(rank)?;
+        Some(Pem {
+            kind: block.kind,
+            label: block.label.clone(),
+            certificate: block.certificate(value),
+            blocks: blocks.len(),
+        })
+    }
+}
+
+/// An `expired-certificate` warning, or an `expiring-certificate` notice when
+/// the certificate expires within `window` seconds of `now`
+pub(crate) fn expiry_diagnostic(
+    certificate: &Certificate,
+    now: i64,
+    window: i64,
+    line_number: usize,
+    (start, end): (usize, usize),
+    entry_index: Option<usize>,
+) -> Option<Diagnostic> {
+    let not_after = certificate.not_after?;
+    let subject = certificate
+        .common_name
+        .as_ref()
+        .map(|name| format!(" for {}", name))
+        .unwrap_or_default();
+    let (code, message, severity) = if not_after <= now {
+        (
+            "expired-certificate",
+            format!(
+                "Certificate{} expired {} ago",
+                subject,
+                describe_span(now.saturating_sub(not_after))
+            ),
+            ShelterSeverity::Warning,
+        )
+    } else if not_after.saturating_sub(now) <= window {
+        (
+            "expiring-certificate",
+            format!(
+                "Certificate{} expires in {}",
+                subject,
+                describe_span(not_after.saturating_sub(now))
+            ),
+            ShelterSeverity::Info,
+        )
+    } else {
+        return None;
+    };
+    Some(Diagnostic {
+        code,
+        message,
+        severity,
+        line_number,
+        start,
+        end,
+        entry_index,
+    })
+}
+
+// =============================================================================
+//  DER
+// =============================================================================
+
+/// Split the first DER element off `data`: tag, content and the rest
+fn element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&first, data) = data.split_first()?;
+    let (len, data) = if first < 0x80 {
+        (first as usize, data)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if !(1..=4).contains(&count) || data.len() < count {
+            return None;
+        }
+        let (bytes, data) = data.split_at(count);
+        (
+            bytes.iter().fold(0usize, |len, &b| len << 8 | b as usize),
+            data,
+        )
+    };
+    (len <= data.len()).then(|| (tag, &data[..len], &data[len..]))
+}
+
+/// Content of the first element of `data` if it has `tag`, and the rest
+fn expect(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (found, content, rest) = element(data)?;
+    (found == tag).then_some((content, rest))
+}
+
+/// `Certificate ::= SEQUENCE { tbsCertificate, ... }`
+fn parse_certificate(der: &[u8]) -> Option<Certificate> {
+    let (certificate, _) = expect(der, SEQUENCE)?;
+    let (tbs, _) = expect(certificate, SEQUENCE)?;
+    let tbs = match element(tbs)? {
+        (VERSION, _, rest) => rest,
+        _ => tbs,
+    };
+    let (_serial, rest) = expect(tbs, INTEGER)?;
+    let (_signature, rest) = expect(rest, SEQUENCE)?;
+    let (issuer, rest) = expect(rest, SEQUENCE)?;
+    let (validity, rest) = expect(rest, SEQUENCE)?;
+    let (subject, _) = expect(rest, SEQUENCE)?;
+
+    let (_, _, validity) = element(validity)?;
+    let (tag, not_after, _) = element(validity)?;
+    let (subject, common_name) = name(subject)?;
+    Some(Certificate {
+        subject,
+        issuer: name(issuer)?.0,
+        not_after: time(tag, not_after),
+        common_name,
+    })
+}
+
+/// `CertificationRequest ::= SEQUENCE { certificationRequestInfo, ... }`
+fn parse_request(der: &[u8]) -> Option<Certificate> {
+    let (request, _) = expect(der, SEQUENCE)?;
+    let (info, _) = expect(request, SEQUENCE)?;
+    let (_version, rest) = expect(info, INTEGER)?;
+    let (subject, _) = expect(rest, SEQUENCE)?;
+    let (subject, common_name) = name(subject)?;
+    Some(Certificate {
+        subject,
+        common_name,
+        ..Default::default()
+    })
+}
+
+/// A distinguished name as `C=US, O=Example, CN=host` and its common name
+fn name(mut rdns: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let mut parts = Vec::new();
+    let mut common_name = None;
+    while !rdns.is_empty() {
+        let (mut set, rest) = expect(rdns, SET)?;
+        rdns = rest;
+        while !set.is_empty() {
+            let (attribute, rest) = expect(set, SEQUENCE)?;
+            set = rest;
+            let (oid, value) = expect(attribute, OID)?;
+            let (tag, value, _) = element(value)?;
+            let value = string(tag, value);
+            let oid = dotted(oid)?;
+            let attribute = attribute_name(&oid).unwrap_or(&oid);
+            if attribute == "CN" && common_name.is_none() {
+                common_name = Some(value.clone());
+            }
+            parts.push(format!("{}={}", attribute, value));
+        }
+    }
+    Some(((!parts.is_empty()).then(|| parts.join(", ")), common_name))
+}
+
+/// Short name of a well-known attribute type
+fn attribute_name(oid: &str) -> Option<&'static str> {
+    Some(match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        "0.9.2342.19200300.100.1.25" => "DC",
+        _ => return None,
+    })
+}
+
+/// Text of a directory string (BMP strings are UTF-16, the rest is treated as
+/// UTF-8), without control characters
+fn string(tag: u8, value: &[u8]) -> String {
+    let text = match tag {
+        BMP_STRING => {
+            let units: Vec<u16> = value
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(value).into_owned(),
+    };
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Dotted form of an object identifier
+fn dotted(oid: &[u8]) -> Option<String> {
+    if oid.last().is_none_or(|&b| b & 0x80 != 0) {
+        return None;
+    }
+    let mut arcs = Vec::new();
+    let mut arc: u64 = 0;
+    for &b in oid {
+        arc = arc.checked_mul(128)? | (b & 0x7F) as u64;
+        if b & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+    let first = arcs[0];
+    let (a, b) = if first < 80 {
+        (first / 40, first % 40)
+    } else {
+        (2, first - 80)
+    };
+    let mut out = format!("{}.{}", a, b);
+    for arc in &arcs[1..] {
+        out.push_str(&format!(".{}", arc));
+    }
+    Some(out)
+}
+
+/// A `UTCTime` (`YYMMDDHHMMSSZ`) or `GeneralizedTime` (`YYYYMMDDHHMMSSZ`)
+/// in Unix seconds
+fn time(tag: u8, value: &[u8]) -> Option<i64> {
+    let text = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        UTC_TIME => {
+            let (year, rest) = text.split_at_checked(2)?;
+            let year: i64 = year.parse().ok()?;
+            (if year < 50 { 2000 + year } else { 1900 + year }, rest)
+        }
+        GENERALIZED_TIME => {
+            let (year, rest) = text.split_at_checked(4)?;
+            (year.parse().ok()?, rest)
+        }
+        _ => return None,
+    };
+    if rest.len() != 10 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| rest[i..i + 2].parse::<i64>().unwrap_or(0);
+    let (month, day) = (field(0), field(2));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * 86_400 + field(4) * 3_600 + field(6) * 60 + field(8))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,319 @@
+//! Rule-based mode selection
+//!
+//! A policy is a list of rules, each combining conditions on an entry (key,
+//! value, source path, entropy and randomness, secret type and its
+//! confidence, quoting, comment and export state) with the mode and highlight
+//! to use when all of them hold. Rules are tried by descending priority, ties
+//! in the order given, and the first match decides. Entries no rule matches
+//! fall through to the key and source patterns, so a policy can say "mask
+//! anything that looks like a credential" without naming keys.
+
+use crate::classify::classify;
+use crate::document::Pair;
+use crate::entropy::{randomness, shannon_entropy};
+use crate::ffi::str_from_raw;
+use crate::patterns::{PatternOptions, ShelterPatternSet};
+use crate::types::{ShelterConfidence, ShelterFlagCondition, ShelterPolicyRule, ShelterQuoteType};
+use regex::Regex;
+use std::slice;
+
+/// A validated policy rule
+#[derive(Debug, Clone)]
+pub(crate) struct PolicyRule {
+    key_glob: Option<ShelterPatternSet>,
+    key_regex: Option<Regex>,
+    value_regex: Option<Regex>,
+    source_glob: Option<ShelterPatternSet>,
+    secret_type: Option<ShelterPatternSet>,
+    min_confidence: Option<ShelterConfidence>,
+    min_entropy: f64,
+    quote_types: u8,
+    is_comment: Option<bool>,
+    is_exported: Option<bool>,
+    looks_random: Option<bool>,
+    priority: i32,
+    pub mode: String,
+    pub highlight_id: i32,
+}
+
+/// Optional string field of an FFI rule (empty = unset)
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes
+unsafe fn field<'a>(
+    ptr: *const std::ffi::c_char,
+    len: usize,
+    name: &str,
+) -> Result<Option<&'a str>, String> {
+    match str_from_raw(ptr, len) {
+        Some("") => Ok(None),
+        Some(s) => Ok(Some(s)),
+        None => Err(format!("{} is not valid UTF-8", name)),
+    }
+}
+
+/// Compile a single glob
+fn glob(pattern: &str, options: PatternOptions) -> Result<ShelterPatternSet, String> {
+    ShelterPatternSet::new([(pattern, "")], options)
+}
+
+/// Compile a regex, naming the field in errors
+fn regex(pattern: &str, name: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid {}: {}", name, e))
+}
+
+/// Convert a confidence level (0 = no condition)
+fn confidence(value: u8) -> Result<Option<ShelterConfidence>, String> {
+    match value {
+        0 => Ok(None),
+        1 => Ok(Some(ShelterConfidence::Low)),
+        2 => Ok(Some(ShelterConfidence::Medium)),
+        3 => Ok(Some(ShelterConfidence::High)),
+        other => Err(format!("Unknown confidence: {}", other)),
+    }
+}
+
+/// Convert a flag condition
+fn flag(value: u8, name: &str) -> Result<Option<bool>, String> {
+    match value {
+        v if v == ShelterFlagCondition::Any as u8 => Ok(None),
+        v if v == ShelterFlagCondition::Set as u8 => Ok(Some(true)),
+        v if v == ShelterFlagCondition::Unset as u8 => Ok(Some(false)),
+        other => Err(format!("Unknown {} condition: {}", name, other)),
+    }
+}
+
+impl PolicyRule {
+    /// Validate an FFI rule
+    ///
+    /// # Safety
+    /// All string fields must be null or valid for their lengths
+    pub(crate) unsafe fn from_ffi(rule: &ShelterPolicyRule) -> Result<Self, String> {
+        let mode = field(rule.mode, rule.mode_len, "Mode")?.ok_or("Policy rule has no mode")?;
+        if rule.min_entropy.is_nan() || rule.min_entropy < 0.0 {
+            return Err("min_entropy must be >= 0".to_owned());
+        }
+        if rule.quote_types > 0b111 {
+            return Err(format!("Unknown quote types: {:#b}", rule.quote_types));
+        }
+
+        let case_insensitive = rule.case_insensitive != 0;
+        let keys = PatternOptions {
+            case_insensitive,
+            paths: false,
+        };
+        let paths = PatternOptions {
+            case_insensitive,
+            paths: true,
+        };
+
+        Ok(PolicyRule {
+            key_glob: field(rule.key_glob, rule.key_glob_len, "Key glob")?
+                .map(|p| glob(p, keys))
+                .transpose()?,
+            key_regex: field(rule.key_regex, rule.key_regex_len, "Key regex")?
+                .map(|p| regex(p, "key regex"))
+                .transpose()?,
+            value_regex: field(rule.value_regex, rule.value_regex_len, "Value regex")?
+                .map(|p| regex(p, "value regex"))
+                .transpose()?,
+            source_glob: field(rule.source_glob, rule.source_glob_len, "Source glob")?
+                .map(|p| glob(p, paths))
+                .transpose()?,
+            secret_type: field(rule.secret_type, rule.secret_type_len, "Secret type")?
+                .map(|p| glob(p, PatternOptions::default()))
+                .transpose()?,
+            min_confidence: confidence(rule.min_confidence)?,
+            min_entropy: rule.min_entropy,
+            quote_types: rule.quote_types,
+            is_comment: flag(rule.is_comment, "comment")?,
+            is_exported: flag(rule.is_exported, "export")?,
+            looks_random: flag(rule.looks_random, "randomness")?,
+            priority: rule.priority,
+            mode: mode.to_owned(),
+            highlight_id: rule.highlight_id,
+        })
+    }
+
+    /// Whether every condition holds for an entry
+    #[inline]
+    pub(crate) fn matches(&self, pair: &Pair, source: Option<&str>) -> bool {
+        self.check(pair, source).is_ok()
+    }
+
+    /// Check every condition, returning the name of the first that fails
+    ///
+    /// Cheap conditions are checked first; entropy and the value regex last.
+    pub(crate) fn check(&self, pair: &Pair, source: Option<&str>) -> Result<(), &'static str> {
+        let kv = &pair.kv;
+        if self.is_comment.is_some_and(|c| c != kv.is_comment) {
+            return Err("is_comment");
+        }
+        if self.is_exported.is_some_and(|e| e != kv.is_exported) {
+            return Err("is_exported");
+        }
+        if self.quote_types != 0
+            && self.quote_types & (1 << ShelterQuoteType::from(kv.quote) as u8) == 0
+        {
+            return Err("quote");
+        }
+        if self.key_glob.as_ref().is_some_and(|g| !g.is_match(&kv.key)) {
+            return Err("key");
+        }
+        if self
+            .key_regex
+            .as_ref()
+            .is_some_and(|r| !r.is_match(&kv.key))
+        {
+            return Err("key_regex");
+        }
+        if let Some(glob) = &self.source_glob {
+            if !source.is_some_and(|source| glob.is_match(source)) {
+                return Err("source");
+            }
+        }
+        if self.secret_type.is_some() || self.min_confidence.is_some() {
+            let classification = classify(&kv.value);
+            if let Some(glob) = &self.secret_type {
+                if !classification.is_some_and(|c| glob.is_match(c.id)) {
+                    return Err("secret_type");
+                }
+            }
+            if let Some(min) = self.min_confidence {
+                if classification.map_or(ShelterConfidence::None, |c| c.confidence) < min {
+                    return Err("min_confidence");
+                }
+            }
+        }
+        if self.min_entropy > 0.0 && shannon_entropy(&kv.value) < self.min_entropy {
+            return Err("min_entropy");
+        }
+        if self
+            .looks_random
+            .is_some_and(|r| r != randomness(&kv.value).looks_random)
+        {
+            return Err("looks_random");
+        }
+        if self
+            .value_regex
+            .as_ref()
+            .is_some_and(|r| !r.is_match(&kv.value))
+        {
+            return Err("value_regex");
+        }
+        Ok(())
+    }
+
+    /// Priority of the rule (higher is tried first)
+    #[inline]
+    pub(crate) fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Conditions of the rule as `name=value` pairs, e.g.
+    /// `key=*_TOKEN min_entropy=3.5`
+    pub(crate) fn summary(&self) -> String {
+        let glob = |set: &ShelterPatternSet| set.rules()[0].pattern.clone();
+        let flag = |value: bool| if value { "yes" } else { "no" }.to_owned();
+        let quotes = || {
+            ["none", "single", "double"]
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| self.quote_types & (1 << bit) != 0)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let conditions = [
+            ("key", self.key_glob.as_ref().map(glob)),
+            ("key_regex", self.key_regex.as_ref().map(|r| r.to_string())),
+            (
+                "value_regex",
+                self.value_regex.as_ref().map(|r| r.to_string()),
+            ),
+            ("source", self.source_glob.as_ref().map(glob)),
+            ("secret_type", self.secret_type.as_ref().map(glob)),
+            (
+                "min_confidence",
+                self.min_confidence
+                    .map(|c| format!("{:?}", c).to_lowercase()),
+            ),
+            (
+                "min_entropy",
+                (self.min_entropy > 0.0).then(|| self.min_entropy.to_string()),
+            ),
+            ("quote", (self.quote_types != 0).then(quotes)),
+            ("is_comment", self.is_comment.map(flag)),
+            ("is_exported", self.is_exported.map(flag)),
+            ("looks_random", self.looks_random.map(flag)),
+        ];
+        let summary: Vec<String> = conditions
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| format!("{}={}", name, value)))
+            .collect();
+        if summary.is_empty() {
+            "always".to_owned()
+        } else {
+            summary.join(" ")
+        }
+    }
+}
+
+/// Compiled policy rules (opaque to C)
+#[derive(Debug, Clone, Default)]
+pub struct ShelterPolicy {
+    rules: Vec<PolicyRule>,
+    /// Rule indices by descending priority, ties in the order given
+    order: Vec<usize>,
+}
+
+impl ShelterPolicy {
+    /// Compile FFI rules
+    ///
+    /// # Safety
+    /// `rules` must be null or valid for `count` rules with valid strings
+    pub(crate) unsafe fn from_ffi(
+        rules: *const ShelterPolicyRule,
+        count: usize,
+    ) -> Result<Self, String> {
+        if rules.is_null() || count == 0 {
+            return Ok(ShelterPolicy::default());
+        }
+        let rules = slice::from_raw_parts(rules, count)
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                PolicyRule::from_ffi(rule).map_err(|e| format!("Policy rule {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut order: Vec<usize> = (0..rules.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(rules[i].priority));
+        Ok(ShelterPolicy { rules, order })
+    }
+
+    /// Rules in the order given
+    #[inline]
+    pub(crate) fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// Rule indices in the order they are tried
+    #[inline]
+    pub(crate) fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// First rule in priority order whose conditions hold, with its index
+    pub(crate) fn evaluate(
+        &self,
+        pair: &Pair,
+        source: Option<&str>,
+    ) -> Option<(usize, &PolicyRule)> {
+        self.order
+            .iter()
+            .map(|&i| (i, &self.rules[i]))
+            .find(|(_, rule)| rule.matches(pair, source))
+    }
+}
@@ -0,0 +1,137 @@
+//! Public key prefixes
+//!
+//! Frontend build tools inline variables with certain prefixes into client
+//! bundles (`NEXT_PUBLIC_`, `VITE_`, `REACT_APP_`, ...), so their values are
+//! public by design. A public key holding a secret is a leak, reported as a
+//! `public-secret` error. Other public values are visible by default: after
+//! key and source patterns, before the default mode.
+//!
+//! A value is secret when the classifier recognises a secret format (not a
+//! publishable key) or it is an encoded payload carrying one.
+
+use crate::classify::classify;
+use crate::diagnostics::Diagnostic;
+use crate::ffi::str_from_raw;
+use crate::payload::hides_secret;
+use crate::types::{ShelterExposure, ShelterSeverity};
+use std::ffi::c_char;
+
+/// Prefixes used when none are configured
+pub(crate) const DEFAULT_PUBLIC_PREFIXES: &[&str] = &[
+    "NEXT_PUBLIC_",
+    "VITE_",
+    "REACT_APP_",
+    "EXPO_PUBLIC_",
+    "PUBLIC_",
+];
+
+/// Mode public values get when no pattern chose one
+pub(crate) const PUBLIC_MODE: &str = "none";
+
+/// Key prefixes that are bundled into client code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PublicPrefixes(Vec<String>);
+
+impl Default for PublicPrefixes {
+    fn default() -> Self {
+        PublicPrefixes(
+            DEFAULT_PUBLIC_PREFIXES
+                .iter()
+                .map(|&p| p.to_owned())
+                .collect(),
+        )
+    }
+}
+
+/// How exposed an entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Exposure {
+    /// No public prefix
+    Private,
+    /// Public prefix and no secret
+    Public,
+    /// Public prefix holding a secret: what it holds, e.g. `stripe`
+    Leaked(&'static str),
+}
+
+impl Exposure {
+    #[inline]
+    pub(crate) fn to_ffi(self) -> ShelterExposure {
+        match self {
+            Exposure::Private => ShelterExposure::Private,
+            Exposure::Public => ShelterExposure::Public,
+            Exposure::Leaked(_) => ShelterExposure::Leaked,
+        }
+    }
+}
+
+impl PublicPrefixes {
+    /// Prefixes from newline-separated FFI text (null = the defaults, empty =
+    /// none)
+    ///
+    /// # Safety
+    /// `text` must be null or valid for `len` bytes
+    pub(crate) unsafe fn from_raw(text: *const c_char, len: usize) -> Result<Self, String> {
+        if text.is_null() {
+            return Ok(Self::default());
+        }
+        let text = str_from_raw(text, len).ok_or("Public prefixes must be valid UTF-8")?;
+        Ok(PublicPrefixes(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        ))
+    }
+
+    /// Longest prefix `key` starts with (the key must not be the bare prefix)
+    pub(crate) fn matching(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .filter(|prefix| key.len() > prefix.len() && key.starts_with(prefix.as_str()))
+            .max_by_key(|prefix| prefix.len())
+            .map(String::as_str)
+    }
+
+    /// How exposed `value` is under `key`
+    pub(crate) fn exposure(&self, key: &str, value: &str) -> Exposure {
+        if self.matching(key).is_none() {
+            return Exposure::Private;
+        }
+        match classify(value) {
+            Some(classification) if classification.secret => Exposure::Leaked(classification.id),
+            _ if hides_secret(value) => Exposure::Leaked("encoded"),
+            _ => Exposure::Public,
+        }
+    }
+}
+
+/// A `public-secret` error for a key with a public `prefix` holding a `secret`
+/// (what the value was recognised as, e.g. `stripe`), reported on the key
+pub(crate) fn leak_diagnostic(
+    key: &str,
+    prefix: &str,
+    secret: &str,
+    line_number: usize,
+    (start, end): (usize, usize),
+    entry_index: usize,
+) -> Diagnostic {
+    let article = if secret.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    };
+    Diagnostic {
+        code: "public-secret",
+        message: format!(
+            "{} holds {} {} secret, but {} variables are bundled into client code",
+            key, article, secret, prefix
+        ),
+        severity: ShelterSeverity::Error,
+        line_number,
+        start,
+        end,
+        entry_index: Some(entry_index),
+    }
+}
@@ -0,0 +1,569 @@
+//! Native render plans and masked documents
+//!
+//! Parses a document, selects a mode per entry and produces either
+//! ready-to-apply overlay chunks (quote handling, multi-line splitting and
+//! padding of masks narrower than the value all happen here, so Lua only places
+//! extmarks) or a masked copy of the text with an offset mapping.
+//!
+//! PEM private keys pasted into comments are masked too, with the `full` mode
+//! and whether or not comments are skipped; their overlays and spans belong to
+//! no entry (`entry_index` is `usize::MAX`).
+
+use crate::display::{end_cell, str_cells, DEFAULT_TABSTOP};
+use crate::document::{Document, Pair};
+use crate::ffi::str_from_raw;
+use crate::modes::{Mode, ShelterModeTable};
+use crate::patterns::{PatternOptions, ShelterPatternSet};
+use crate::pem::comment_blocks;
+use crate::policy::ShelterPolicy;
+use crate::public::{Exposure, PublicPrefixes, PUBLIC_MODE};
+use crate::reveal::Reveal;
+use crate::types::{
+    ShelterOffsetSpan, ShelterPatternRule, ShelterPemKind, ShelterQuoteType, ShelterRenderConfig,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::slice;
+use std::sync::OnceLock;
+
+/// Built-in modes used when no mode table is supplied
+fn default_modes() -> &'static ShelterModeTable {
+    static DEFAULT: OnceLock<ShelterModeTable> = OnceLock::new();
+    DEFAULT.get_or_init(ShelterModeTable::default)
+}
+
+/// An overlay chunk before conversion to its FFI form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Overlay {
+    pub row: usize,
+    pub col: usize,
+    pub end_col: usize,
+    pub text: String,
+    pub entry_index: usize,
+    pub hl_id: i32,
+    pub is_continuation: bool,
+}
+
+/// Mode and highlight chosen for an entry
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Selection<'t> {
+    pub mode: &'t Mode,
+    pub hl_id: i32,
+    /// Index of the policy rule that decided, if any
+    pub policy_rule: Option<usize>,
+}
+
+/// Owned, validated render configuration
+pub(crate) struct RenderSettings<'t> {
+    pub modes: &'t ShelterModeTable,
+    pub default_mode: String,
+    pub key_rules: Cow<'t, ShelterPatternSet>,
+    pub source_rules: Cow<'t, ShelterPatternSet>,
+    pub policy: Option<&'t ShelterPolicy>,
+    pub source: Option<String>,
+    /// Key prefixes bundled into client code
+    pub public_prefixes: PublicPrefixes,
+    pub tabstop: usize,
+    pub reveals: HashMap<u64, Reveal>,
+    pub highlight_id: i32,
+    pub skip_comments: bool,
+    /// Honour inline `# shelter:` directives
+    pub directives: bool,
+}
+
+/// Compile FFI pattern rules into a set
+///
+/// # Safety
+/// `rules` must be null or valid for `count` elements with valid strings
+pub(crate) unsafe fn rules_from_ffi(
+    rules: *const ShelterPatternRule,
+    count: usize,
+    options: PatternOptions,
+) -> Result<ShelterPatternSet, String> {
+    if rules.is_null() || count == 0 {
+        return Ok(ShelterPatternSet::default());
+    }
+    let pairs = slice::from_raw_parts(rules, count)
+        .iter()
+        .map(|rule| {
+            let pattern =
+                str_from_raw(rule.pattern, rule.pattern_len).ok_or("Pattern is not valid UTF-8")?;
+            let mode = str_from_raw(rule.mode, rule.mode_len).ok_or("Mode is not valid UTF-8")?;
+            Ok((pattern, mode))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    ShelterPatternSet::new(pairs, options)
+}
+
+/// A precompiled set, or one compiled from rules
+///
+/// # Safety
+/// `set` must be null or valid for `'t`; `rules` as for `rules_from_ffi`
+unsafe fn pattern_set<'t>(
+    set: *const ShelterPatternSet,
+    rules: *const ShelterPatternRule,
+    count: usize,
+    options: PatternOptions,
+) -> Result<Cow<'t, ShelterPatternSet>, String> {
+    if set.is_null() {
+        rules_from_ffi(rules, count, options).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(&*set))
+    }
+}
+
+impl<'t> RenderSettings<'t> {
+    /// Convert an FFI render config (null = defaults)
+    ///
+    /// # Safety
+    /// `config` must be null or point to a valid `ShelterRenderConfig`
+    pub(crate) unsafe fn from_ffi(config: *const ShelterRenderConfig) -> Result<Self, String> {
+        let config = if config.is_null() {
+            ShelterRenderConfig::default()
+        } else {
+            *config
+        };
+
+        let modes = if config.modes.is_null() {
+            default_modes()
+        } else {
+            &*config.modes
+        };
+
+        let default_mode = match str_from_raw(config.default_mode, config.default_mode_len) {
+            Some("") => "full".to_owned(),
+            Some(mode) => mode.to_owned(),
+            None => return Err("Default mode is not valid UTF-8".to_owned()),
+        };
+
+        let source = match str_from_raw(config.source, config.source_len) {
+            Some("") => None,
+            Some(source) => Some(source.to_owned()),
+            None => return Err("Source is not valid UTF-8".to_owned()),
+        };
+
+        let reveals = if config.reveals.is_null() || config.reveal_count == 0 {
+            HashMap::new()
+        } else {
+            slice::from_raw_parts(config.reveals, config.reveal_count)
+                .iter()
+                .map(|r| Ok((r.id, Reveal::from_ffi(r)?)))
+                .collect::<Result<_, String>>()?
+        };
+
+        Ok(RenderSettings {
+            modes,
+            default_mode,
+            key_rules: pattern_set(
+                config.key_set,
+                config.key_patterns,
+                config.key_pattern_count,
+                PatternOptions::default(),
+            )?,
+            source_rules: pattern_set(
+                config.source_set,
+                config.source_patterns,
+                config.source_pattern_count,
+                PatternOptions {
+                    paths: true,
+                    ..Default::default()
+                },
+            )?,
+            policy: config.policy.as_ref(),
+            source,
+            public_prefixes: PublicPrefixes::from_raw(
+                config.public_prefixes,
+                config.public_prefixes_len,
+            )?,
+            tabstop: if config.tabstop == 0 {
+                DEFAULT_TABSTOP
+            } else {
+                config.tabstop
+            },
+            reveals,
+            highlight_id: config.highlight_id,
+            skip_comments: config.skip_comments != 0,
+            directives: config.ignore_directives == 0,
+        })
+    }
+
+    /// Mode name for an entry: key pattern → source pattern → public prefix
+    /// (`none` unless the value is secret) → default
+    pub(crate) fn mode_name(&self, pair: &Pair) -> &str {
+        let key = pair.kv.key.as_ref();
+        if let Some(rule) = self.key_rules.best_match(key) {
+            return &rule.mode;
+        }
+        if let Some(source) = &self.source {
+            if let Some(rule) = self.source_rules.best_match(source) {
+                return &rule.mode;
+            }
+        }
+        if self.public_prefixes.exposure(key, &pair.kv.value) == Exposure::Public {
+            return PUBLIC_MODE;
+        }
+        &self.default_mode
+    }
+
+    /// Reveal level for an entry id
+    #[inline]
+    pub(crate) fn reveal_for(&self, id: u64) -> Reveal {
+        self.reveals.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Resolve a mode by name, falling back to `full` for unknown names
+    fn resolve(&self, name: &str) -> &'t Mode {
+        let modes = self.modes;
+        modes
+            .get(name)
+            .or_else(|| modes.get("full"))
+            .unwrap_or_else(|| default_modes().get("full").expect("built-in full mode"))
+    }
+
+    /// Mode and highlight for an entry: inline directive → policy rule → key
+    /// and source patterns → public prefix
+    pub(crate) fn select(&self, pair: &Pair) -> Selection<'t> {
+        if let Some(directive) = pair.directive.filter(|_| self.directives) {
+            return Selection {
+                mode: self.resolve(directive.mode),
+                hl_id: self.highlight_id,
+                policy_rule: None,
+            };
+        }
+        let matched = self
+            .policy
+            .and_then(|policy| policy.evaluate(pair, self.source.as_deref()));
+        match matched {
+            Some((index, rule)) => Selection {
+                mode: self.resolve(&rule.mode),
+                hl_id: if rule.highlight_id != 0 {
+                    rule.highlight_id
+                } else {
+                    self.highlight_id
+                },
+                policy_rule: Some(index),
+            },
+            None => Selection {
+                mode: self.resolve(self.mode_name(pair)),
+                hl_id: self.highlight_id,
+                policy_rule: None,
+            },
+        }
+    }
+}
+
+/// Cell range a byte range of a line occupies on screen
+#[inline]
+fn cell_range(line: &str, start: usize, end: usize, tabstop: usize) -> (usize, usize) {
+    let start_cell = end_cell(&line[..start], 0, tabstop);
+    (start_cell, end_cell(&line[start..end], start_cell, tabstop))
+}
+
+/// Pad `mask` with spaces so it covers at least `width` cells from `col`
+fn pad_to_width(mut mask: String, col: usize, width: usize, tabstop: usize) -> String {
+    let mask_width = end_cell(&mask, col, tabstop) - col;
+    if mask_width < width {
+        mask.extend(std::iter::repeat_n(' ', width - mask_width));
+    }
+    mask
+}
+
+/// Byte offset where a 1-based line ends (excluding `\n` and a trailing `\r`)
+pub(crate) fn line_end(input: &str, doc: &Document, line_number: usize) -> usize {
+    let end = doc
+        .line_starts
+        .get(line_number)
+        .map(|&next| next - 1)
+        .unwrap_or(input.len());
+    if end > 0 && input.as_bytes().get(end - 1) == Some(&b'\r') {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// Byte ranges of an entry's raw content `raw` that a structured mode hides,
+/// when it hides only parts of the value
+pub(crate) fn hidden_raw_ranges(
+    raw: &str,
+    pair: &Pair,
+    mode: &Mode,
+    reveal: Reveal,
+) -> Option<Vec<(usize, usize)>> {
+    if reveal != Reveal::Hidden {
+        return None;
+    }
+    pair.raw_ranges(raw, &mode.hidden_ranges(&pair.kv.value)?)
+}
+
+/// Overlays for one entry
+fn render_pair(
+    input: &str,
+    doc: &Document,
+    index: usize,
+    pair: &Pair,
+    selection: Selection,
+    settings: &RenderSettings,
+    out: &mut Vec<Overlay>,
+) {
+    let (mode, hl_id, tabstop) = (selection.mode, selection.hl_id, settings.tabstop);
+    let reveal = settings.reveal_for(pair.id);
+    if mode.leaves_visible(&pair.kv.value) || reveal.is_full() {
+        return;
+    }
+
+    let (content_start, content_end) = pair.content_range();
+    if content_end <= content_start || content_end > input.len() {
+        return;
+    }
+
+    // Structured values: one overlay per credential or JSON leaf, the rest
+    // stays text
+    if let Some(ranges) = hidden_raw_ranges(&input[content_start..content_end], pair, mode, reveal)
+    {
+        for (start, end) in ranges {
+            let (start, end) = (content_start + start, content_start + end);
+            let first_line = doc.line_starts.partition_point(|&s| s <= start);
+            let last_line = doc.line_starts.partition_point(|&s| s < end);
+            for line_number in first_line..=last_line {
+                let line_start = doc.line_start(line_number);
+                let start = start.max(line_start);
+                let end = end.min(line_end(input, doc, line_number));
+                if end <= start {
+                    continue;
+                }
+                let (col, end_col) = (start - line_start, end - line_start);
+                let (start_cell, end_cell) =
+                    cell_range(&input[line_start..], col, end_col, tabstop);
+                out.push(Overlay {
+                    row: line_number - 1,
+                    col,
+                    end_col,
+                    text: pad_to_width(
+                        mode.mask_hidden(&input[start..end]),
+                        start_cell,
+                        end_cell - start_cell,
+                        tabstop,
+                    ),
+                    entry_index: index,
+                    hl_id,
+                    is_continuation: line_number != pair.line_number,
+                });
+            }
+        }
+        return;
+    }
+
+    if pair.value_end_line <= pair.line_number {
+        let raw = &input[content_start..content_end];
+        let line_start = doc.line_start(pair.line_number);
+        let (col, end_col) = (content_start - line_start, content_end - line_start);
+        let (start_cell, end_cell) = cell_range(&input[line_start..], col, end_col, tabstop);
+
+        // Length-preserving full masks take the on-screen width, which depends
+        // on where tabs land
+        let masked = reveal.apply_with_width(mode, &pair.kv.value, end_cell - start_cell);
+        if masked == raw {
+            return;
+        }
+        out.push(Overlay {
+            row: pair.line_number - 1,
+            col,
+            end_col,
+            text: pad_to_width(masked, start_cell, end_cell - start_cell, tabstop),
+            entry_index: index,
+            hl_id,
+            is_continuation: false,
+        });
+        return;
+    }
+
+    // Multi-line: one overlay per line segment, fully masked (partial reveals
+    // do not apply)
+    for line_number in pair.line_number..=pair.value_end_line {
+        let line_start = doc.line_start(line_number);
+        let start = content_start.max(line_start);
+        let end = content_end.min(line_end(input, doc, line_number));
+        if end <= start {
+            continue;
+        }
+        let (col, end_col) = (start - line_start, end - line_start);
+        let (start_cell, end_cell) = cell_range(&input[line_start..], col, end_col, tabstop);
+        out.push(Overlay {
+            row: line_number - 1,
+            col,
+            end_col,
+            text: mode.fill(end_cell - start_cell),
+            entry_index: index,
+            hl_id,
+            is_continuation: line_number != pair.line_number,
+        });
+    }
+}
+
+/// Byte ranges of the base64 lines of private keys in comments
+fn comment_keys(input: &str, doc: &Document) -> Vec<(usize, usize)> {
+    comment_blocks(input, doc)
+        .into_iter()
+        .filter(|block| block.kind == ShelterPemKind::PrivateKey)
+        .flat_map(|block| block.body)
+        .collect()
+}
+
+/// Build the render plan for a parsed document
+pub(crate) fn render_plan(input: &str, doc: &Document, settings: &RenderSettings) -> Vec<Overlay> {
+    let keys = comment_keys(input, doc);
+    let key_lines: Vec<usize> = keys
+        .iter()
+        .map(|&(start, _)| doc.line_number(start))
+        .collect();
+
+    let mut overlays = Vec::with_capacity(doc.pairs.len());
+    for (index, pair) in doc.pairs.iter().enumerate() {
+        // Commented-out "pairs" korni finds in a key's base64 are covered below
+        if pair.kv.is_comment && (settings.skip_comments || key_lines.contains(&pair.line_number)) {
+            continue;
+        }
+        let selection = settings.select(pair);
+        render_pair(input, doc, index, pair, selection, settings, &mut overlays);
+    }
+    if keys.is_empty() {
+        return overlays;
+    }
+
+    let mode = settings.resolve("full");
+    for (i, (&(start, end), &line_number)) in keys.iter().zip(&key_lines).enumerate() {
+        let line_start = doc.line_start(line_number);
+        let (col, end_col) = (start - line_start, end - line_start);
+        let (start_cell, end_cell) =
+            cell_range(&input[line_start..], col, end_col, settings.tabstop);
+        overlays.push(Overlay {
+            row: line_number - 1,
+            col,
+            end_col,
+            text: mode.fill(end_cell - start_cell),
+            entry_index: usize::MAX,
+            hl_id: settings.highlight_id,
+            is_continuation: i > 0 && key_lines[i - 1] + 1 == line_number,
+        });
+    }
+    overlays.sort_by_key(|overlay| (overlay.row, overlay.col));
+    overlays
+}
+
+/// Escape masked text so it stays valid inside the value's quotes
+fn escape_for_quote(text: String, quote: ShelterQuoteType) -> String {
+    if quote != ShelterQuoteType::Double || !text.contains(['\\', '"', '\n', '\r']) {
+        return text;
+    }
+    let mut out = String::with_capacity(text.len() + 4);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Masked replacement for an entry's raw content, or None to keep it
+fn masked_content(raw: &str, pair: &Pair, mode: &Mode, reveal: Reveal) -> Option<String> {
+    if mode.leaves_visible(&pair.kv.value) || reveal.is_full() || raw.is_empty() {
+        return None;
+    }
+
+    if let Some(ranges) = hidden_raw_ranges(raw, pair, mode, reveal) {
+        let quote = ShelterQuoteType::from(pair.kv.quote);
+        let mut masked = String::with_capacity(raw.len());
+        let mut cursor = 0;
+        for (start, end) in ranges {
+            masked.push_str(&raw[cursor..start]);
+            masked.push_str(&escape_for_quote(mode.mask_hidden(&raw[start..end]), quote));
+            cursor = end;
+        }
+        masked.push_str(&raw[cursor..]);
+        return (masked != raw).then_some(masked);
+    }
+
+    if pair.value_end_line > pair.line_number {
+        // Multi-line: keep line breaks, mask each line on its own
+        let masked = raw
+            .split('\n')
+            .map(|line| match line.strip_suffix('\r') {
+                Some(line) => mode.mask_hidden(line) + "\r",
+                None => mode.mask_hidden(line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Some(masked);
+    }
+
+    let masked = escape_for_quote(
+        reveal.apply(mode, &pair.kv.value),
+        ShelterQuoteType::from(pair.kv.quote),
+    );
+    (masked != raw).then_some(masked)
+}
+
+/// Render a masked copy of the document
+///
+/// Everything outside replaced value contents is copied byte-for-byte. Returns
+/// the text and one span per replaced value, in document order.
+pub(crate) fn render_masked(
+    input: &str,
+    doc: &Document,
+    settings: &RenderSettings,
+) -> (String, Vec<ShelterOffsetSpan>) {
+    let keys = comment_keys(input, doc);
+
+    // (start, end, replacement, entry index) in document order
+    let mut replacements = Vec::new();
+    for (index, pair) in doc.pairs.iter().enumerate() {
+        if pair.kv.is_comment && settings.skip_comments {
+            continue;
+        }
+        let (start, end) = pair.content_range();
+        if end > input.len() {
+            continue;
+        }
+        let mode = settings.select(pair).mode;
+        let reveal = settings.reveal_for(pair.id);
+        if let Some(masked) = masked_content(&input[start..end], pair, mode, reveal) {
+            replacements.push((start, end, masked, index));
+        }
+    }
+    if !keys.is_empty() {
+        let mode = settings.resolve("full");
+        for (start, end) in keys {
+            let masked = mode.fill(str_cells(&input[start..end]));
+            replacements.push((start, end, masked, usize::MAX));
+        }
+        replacements.sort_by_key(|&(start, ..)| start);
+    }
+
+    let mut out = String::with_capacity(input.len());
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    for (start, end, masked, index) in replacements {
+        if start < cursor {
+            continue;
+        }
+        out.push_str(&input[cursor..start]);
+        let masked_start = out.len();
+        out.push_str(&masked);
+        spans.push(ShelterOffsetSpan {
+            original_start: start,
+            original_end: end,
+            masked_start,
+            masked_end: out.len(),
+            entry_index: index,
+        });
+        cursor = end;
+    }
+
+    out.push_str(&input[cursor..]);
+    (out, spans)
+}
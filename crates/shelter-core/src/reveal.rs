@@ -0,0 +1,88 @@
+//! Progressive reveal of masked values
+//!
+//! A reveal level shows part of one entry's value on top of its mode: some
+//! leading/trailing characters, the public prefix of a recognised token, or
+//! everything. Levels are keyed by stable entry id (see `identity`), so they
+//! survive edits elsewhere in the file.
+//!
+//! Partial levels replace the mode's mask for single-line values; multi-line
+//! values stay masked unless fully revealed.
+
+use crate::classify::public_prefix;
+use crate::modes::Mode;
+use crate::types::ShelterReveal;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How much of a value to reveal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Reveal {
+    /// Masked as the mode says
+    #[default]
+    Hidden,
+    /// Show `leading`/`trailing` graphemes, mask the rest
+    Edges { leading: usize, trailing: usize },
+    /// Show the public prefix of a recognised token, mask the rest
+    Prefix,
+    /// Show the whole value
+    Full,
+}
+
+impl Reveal {
+    /// Validate an FFI reveal level
+    pub(crate) fn from_ffi(reveal: &ShelterReveal) -> Result<Self, String> {
+        match reveal.kind {
+            0 => Ok(Reveal::Hidden),
+            1 => Ok(Reveal::Edges {
+                leading: reveal.leading,
+                trailing: reveal.trailing,
+            }),
+            2 => Ok(Reveal::Prefix),
+            3 => Ok(Reveal::Full),
+            other => Err(format!("Unknown reveal kind: {}", other)),
+        }
+    }
+
+    /// Whether the value is shown as-is
+    #[inline]
+    pub(crate) fn is_full(self) -> bool {
+        self == Reveal::Full
+    }
+
+    /// Mask a single-line value under this level
+    ///
+    /// Unrevealed parts are masked with the mode's glyph and length policy. A
+    /// prefix reveal of a value with no recognised prefix keeps the mode's
+    /// mask.
+    pub(crate) fn apply(self, mode: &Mode, value: &str) -> String {
+        if mode.leaves_visible(value) {
+            return value.to_owned();
+        }
+        match self {
+            Reveal::Hidden => mode.apply(value),
+            Reveal::Full => value.to_owned(),
+            Reveal::Edges { leading, trailing } => {
+                let graphemes: Vec<&str> = value.graphemes(true).collect();
+                let len = graphemes.len();
+                if leading.saturating_add(trailing) >= len {
+                    return value.to_owned();
+                }
+                graphemes[..leading].concat()
+                    + &mode.mask_hidden(&graphemes[leading..len - trailing].concat())
+                    + &graphemes[len - trailing..].concat()
+            }
+            Reveal::Prefix => match public_prefix(value) {
+                Some(prefix) => prefix.to_owned() + &mode.mask_hidden(&value[prefix.len()..]),
+                None => mode.apply(value),
+            },
+        }
+    }
+
+    /// Same as `apply`, with the mode's own mask taking `cells` when hidden
+    /// (see `Mode::apply_with_width`)
+    pub(crate) fn apply_with_width(self, mode: &Mode, value: &str, cells: usize) -> String {
+        match self {
+            Reveal::Hidden => mode.apply_with_width(value, cells),
+            _ => self.apply(mode, value),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Safe value detection for the `smart` mode
+//!
+//! The smart mode leaves values visible when they are obviously not secret
+//! and masks everything else, deciding in order:
+//! 1. tokens the classifier recognises are masked if secret and visible if not
+//!    (publishable keys, certificates)
+//! 2. random-looking values (see `entropy`) are masked
+//! 3. values with one of the enabled safe shapes, or matching a configured
+//!    safe value glob, are visible
+//!
+//! Safe shapes:
+//! - booleans: `true`, `false`, `yes`, `no`, `on`, `off`, `enabled`, `disabled`
+//! - small integers: up to five digits, optionally signed
+//! - loopback URLs: `localhost`, `127.x.x.x`, `[::1]` or `0.0.0.0` with an
+//!   optional scheme, port and path, but no credentials or query
+//! - file paths: absolute, `./`, `../`, `~/` or a Windows drive
+//! - enums: common environment names and log levels (`development`, `debug`,
+//!   ...)
+
+use crate::classify::classify;
+use crate::entropy::randomness;
+use crate::patterns::ShelterPatternSet;
+use crate::types::ShelterSafeShape;
+
+/// Every safe shape, as a bitmask of `1 << ShelterSafeShape`
+pub(crate) const ALL_SHAPES: u8 = 0b11111;
+
+/// Boolean spellings
+pub(crate) const BOOLEANS: &[&str] = &[
+    "true", "false", "yes", "no", "on", "off", "enabled", "disabled",
+];
+
+/// Well-known enum values (environment names, log levels, formats)
+const ENUMS: &[&str] = &[
+    "development",
+    "dev",
+    "production",
+    "prod",
+    "staging",
+    "stage",
+    "test",
+    "testing",
+    "local",
+    "preview",
+    "trace",
+    "debug",
+    "info",
+    "warn",
+    "warning",
+    "error",
+    "fatal",
+    "silent",
+    "verbose",
+    "none",
+    "auto",
+    "json",
+    "text",
+    "pretty",
+    "utc",
+];
+
+/// Whether `value` is one of `words`, ignoring case
+fn one_of(value: &str, words: &[&str]) -> bool {
+    words.iter().any(|word| value.eq_ignore_ascii_case(word))
+}
+
+/// Up to five digits, optionally signed
+fn is_small_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    (1..=5).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Loopback host, optionally with a port
+fn is_loopback_host(authority: &str) -> bool {
+    let host = match authority.rsplit_once(':') {
+        Some((host, port))
+            if !host.is_empty() && !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            host
+        }
+        _ => authority,
+    };
+    let host = host.to_ascii_lowercase();
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host == "[::1]"
+        || host == "0.0.0.0"
+        || host.strip_prefix("127.").is_some_and(|rest| {
+            rest.split('.').count() == 3 && rest.split('.').all(|part| part.parse::<u8>().is_ok())
+        })
+}
+
+/// Loopback URL or host without credentials or query
+fn is_loopback(value: &str) -> bool {
+    if value.contains(['@', '?', '#']) || value.chars().any(char::is_whitespace) {
+        return false;
+    }
+    let rest = match value.split_once("://") {
+        Some((scheme, rest))
+            if !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-')) =>
+        {
+            rest
+        }
+        Some(_) => return false,
+        None => value,
+    };
+    let authority = rest
+        .split_once('/')
+        .map_or(rest, |(authority, _)| authority);
+    is_loopback_host(authority)
+}
+
+/// Absolute, relative, home or Windows drive path
+pub(crate) fn is_path(value: &str) -> bool {
+    if value.contains("://") {
+        return false;
+    }
+    let bytes = value.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/');
+    drive
+        || ["/", "./", "../", "~/", ".\\", "..\\"]
+            .iter()
+            .any(|p| value.starts_with(p))
+}
+
+/// Whether `value` has one of the shapes in `shapes`
+fn has_safe_shape(value: &str, shapes: u8) -> bool {
+    let enabled = |shape: ShelterSafeShape| shapes & (1 << shape as u8) != 0;
+    (enabled(ShelterSafeShape::Boolean) && one_of(value, BOOLEANS))
+        || (enabled(ShelterSafeShape::Integer) && is_small_integer(value))
+        || (enabled(ShelterSafeShape::Loopback) && is_loopback(value))
+        || (enabled(ShelterSafeShape::Path) && is_path(value))
+        || (enabled(ShelterSafeShape::Enum) && one_of(value, ENUMS))
+}
+
+/// Whether the smart mode leaves `value` visible (see the module docs)
+pub(crate) fn is_safe(value: &str, shapes: u8, safe_values: Option<&ShelterPatternSet>) -> bool {
+    if let Some(classification) = classify(value) {
+        return !classification.secret;
+    }
+    !randomness(value).looks_random
+        && (has_safe_shape(value, shapes) || safe_values.is_some_and(|set| set.is_match(value)))
+}
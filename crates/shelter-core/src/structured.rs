@@ -0,0 +1,271 @@
+//! Credential components of structured values
+//!
+//! Finds the secret parts of URLs and connection strings so they can be masked
+//! while the rest (scheme, host, port, database) stays readable:
+//! - URL userinfo: the password of `user:password@`, or the whole userinfo
+//!   when it has no `:` (usually a token, as in `https://<token>@github.com`)
+//! - query and `;`-separated properties with secret names (`password=`,
+//!   `token=`, `api_key=`, ...), including JDBC URLs
+//! - ADO.NET style `Key=Value;` strings (`Password=`, `Pwd=`, `AccountKey=`)
+//! - JSON documents: string leaves under secret keys (`private_key`,
+//!   `client_secret`, or configured key path globs), leaves that are known
+//!   secrets themselves, or every string leaf; keys, braces, numbers and
+//!   literals stay visible
+//!
+//! Redis and AMQP URIs (`redis://:password@host`) are plain URLs here. Values
+//! that look like connection strings but cannot be taken apart (e.g. Oracle
+//! `user/password@host` descriptors), and URLs that are secret as a whole
+//! (Slack webhooks), are not structured, so callers mask them whole.
+
+use crate::classify::classify;
+use crate::infer::infer_type;
+use crate::json::{leaves, LeafKind};
+use crate::patterns::ShelterPatternSet;
+use crate::types::ShelterValueType;
+use std::borrow::Cow;
+
+/// Parameter names that hold credentials (lowercase, without `_`, `-` or
+/// spaces)
+const SECRET_NAMES: &[&str] = &[
+    "password",
+    "passwd",
+    "pass",
+    "pwd",
+    "secret",
+    "token",
+    "key",
+    "apikey",
+    "auth",
+    "sig",
+    "signature",
+    "credential",
+    "credentials",
+    "accountkey",
+    "sharedaccesskey",
+];
+
+/// Name suffixes that hold credentials (`client_secret`, `access_token`, ...)
+const SECRET_SUFFIXES: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "apikey",
+    "accesskey",
+    "privatekey",
+    "signature",
+    "credential",
+];
+
+/// A secret part of a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SecretSpan<'a> {
+    /// Byte range within the value
+    pub start: usize,
+    pub end: usize,
+    /// What the part is: `password`, `userinfo`, the parameter name, or the
+    /// key path of a JSON leaf
+    pub component: Cow<'a, str>,
+}
+
+/// A value taken apart into its secret parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Structure<'a> {
+    /// `Url`, `ConnectionString` or `Json`
+    pub kind: ShelterValueType,
+    /// Secret parts in value order (empty when the value holds no credentials)
+    pub spans: Vec<SecretSpan<'a>>,
+}
+
+/// Which leaves of a JSON value are secret
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct JsonRules<'a> {
+    /// Globs matched against leaf key paths (None = leaves whose own key is a
+    /// secret name)
+    pub keys: Option<&'a ShelterPatternSet>,
+    /// Every string leaf is secret
+    pub all_strings: bool,
+}
+
+/// Whether a parameter name holds a credential
+fn is_secret_name(name: &str) -> bool {
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect();
+    SECRET_NAMES.contains(&name.as_str()) || SECRET_SUFFIXES.iter().any(|s| name.ends_with(s))
+}
+
+/// Span of the value of a `name=value` part at `offset`, if the name is secret
+fn property_span(part: &str, offset: usize) -> Option<SecretSpan<'_>> {
+    let (name, value) = part.split_once('=')?;
+    let name = name.trim();
+    if !is_secret_name(name) {
+        return None;
+    }
+    // Trim whitespace and a pair of quotes around the value
+    let leading = value.len() - value.trim_start().len();
+    let mut inner = value.trim();
+    let mut start = offset + name_end(part) + 1 + leading;
+    if inner.len() >= 2
+        && (inner.starts_with('"') && inner.ends_with('"')
+            || inner.starts_with('\'') && inner.ends_with('\''))
+    {
+        inner = &inner[1..inner.len() - 1];
+        start += 1;
+    }
+    (!inner.is_empty()).then_some(SecretSpan {
+        start,
+        end: start + inner.len(),
+        component: Cow::Borrowed(name),
+    })
+}
+
+/// Byte offset of the `=` of a `name=value` part
+#[inline]
+fn name_end(part: &str) -> usize {
+    part.find('=').unwrap_or(part.len())
+}
+
+/// Split on `separators` outside single or double quotes, with offsets
+pub(crate) fn split_unquoted<'a>(text: &'a str, separators: &[char]) -> Vec<(usize, &'a str)> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if text[start..i].trim_end().ends_with('=') => quote = Some(c),
+            (None, c) if separators.contains(&c) => {
+                parts.push((start, &text[start..i]));
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push((start, &text[start..]));
+    parts
+}
+
+/// Secret properties among `separators`-separated `name=value` parts
+fn property_spans<'a>(text: &'a str, offset: usize, separators: &[char]) -> Vec<SecretSpan<'a>> {
+    split_unquoted(text, separators)
+        .into_iter()
+        .filter_map(|(start, part)| property_span(part, offset + start))
+        .collect()
+}
+
+/// Secret parts of `scheme://[userinfo@]host[/path][?query]`, with the URL
+/// starting at `offset` of the value
+fn url_spans(url: &str, offset: usize) -> Option<Vec<SecretSpan<'_>>> {
+    let (scheme, rest) = url.split_once("://")?;
+    if scheme.is_empty() || url.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let rest_start = offset + scheme.len() + 3;
+    // Passwords may hold `/`, `?`, `#` or `;` unescaped, so the userinfo runs
+    // to the last `@`: a path or query with one is masked rather than shown
+    let host_start = rest.rfind('@').map_or(0, |at| at + 1);
+    let authority_len = host_start
+        + rest[host_start..]
+            .find(['/', '?', '#', ';'])
+            .unwrap_or(rest.len() - host_start);
+    let authority = &rest[..authority_len];
+
+    let mut spans = Vec::new();
+    if let Some(at) = authority.rfind('@') {
+        let userinfo = &authority[..at];
+        match userinfo.split_once(':') {
+            Some((user, password)) if !password.is_empty() => {
+                let start = rest_start + user.len() + 1;
+                spans.push(SecretSpan {
+                    start,
+                    end: start + password.len(),
+                    component: Cow::Borrowed("password"),
+                });
+            }
+            Some(_) => {}
+            None if !userinfo.is_empty() => spans.push(SecretSpan {
+                start: rest_start,
+                end: rest_start + userinfo.len(),
+                component: Cow::Borrowed("userinfo"),
+            }),
+            None => {}
+        }
+    }
+
+    // Query parameters and JDBC `;name=value` properties, up to the fragment
+    let tail = &rest[authority_len..];
+    let tail = &tail[..tail.find('#').unwrap_or(tail.len())];
+    let properties_start = tail.find(['?', ';']).unwrap_or(tail.len());
+    spans.extend(property_spans(
+        &tail[properties_start..],
+        rest_start + authority_len + properties_start,
+        &['?', '&', ';'],
+    ));
+    Some(spans)
+}
+
+/// Secret string leaves of a JSON document
+fn json_spans<'a>(value: &str, rules: JsonRules) -> Option<Vec<SecretSpan<'a>>> {
+    let spans = leaves(value)?
+        .into_iter()
+        .filter(|leaf| leaf.kind == LeafKind::String && leaf.end > leaf.start)
+        .filter(|leaf| {
+            rules.all_strings
+                || match rules.keys {
+                    Some(keys) => keys.is_match(&leaf.path),
+                    None => is_secret_name(leaf_name(&leaf.path)),
+                }
+                || classify(&value[leaf.start..leaf.end]).is_some_and(|c| c.secret)
+        })
+        .map(|leaf| SecretSpan {
+            start: leaf.start,
+            end: leaf.end,
+            component: Cow::Owned(leaf.path),
+        })
+        .collect();
+    Some(spans)
+}
+
+/// Last object key of a JSON path (`tokens` for `auth.tokens[0]`)
+fn leaf_name(path: &str) -> &str {
+    let name = path.rsplit('.').next().unwrap_or(path);
+    name.find('[').map_or(name, |i| &name[..i])
+}
+
+/// Take a URL, connection string or JSON document apart into its secret parts,
+/// with the default JSON rules
+pub(crate) fn structure(value: &str) -> Option<Structure<'_>> {
+    structure_with(value, JsonRules::default())
+}
+
+/// Take a URL, connection string or JSON document apart into its secret parts
+///
+/// Returns None for other values, and for connection strings in a form that
+/// cannot be taken apart.
+pub(crate) fn structure_with<'a>(value: &'a str, rules: JsonRules) -> Option<Structure<'a>> {
+    let kind = infer_type(value);
+    if classify(value).is_some_and(|c| c.secret) {
+        return None;
+    }
+    let spans = match kind {
+        ShelterValueType::Url => url_spans(value, 0)?,
+        ShelterValueType::ConnectionString => {
+            let jdbc = value
+                .get(..5)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("jdbc:"));
+            if jdbc {
+                url_spans(&value[5..], 5)?
+            } else if value.contains("://") {
+                url_spans(value, 0)?
+            } else {
+                property_spans(value, 0, &[';'])
+            }
+        }
+        ShelterValueType::Json => json_spans(value, rules)?,
+        _ => return None,
+    };
+    Some(Structure { kind, spans })
+}
@@ -2,7 +2,19 @@
 //!
 //! All types use #[repr(C)] for C ABI compatibility with LuaJIT FFI.
 
-use std::ffi::{c_char, CString};
+use crate::classify::Classification;
+use crate::directives::Override;
+use crate::display::str_cells;
+use crate::entropy::Randomness;
+use crate::jwt::Jwt;
+use crate::modes::ShelterModeTable;
+use crate::patterns::ShelterPatternSet;
+use crate::payload::Payload;
+use crate::pem::Pem;
+use crate::policy::ShelterPolicy;
+use crate::smart::ALL_SHAPES;
+use crate::structured::Structure;
+use std::ffi::{c_char, CStr, CString};
 use std::ptr;
 
 /// Quote type for parsed values
@@ -24,9 +36,116 @@ impl From<korni::QuoteType> for ShelterQuoteType {
     }
 }
 
+/// How sure the classifier is about a value's secret type
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShelterConfidence {
+    /// Not classified
+    None = 0,
+    /// Known public prefix without the full format, or a failed checksum
+    Low = 1,
+    /// Full format matched
+    Medium = 2,
+    /// Full format matched and its checksum is valid
+    High = 3,
+}
+
+/// Smallest charset containing every character of a value
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterCharset {
+    /// Empty value, or not analysed
+    None = 0,
+    /// `0-9`
+    Digits = 1,
+    /// `0-9a-fA-F`
+    Hex = 2,
+    /// `0-9a-zA-Z`
+    Alphanumeric = 3,
+    /// Alphanumeric plus `+/=-_` (standard and URL-safe base64)
+    Base64 = 4,
+    /// Anything else
+    Other = 5,
+}
+
+/// Encoding of a value's payload
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterEncoding {
+    /// Not encoded
+    None = 0,
+    /// Standard base64 (`+`, `/`)
+    Base64 = 1,
+    /// URL-safe base64 (`-`, `_`)
+    Base64Url = 2,
+}
+
+/// What a PEM block holds
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterPemKind {
+    /// Any other label (parameters, signatures, ...)
+    Other = 0,
+    /// `PRIVATE KEY`, `RSA PRIVATE KEY`, `OPENSSH PRIVATE KEY`, ...
+    PrivateKey = 1,
+    /// `CERTIFICATE`
+    Certificate = 2,
+    /// `CERTIFICATE REQUEST` (CSR)
+    CertificateRequest = 3,
+    /// `PUBLIC KEY`, `RSA PUBLIC KEY`, ...
+    PublicKey = 4,
+}
+
+/// Whether an entry is bundled into client code (see `public`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterExposure {
+    /// No public prefix, or not requested
+    Private = 0,
+    /// Public prefix and no secret
+    Public = 1,
+    /// Public prefix holding a secret
+    Leaked = 2,
+}
+
+/// Inferred type of a value
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterValueType {
+    /// Empty value, or not analysed
+    None = 0,
+    /// `true`, `false`, `yes`, `no`, `on`, `off`, `enabled`, `disabled`
+    Boolean = 1,
+    /// Optionally signed digits
+    Integer = 2,
+    /// Decimal or exponent notation, e.g. `0.5` or `1e-3`
+    Float = 3,
+    /// `scheme://...` that is not a connection string
+    Url = 4,
+    /// Database or broker URI (`postgres://`, `redis://`, `jdbc:...`) or
+    /// `Key=Value;` connection string
+    ConnectionString = 5,
+    /// Absolute, relative, home or Windows drive path
+    Path = 6,
+    /// `user@example.com`
+    Email = 7,
+    /// JSON object or array
+    Json = 8,
+    /// Standard base64 with padding or `+`/`/` characters
+    Base64 = 9,
+    /// `-----BEGIN ...-----` block
+    Pem = 10,
+    /// `8-4-4-4-12` hex UUID
+    Uuid = 11,
+    /// `30s`, `1h30m`, `250ms` or ISO 8601 `PT5M`
+    Duration = 12,
+    /// Anything else
+    Opaque = 13,
+}
+
 /// A parsed key-value entry from an EDF file
 /// Memory layout optimized: all 8-byte fields first, then 1-byte fields packed
-/// Total size: 88 bytes (80 bytes data + 3 bytes flags + 5 bytes padding)
+/// Total size: 216 bytes (200 bytes data + 9 bytes flags + padding)
 #[repr(C)]
 pub struct ShelterEntry {
     // === 8-byte aligned fields (pointers and sizes) ===
@@ -50,6 +169,43 @@ pub struct ShelterEntry {
     pub line_number: usize,
     /// 1-based line number where value ends (for multi-line values)
     pub value_end_line: usize,
+    /// Stable identity derived from key and occurrence (survives edits
+    /// elsewhere)
+    pub id: u64,
+    /// Mask cells the entry's mode draws (the value width unless analysed)
+    pub masked_length: usize,
+    /// Keyed fingerprint of the value (0 unless requested, see
+    /// `shelter_fingerprint`)
+    pub fingerprint: u64,
+    /// Index of the policy rule that chose the entry's mode (`usize::MAX` if
+    /// none matched or no policy was given)
+    pub policy_rule: usize,
+    /// Mode set by an inline `# shelter:` directive (null-terminated, null if
+    /// none)
+    pub directive_mode: *mut c_char,
+    /// Length of directive_mode
+    pub directive_mode_len: usize,
+    /// 1-based line of the directive that set the mode (0 if none)
+    pub directive_line: usize,
+    /// Secret type id of the value, e.g. `stripe` (null-terminated, null if
+    /// unclassified or not requested)
+    pub secret_type: *mut c_char,
+    /// Length of secret_type
+    pub secret_type_len: usize,
+    /// Shannon entropy of the value in bits per character (0 unless requested)
+    pub entropy: f64,
+    /// Entropy relative to the value's charset and length, 0 to 1 (0 unless
+    /// requested)
+    pub randomness: f64,
+    /// What an encoded value carries, e.g. `base64 → JSON with private_key`
+    /// (null-terminated, null if not encoded or not requested)
+    pub payload: *mut c_char,
+    /// Length of payload
+    pub payload_len: usize,
+    /// Claims of a JWT value (null if not a JWT or not requested)
+    pub jwt: *mut ShelterJwt,
+    /// PEM blocks of the value (null if none or not requested)
+    pub pem: *mut ShelterPem,
 
     // === 1-byte fields (packed at end to minimize padding) ===
     /// Quote type (0=none, 1=single, 2=double)
@@ -58,12 +214,39 @@ pub struct ShelterEntry {
     pub is_exported: u8,
     /// Whether entry is inside a comment
     pub is_comment: u8,
-    // Implicit 5 bytes padding to align struct to 8 bytes
+    /// Confidence of secret_type (see `ShelterConfidence`)
+    pub secret_confidence: u8,
+    /// Charset of the value (see `ShelterCharset`)
+    pub charset: u8,
+    /// Whether the value looks like a random token
+    pub looks_random: u8,
+    /// Inferred type of the value (see `ShelterValueType`)
+    pub value_type: u8,
+    /// Whether the encoded payload holds a secret
+    pub payload_secret: u8,
+    /// Whether the key is bundled into client code (see `ShelterExposure`)
+    pub exposure: u8,
 }
 
 impl ShelterEntry {
     /// Create a new entry from a korni KeyValuePair
-    pub fn from_korni(kv: &korni::KeyValuePair, line_number: usize, value_end_line: usize) -> Self {
+    pub fn from_korni(
+        kv: &korni::KeyValuePair,
+        line_number: usize,
+        value_end_line: usize,
+        id: u64,
+    ) -> Self {
+        Self::with_directive(kv, line_number, value_end_line, id, None)
+    }
+
+    /// Create a new entry with the mode override of an inline directive
+    pub(crate) fn with_directive(
+        kv: &korni::KeyValuePair,
+        line_number: usize,
+        value_end_line: usize,
+        id: u64,
+        directive: Option<&Override>,
+    ) -> Self {
         let key_cstr = CString::new(kv.key.as_ref()).unwrap_or_default();
         let value_cstr = CString::new(kv.value.as_ref()).unwrap_or_default();
 
@@ -88,9 +271,100 @@ impl ShelterEntry {
             value_end,
             line_number,
             value_end_line,
+            id,
+            masked_length: str_cells(&kv.value),
+            fingerprint: 0,
+            policy_rule: usize::MAX,
+            directive_mode: directive.map_or(ptr::null_mut(), |d| {
+                CString::new(d.mode).unwrap_or_default().into_raw()
+            }),
+            directive_mode_len: directive.map_or(0, |d| d.mode.len()),
+            directive_line: directive.map_or(0, |d| d.line_number),
+            secret_type: ptr::null_mut(),
+            secret_type_len: 0,
+            entropy: 0.0,
+            randomness: 0.0,
+            payload: ptr::null_mut(),
+            payload_len: 0,
+            jwt: ptr::null_mut(),
+            pem: ptr::null_mut(),
             quote_type: ShelterQuoteType::from(kv.quote) as u8,
             is_exported: kv.is_exported as u8,
             is_comment: kv.is_comment as u8,
+            secret_confidence: ShelterConfidence::None as u8,
+            charset: ShelterCharset::None as u8,
+            looks_random: 0,
+            value_type: ShelterValueType::None as u8,
+            payload_secret: 0,
+            exposure: ShelterExposure::Private as u8,
+        }
+    }
+
+    /// Record what an encoded value carries
+    pub(crate) fn set_payload(&mut self, payload: Option<&Payload>) {
+        if let Some(payload) = payload {
+            let summary = payload.summary();
+            self.payload_len = summary.len();
+            self.payload = CString::new(summary).unwrap_or_default().into_raw();
+            self.payload_secret = payload.secret.is_some() as u8;
+        }
+    }
+
+    /// Record the claims of a JWT value
+    pub(crate) fn set_jwt(&mut self, jwt: Option<&Jwt>) {
+        if let Some(jwt) = jwt {
+            self.jwt = Box::into_raw(Box::new(ShelterJwt::from_claims(jwt)));
+        }
+    }
+
+    /// Record what the value's PEM blocks hold
+    pub(crate) fn set_pem(&mut self, pem: Option<&Pem>) {
+        if let Some(pem) = pem {
+            self.pem = Box::into_raw(Box::new(ShelterPem::from_pem(pem)));
+        }
+    }
+
+    /// Record the value's classification
+    pub(crate) fn set_classification(&mut self, classification: Option<&Classification>) {
+        if let Some(c) = classification {
+            self.secret_type = CString::new(c.id).unwrap_or_default().into_raw();
+            self.secret_type_len = c.id.len();
+            self.secret_confidence = c.confidence as u8;
+        }
+    }
+
+    /// Record the value's randomness
+    pub(crate) fn set_randomness(&mut self, randomness: &Randomness) {
+        self.entropy = randomness.entropy;
+        self.randomness = randomness.score;
+        self.charset = randomness.charset as u8;
+        self.looks_random = randomness.looks_random as u8;
+    }
+
+    /// Value bytes (without null terminator)
+    ///
+    /// # Safety
+    /// `self.value` must be null or a valid pointer created by `from_korni`
+    #[inline]
+    pub(crate) unsafe fn value_bytes(&self) -> &[u8] {
+        if self.value.is_null() {
+            &[]
+        } else {
+            CStr::from_ptr(self.value).to_bytes()
+        }
+    }
+
+    /// Directive mode bytes (empty if none)
+    ///
+    /// # Safety
+    /// `self.directive_mode` must be null or a valid pointer created by
+    /// `with_directive`
+    #[inline]
+    pub(crate) unsafe fn directive_bytes(&self) -> &[u8] {
+        if self.directive_mode.is_null() {
+            &[]
+        } else {
+            CStr::from_ptr(self.directive_mode).to_bytes()
         }
     }
 }
@@ -109,6 +383,15 @@ pub struct ShelterResult {
     pub line_offsets: *mut usize,
     /// Number of lines (length of line_offsets array)
     pub line_count: usize,
+    /// Array of diagnostics, in line order
+    pub diagnostics: *mut ShelterDiagnostic,
+    /// Number of diagnostics
+    pub diagnostic_count: usize,
+    /// Base64 lines of private keys pasted into comments, which are masked
+    /// whatever the mode (only with `ShelterAnalysisOptions::pems`)
+    pub comment_keys: *mut ShelterLineSpan,
+    /// Number of comment_keys
+    pub comment_key_count: usize,
     /// Error message (null if no error)
     pub error: *mut c_char,
 }
@@ -116,9 +399,16 @@ pub struct ShelterResult {
 impl ShelterResult {
     /// Create a successful result with entries and line offsets
     #[inline]
-    pub fn ok(entries: Vec<ShelterEntry>, line_offsets: Vec<usize>) -> *mut Self {
+    pub fn ok(
+        entries: Vec<ShelterEntry>,
+        line_offsets: Vec<usize>,
+        diagnostics: Vec<ShelterDiagnostic>,
+        comment_keys: Vec<ShelterLineSpan>,
+    ) -> *mut Self {
         let count = entries.len();
         let line_count = line_offsets.len();
+        let diagnostic_count = diagnostics.len();
+        let comment_key_count = comment_keys.len();
 
         let entries_ptr = if entries.is_empty() {
             ptr::null_mut()
@@ -134,11 +424,27 @@ impl ShelterResult {
             Box::into_raw(boxed) as *mut usize
         };
 
+        let diagnostics_ptr = if diagnostics.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(diagnostics.into_boxed_slice()) as *mut ShelterDiagnostic
+        };
+
+        let comment_keys_ptr = if comment_keys.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(comment_keys.into_boxed_slice()) as *mut ShelterLineSpan
+        };
+
         Box::into_raw(Box::new(ShelterResult {
             entries: entries_ptr,
             count,
             line_offsets: line_offsets_ptr,
             line_count,
+            diagnostics: diagnostics_ptr,
+            diagnostic_count,
+            comment_keys: comment_keys_ptr,
+            comment_key_count,
             error: ptr::null_mut(),
         }))
     }
@@ -155,11 +461,57 @@ impl ShelterResult {
             count: 0,
             line_offsets: ptr::null_mut(),
             line_count: 0,
+            diagnostics: ptr::null_mut(),
+            diagnostic_count: 0,
+            comment_keys: ptr::null_mut(),
+            comment_key_count: 0,
             error,
         }))
     }
 }
 
+/// Diagnostic severity (values match `vim.diagnostic.severity`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterSeverity {
+    Error = 1,
+    Warning = 2,
+    Info = 3,
+    Hint = 4,
+}
+
+/// A problem found in a document
+#[repr(C)]
+#[derive(Debug)]
+pub struct ShelterDiagnostic {
+    /// Stable code, e.g. `unknown-directive` (null-terminated)
+    pub code: *mut c_char,
+    /// Human-readable message (null-terminated)
+    pub message: *mut c_char,
+    /// 1-based line number
+    pub line_number: usize,
+    /// Byte offset where the reported text starts
+    pub start: usize,
+    /// Byte offset where the reported text ends (exclusive)
+    pub end: usize,
+    /// Index of the affected entry (`usize::MAX` if none)
+    pub entry_index: usize,
+    /// Severity (see `ShelterSeverity`)
+    pub severity: u8,
+}
+
+/// A byte range on one line
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShelterLineSpan {
+    /// 1-based line number
+    pub line_number: usize,
+    /// Byte offset where the range starts
+    pub start: usize,
+    /// Byte offset where the range ends (exclusive)
+    pub end: usize,
+}
+
 /// Options for parsing
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -187,3 +539,1190 @@ impl From<ShelterParseOptions> for korni::ParseOptions {
         }
     }
 }
+
+/// Optional per-entry analysis performed by `shelter_parse_with`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterAnalysisOptions {
+    /// Mode selection and mode table; when set, `masked_length` follows each
+    /// entry's mode (null = value width)
+    pub render: *const ShelterRenderConfig,
+    /// Key for value fingerprints (may be null when `key_len` is 0)
+    pub fingerprint_key: *const c_char,
+    /// Length of fingerprint_key in bytes
+    pub fingerprint_key_len: usize,
+    /// Current time in Unix seconds for expiry checks (0 = system clock)
+    pub now: i64,
+    /// Days before expiry from which certificates are reported (0 = 30)
+    pub expiry_window_days: usize,
+    /// Length below which secret values are reported (0 = 12)
+    pub min_secret_length: usize,
+    /// Newline-separated key prefixes bundled into client code (null = the
+    /// defaults, see `ShelterRenderConfig::public_prefixes`)
+    pub public_prefixes: *const c_char,
+    /// Length of public_prefixes in bytes
+    pub public_prefixes_len: usize,
+    /// Newline-separated names of the modes directives may set; others are
+    /// reported and ignored (null = any)
+    pub mode_names: *const c_char,
+    /// Length of mode_names in bytes
+    pub mode_names_len: usize,
+    /// Whether to fill in each entry's `fingerprint`
+    pub fingerprints: u8,
+    /// Whether to fill in each entry's `secret_type` and `secret_confidence`
+    pub classify: u8,
+    /// Whether to fill in each entry's `entropy`, `randomness`, `charset` and
+    /// `looks_random`
+    pub entropy: u8,
+    /// Whether to fill in each entry's `value_type`
+    pub value_types: u8,
+    /// Whether to fill in each entry's `payload` and `payload_secret`
+    pub payloads: u8,
+    /// Whether to fill in each entry's `jwt` and report expired tokens
+    pub jwts: u8,
+    /// Whether to fill in each entry's `pem`, report expired and expiring
+    /// certificates and list private keys in comments
+    pub pems: u8,
+    /// Whether to report placeholders, default credentials and short secrets
+    pub weak_values: u8,
+    /// Whether to fill in each entry's `exposure` and report secrets under
+    /// public prefixes
+    pub exposure: u8,
+}
+
+impl Default for ShelterAnalysisOptions {
+    fn default() -> Self {
+        Self {
+            render: ptr::null(),
+            fingerprint_key: ptr::null(),
+            fingerprint_key_len: 0,
+            now: 0,
+            expiry_window_days: 0,
+            min_secret_length: 0,
+            public_prefixes: ptr::null(),
+            public_prefixes_len: 0,
+            mode_names: ptr::null(),
+            mode_names_len: 0,
+            fingerprints: 0,
+            classify: 0,
+            entropy: 0,
+            value_types: 0,
+            payloads: 0,
+            jwts: 0,
+            pems: 0,
+            weak_values: 0,
+            exposure: 0,
+        }
+    }
+}
+
+/// Kind of change between two parse results
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterChangeKind {
+    /// Entry only exists in the new result
+    Added = 0,
+    /// Entry only exists in the old result
+    Removed = 1,
+    /// Entry is unchanged except for its line number
+    Moved = 2,
+    /// Entry value, quoting or value position changed
+    ValueChanged = 3,
+}
+
+/// A single entry change between two parse results
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterChange {
+    /// Stable entry identity (see `ShelterEntry::id`)
+    pub id: u64,
+    /// Index into the old result's entries (`usize::MAX` for added entries)
+    pub old_index: usize,
+    /// Index into the new result's entries (`usize::MAX` for removed entries)
+    pub new_index: usize,
+    /// Change kind (see `ShelterChangeKind`)
+    pub kind: u8,
+}
+
+/// Result of diffing two parse results
+#[repr(C)]
+pub struct ShelterDiff {
+    /// Array of changes (removed first, then added/moved/changed in new order)
+    pub changes: *mut ShelterChange,
+    /// Number of changes
+    pub count: usize,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterDiff {
+    /// Create a successful diff
+    #[inline]
+    pub fn ok(changes: Vec<ShelterChange>) -> *mut Self {
+        let count = changes.len();
+        let changes_ptr = if changes.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(changes.into_boxed_slice()) as *mut ShelterChange
+        };
+
+        Box::into_raw(Box::new(ShelterDiff {
+            changes: changes_ptr,
+            count,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error diff
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterDiff {
+            changes: ptr::null_mut(),
+            count: 0,
+            error,
+        }))
+    }
+}
+
+/// Built-in masking behaviour of a mode
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterModeKind {
+    /// Replace every character with the mask character
+    Full = 0,
+    /// Show start/end characters, mask the middle
+    Partial = 1,
+    /// Leave the value visible
+    None = 2,
+    /// Short mask followed by the value's keyed fingerprint, e.g. `••••[a1f3]`
+    Fingerprint = 3,
+    /// Leave obviously non-secret values visible, mask the rest like `Full`
+    Smart = 4,
+    /// Mask only the credentials of URLs and connection strings; other
+    /// values use `fallback_mode`
+    Structured = 5,
+}
+
+/// Value shape the smart mode leaves visible
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterSafeShape {
+    /// `true`, `false`, `yes`, `no`, `on`, `off`, `enabled`, `disabled`
+    Boolean = 0,
+    /// Up to five digits, optionally signed
+    Integer = 1,
+    /// `localhost`, `127.x.x.x`, `[::1]` or `0.0.0.0` URLs without credentials
+    Loopback = 2,
+    /// Absolute, relative, home or Windows drive paths
+    Path = 3,
+    /// Environment names, log levels and similar well-known values
+    Enum = 4,
+}
+
+/// Which leaves of a JSON value the structured mode masks
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterJsonLeaves {
+    /// Strings under secret keys, and strings that are known secrets
+    Secret = 0,
+    /// Every string
+    Strings = 1,
+}
+
+/// How a mode chooses the number of mask cells
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterLengthStrategy {
+    /// As wide as the value (or `fixed_length`)
+    Preserve = 0,
+    /// Value width rounded up to a multiple of `bucket_size`
+    Bucket = 1,
+    /// Keyed hash of the value mapped into `min_length..=max_length`
+    Keyed = 2,
+}
+
+/// Options for a native masking mode (same options as the Lua modes)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterModeOptions {
+    /// Mask character (UTF-8, must be a single character; null = "*", or "•"
+    /// for fingerprint modes)
+    pub mask_char: *const c_char,
+    /// Length of mask_char in bytes
+    pub mask_char_len: usize,
+    /// Fixed output length for full masks, or mask glyphs before a
+    /// fingerprint (0 = unset)
+    pub fixed_length: usize,
+    /// Characters shown at start (partial)
+    pub show_start: usize,
+    /// Characters shown at end (partial)
+    pub show_end: usize,
+    /// Minimum number of mask characters (partial, >= 1)
+    pub min_mask: usize,
+    /// Bucket width in cells (bucket strategy, >= 1)
+    pub bucket_size: usize,
+    /// Shortest mask in cells (keyed strategy, >= 1)
+    pub min_length: usize,
+    /// Longest mask in cells (keyed strategy, >= min_length)
+    pub max_length: usize,
+    /// Hex digits of the fingerprint shown (fingerprint, 1..=16)
+    pub fingerprint_length: usize,
+    /// Newline-separated globs of values left visible (smart, matched
+    /// regardless of case; null = none)
+    pub safe_values: *const c_char,
+    /// Length of safe_values in bytes
+    pub safe_values_len: usize,
+    /// Newline-separated globs of JSON key paths whose strings are masked
+    /// (structured, matched regardless of case; null = secret key names)
+    pub secret_keys: *const c_char,
+    /// Length of secret_keys in bytes
+    pub secret_keys_len: usize,
+    /// Masking behaviour (see `ShelterModeKind`)
+    pub kind: u8,
+    /// Whether to preserve the original value length (full)
+    pub preserve_length: u8,
+    /// Mode for values too short for partial masking (0=full, 2=none)
+    pub fallback_mode: u8,
+    /// How mask lengths are chosen (see `ShelterLengthStrategy`)
+    pub length_strategy: u8,
+    /// Shapes left visible as a bitmask of `1 << ShelterSafeShape` (smart)
+    pub safe_shapes: u8,
+    /// Which JSON leaves are masked (see `ShelterJsonLeaves`, structured)
+    pub json_leaves: u8,
+}
+
+impl Default for ShelterModeOptions {
+    fn default() -> Self {
+        Self {
+            mask_char: ptr::null(),
+            mask_char_len: 0,
+            fixed_length: 0,
+            show_start: 3,
+            show_end: 3,
+            min_mask: 3,
+            bucket_size: 8,
+            min_length: 12,
+            max_length: 24,
+            fingerprint_length: 4,
+            safe_values: ptr::null(),
+            safe_values_len: 0,
+            secret_keys: ptr::null(),
+            secret_keys_len: 0,
+            kind: ShelterModeKind::Full as u8,
+            preserve_length: 1,
+            fallback_mode: ShelterModeKind::Full as u8,
+            length_strategy: ShelterLengthStrategy::Preserve as u8,
+            safe_shapes: ALL_SHAPES,
+            json_leaves: ShelterJsonLeaves::Secret as u8,
+        }
+    }
+}
+
+/// A glob pattern mapped to a mode name
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterPatternRule {
+    /// Glob pattern (UTF-8)
+    pub pattern: *const c_char,
+    /// Length of pattern in bytes
+    pub pattern_len: usize,
+    /// Mode name (UTF-8)
+    pub mode: *const c_char,
+    /// Length of mode in bytes
+    pub mode_len: usize,
+}
+
+/// Options for compiling a pattern set
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShelterPatternOptions {
+    /// Match regardless of case
+    pub case_insensitive: u8,
+    /// Match file paths: `*` and `?` stop at `/`, `**` crosses it
+    pub paths: u8,
+}
+
+/// Condition on a yes/no property of an entry
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterFlagCondition {
+    /// Either way
+    Any = 0,
+    /// Property must hold
+    Set = 1,
+    /// Property must not hold
+    Unset = 2,
+}
+
+/// A policy rule: conditions on an entry and the mode to use when all hold
+///
+/// Empty strings, zero `min_entropy` and zero `quote_types` leave a condition
+/// out, so a rule with no conditions matches every entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterPolicyRule {
+    /// Key glob (see `shelter_pattern_set_new`)
+    pub key_glob: *const c_char,
+    /// Length of key_glob in bytes
+    pub key_glob_len: usize,
+    /// Key regex (unanchored)
+    pub key_regex: *const c_char,
+    /// Length of key_regex in bytes
+    pub key_regex_len: usize,
+    /// Value regex (unanchored)
+    pub value_regex: *const c_char,
+    /// Length of value_regex in bytes
+    pub value_regex_len: usize,
+    /// Source path glob (never matches when the source is unknown)
+    pub source_glob: *const c_char,
+    /// Length of source_glob in bytes
+    pub source_glob_len: usize,
+    /// Glob over the detected secret type id (`*` = any recognised token)
+    pub secret_type: *const c_char,
+    /// Length of secret_type in bytes
+    pub secret_type_len: usize,
+    /// Mode name to use when the rule matches
+    pub mode: *const c_char,
+    /// Length of mode in bytes
+    pub mode_len: usize,
+    /// Minimum Shannon entropy of the value in bits per character
+    pub min_entropy: f64,
+    /// Rules are tried by descending priority, ties in the order given
+    pub priority: i32,
+    /// Highlight id for the entry's overlays (0 = the render config's)
+    pub highlight_id: i32,
+    /// Allowed quote types as a bitmask of `1 << ShelterQuoteType` (0 = any)
+    pub quote_types: u8,
+    /// Comment condition (see `ShelterFlagCondition`)
+    pub is_comment: u8,
+    /// Export condition (see `ShelterFlagCondition`)
+    pub is_exported: u8,
+    /// Match the key and source globs regardless of case
+    pub case_insensitive: u8,
+    /// Minimum classifier confidence (see `ShelterConfidence`; 0 = any)
+    pub min_confidence: u8,
+    /// Random-looking value condition (see `ShelterFlagCondition`)
+    pub looks_random: u8,
+}
+
+impl Default for ShelterPolicyRule {
+    fn default() -> Self {
+        Self {
+            key_glob: ptr::null(),
+            key_glob_len: 0,
+            key_regex: ptr::null(),
+            key_regex_len: 0,
+            value_regex: ptr::null(),
+            value_regex_len: 0,
+            source_glob: ptr::null(),
+            source_glob_len: 0,
+            secret_type: ptr::null(),
+            secret_type_len: 0,
+            mode: ptr::null(),
+            mode_len: 0,
+            min_entropy: 0.0,
+            priority: 0,
+            highlight_id: 0,
+            quote_types: 0,
+            is_comment: ShelterFlagCondition::Any as u8,
+            is_exported: ShelterFlagCondition::Any as u8,
+            case_insensitive: 0,
+            min_confidence: ShelterConfidence::None as u8,
+            looks_random: ShelterFlagCondition::Any as u8,
+        }
+    }
+}
+
+/// Configuration for rendering masks natively
+///
+/// Mode selection matches the Lua engine: policy rule → key pattern → source
+/// pattern → default mode, each pattern set picking its most specific match.
+/// Unknown modes fall back to `full`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterRenderConfig {
+    /// Mode table (null = built-in modes with default options)
+    pub modes: *const ShelterModeTable,
+    /// Default mode name (null = "full")
+    pub default_mode: *const c_char,
+    /// Length of default_mode in bytes
+    pub default_mode_len: usize,
+    /// Key patterns
+    pub key_patterns: *const ShelterPatternRule,
+    /// Number of key patterns
+    pub key_pattern_count: usize,
+    /// Source file patterns (matched against the source path)
+    pub source_patterns: *const ShelterPatternRule,
+    /// Number of source patterns
+    pub source_pattern_count: usize,
+    /// Compiled key patterns, used instead of `key_patterns` when not null
+    pub key_set: *const ShelterPatternSet,
+    /// Compiled source patterns, used instead of `source_patterns` when not
+    /// null
+    pub source_set: *const ShelterPatternSet,
+    /// Policy rules tried before the patterns (null = none)
+    pub policy: *const ShelterPolicy,
+    /// Source file path (null = unknown)
+    pub source: *const c_char,
+    /// Length of source in bytes
+    pub source_len: usize,
+    /// Newline-separated key prefixes bundled into client code (null =
+    /// `NEXT_PUBLIC_`, `VITE_`, `REACT_APP_`, `EXPO_PUBLIC_` and `PUBLIC_`)
+    pub public_prefixes: *const c_char,
+    /// Length of public_prefixes in bytes
+    pub public_prefixes_len: usize,
+    /// Tab width used to measure values containing tabs (0 = 8)
+    pub tabstop: usize,
+    /// Per-entry reveal levels (entries not listed stay hidden)
+    pub reveals: *const ShelterReveal,
+    /// Number of reveal levels
+    pub reveal_count: usize,
+    /// Highlight id for overlays
+    pub highlight_id: i32,
+    /// Skip entries inside comments
+    pub skip_comments: u8,
+    /// Ignore inline `# shelter:` directives
+    pub ignore_directives: u8,
+}
+
+impl Default for ShelterRenderConfig {
+    fn default() -> Self {
+        Self {
+            modes: ptr::null(),
+            default_mode: ptr::null(),
+            default_mode_len: 0,
+            key_patterns: ptr::null(),
+            key_pattern_count: 0,
+            source_patterns: ptr::null(),
+            source_pattern_count: 0,
+            key_set: ptr::null(),
+            source_set: ptr::null(),
+            policy: ptr::null(),
+            source: ptr::null(),
+            source_len: 0,
+            public_prefixes: ptr::null(),
+            public_prefixes_len: 0,
+            tabstop: 0,
+            reveals: ptr::null(),
+            reveal_count: 0,
+            highlight_id: 0,
+            skip_comments: 1,
+            ignore_directives: 0,
+        }
+    }
+}
+
+/// How much of an entry's value to reveal
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterRevealKind {
+    /// Masked as the entry's mode says
+    Hidden = 0,
+    /// Show `leading` and `trailing` characters
+    Edges = 1,
+    /// Show the public prefix of a recognised token (e.g. `sk_live_`)
+    Prefix = 2,
+    /// Show the whole value
+    Full = 3,
+}
+
+/// Reveal level for one entry, keyed by its stable id
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShelterReveal {
+    /// Entry id (see `ShelterEntry::id`)
+    pub id: u64,
+    /// Characters shown at the start (edges)
+    pub leading: usize,
+    /// Characters shown at the end (edges)
+    pub trailing: usize,
+    /// Reveal level (see `ShelterRevealKind`)
+    pub kind: u8,
+}
+
+/// A ready-to-apply overlay chunk (one per line of a masked value)
+#[repr(C)]
+pub struct ShelterOverlay {
+    /// Virtual text (null-terminated)
+    pub text: *mut c_char,
+    /// Length of text in bytes
+    pub text_len: usize,
+    /// 0-based row
+    pub row: usize,
+    /// 0-based byte column where the overlay starts
+    pub col: usize,
+    /// 0-based byte column where the covered text ends (exclusive)
+    pub end_col: usize,
+    /// Index of the entry in the parse result (`usize::MAX` for private keys
+    /// in comments)
+    pub entry_index: usize,
+    /// Highlight id
+    pub hl_id: i32,
+    /// Whether this chunk continues a multi-line value (row after the first)
+    pub is_continuation: u8,
+}
+
+/// Overlays for a whole document
+#[repr(C)]
+pub struct ShelterRenderPlan {
+    /// Array of overlays, ordered by row then column
+    pub overlays: *mut ShelterOverlay,
+    /// Number of overlays
+    pub count: usize,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterRenderPlan {
+    /// Create a successful plan
+    #[inline]
+    pub fn ok(overlays: Vec<ShelterOverlay>) -> *mut Self {
+        let count = overlays.len();
+        let overlays_ptr = if overlays.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(overlays.into_boxed_slice()) as *mut ShelterOverlay
+        };
+
+        Box::into_raw(Box::new(ShelterRenderPlan {
+            overlays: overlays_ptr,
+            count,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error plan
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterRenderPlan {
+            overlays: ptr::null_mut(),
+            count: 0,
+            error,
+        }))
+    }
+}
+
+/// Maps a replaced value between original and masked text (byte offsets)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShelterOffsetSpan {
+    /// Start of the value content in the original text
+    pub original_start: usize,
+    /// End of the value content in the original text (exclusive)
+    pub original_end: usize,
+    /// Start of the replacement in the masked text
+    pub masked_start: usize,
+    /// End of the replacement in the masked text (exclusive)
+    pub masked_end: usize,
+    /// Index of the entry in the parse result (`usize::MAX` for private keys
+    /// in comments)
+    pub entry_index: usize,
+}
+
+/// A fully masked copy of a document
+///
+/// Text outside the spans is identical to the original; offsets outside the
+/// spans shift by the accumulated length difference of preceding spans.
+#[repr(C)]
+pub struct ShelterMaskedDocument {
+    /// Masked text (null-terminated)
+    pub text: *mut c_char,
+    /// Length of text in bytes
+    pub text_len: usize,
+    /// Array of replaced spans, in document order
+    pub spans: *mut ShelterOffsetSpan,
+    /// Number of spans
+    pub span_count: usize,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterMaskedDocument {
+    /// Create a successful masked document, or an error when the text holds a
+    /// NUL byte (it could not be read back with its length)
+    #[inline]
+    pub fn ok(text: String, spans: Vec<ShelterOffsetSpan>) -> *mut Self {
+        let text_len = text.len();
+        let text = match CString::new(text) {
+            Ok(text) => text,
+            Err(_) => return Self::err("Masked document contains a NUL byte"),
+        };
+        let span_count = spans.len();
+        let spans_ptr = if spans.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(spans.into_boxed_slice()) as *mut ShelterOffsetSpan
+        };
+
+        Box::into_raw(Box::new(ShelterMaskedDocument {
+            text: text.into_raw(),
+            text_len,
+            spans: spans_ptr,
+            span_count,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error result
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterMaskedDocument {
+            text: ptr::null_mut(),
+            text_len: 0,
+            spans: ptr::null_mut(),
+            span_count: 0,
+            error,
+        }))
+    }
+}
+
+/// A run of secret-value bytes that would remain visible on screen
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShelterLeak {
+    /// 0-based row
+    pub row: usize,
+    /// 0-based byte column where the visible run starts
+    pub col: usize,
+    /// 0-based byte column where the visible run ends (exclusive)
+    pub end_col: usize,
+    /// Index of the entry in the parse result
+    pub entry_index: usize,
+}
+
+/// Result of verifying planned overlays against a document
+#[repr(C)]
+pub struct ShelterLeakReport {
+    /// Array of leaks, in document order (empty when nothing is visible)
+    pub leaks: *mut ShelterLeak,
+    /// Number of leaks
+    pub count: usize,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterLeakReport {
+    /// Create a successful report
+    #[inline]
+    pub fn ok(leaks: Vec<ShelterLeak>) -> *mut Self {
+        let count = leaks.len();
+        let leaks_ptr = if leaks.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(leaks.into_boxed_slice()) as *mut ShelterLeak
+        };
+
+        Box::into_raw(Box::new(ShelterLeakReport {
+            leaks: leaks_ptr,
+            count,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error report
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterLeakReport {
+            leaks: ptr::null_mut(),
+            count: 0,
+            error,
+        }))
+    }
+}
+
+/// A credential inside a structured value
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShelterSecretSpan {
+    /// Byte offset within the value where the credential starts
+    pub start: usize,
+    /// Byte offset within the value where it ends (exclusive)
+    pub end: usize,
+    /// What the credential is: `password`, `userinfo`, the parameter name or
+    /// the JSON key path (null-terminated)
+    pub component: *mut c_char,
+    /// Length of component
+    pub component_len: usize,
+}
+
+/// Credentials of a URL, connection string or JSON value
+#[repr(C)]
+pub struct ShelterSecretSpans {
+    /// Array of credentials, in value order
+    pub spans: *mut ShelterSecretSpan,
+    /// Number of spans
+    pub count: usize,
+    /// Structure the value was taken apart as (see `ShelterValueType`: `Url`,
+    /// `ConnectionString` or `Json`; `None` when it could not be)
+    pub kind: u8,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterSecretSpans {
+    /// Create a successful result
+    #[inline]
+    pub(crate) fn ok(structure: Option<Structure>) -> *mut Self {
+        let (kind, spans) = match structure {
+            Some(structure) => (structure.kind, structure.spans),
+            None => (ShelterValueType::None, Vec::new()),
+        };
+        let count = spans.len();
+        let spans_ptr = if spans.is_empty() {
+            ptr::null_mut()
+        } else {
+            let spans: Vec<ShelterSecretSpan> = spans
+                .into_iter()
+                .map(|span| ShelterSecretSpan {
+                    start: span.start,
+                    end: span.end,
+                    component_len: span.component.len(),
+                    component: CString::new(span.component.into_owned())
+                        .unwrap_or_default()
+                        .into_raw(),
+                })
+                .collect();
+            Box::into_raw(spans.into_boxed_slice()) as *mut ShelterSecretSpan
+        };
+
+        Box::into_raw(Box::new(ShelterSecretSpans {
+            spans: spans_ptr,
+            count,
+            kind: kind as u8,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error result
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterSecretSpans {
+            spans: ptr::null_mut(),
+            count: 0,
+            kind: ShelterValueType::None as u8,
+            error,
+        }))
+    }
+}
+
+/// Claims of a JWT (signature not verified)
+#[repr(C)]
+#[derive(Debug)]
+pub struct ShelterJwt {
+    /// Signing algorithm, e.g. `RS256` (null-terminated, null if absent)
+    pub alg: *mut c_char,
+    /// Length of alg
+    pub alg_len: usize,
+    /// Issuer (null-terminated, null if absent)
+    pub iss: *mut c_char,
+    /// Length of iss
+    pub iss_len: usize,
+    /// Audiences joined by `, ` (null-terminated, null if absent)
+    pub aud: *mut c_char,
+    /// Length of aud
+    pub aud_len: usize,
+    /// Expiry in Unix seconds (0 if absent)
+    pub exp: i64,
+    /// Issue time in Unix seconds (0 if absent)
+    pub iat: i64,
+}
+
+impl ShelterJwt {
+    pub(crate) fn from_claims(jwt: &Jwt) -> Self {
+        // Claims with a NUL (`\u0000`) cannot be passed as C strings
+        let text = |claim: &Option<String>| match claim {
+            Some(text) if !text.contains('\0') => (
+                CString::new(text.as_str()).unwrap_or_default().into_raw(),
+                text.len(),
+            ),
+            _ => (ptr::null_mut(), 0),
+        };
+        let (alg, alg_len) = text(&jwt.alg);
+        let (iss, iss_len) = text(&jwt.iss);
+        let (aud, aud_len) = text(&jwt.aud);
+        ShelterJwt {
+            alg,
+            alg_len,
+            iss,
+            iss_len,
+            aud,
+            aud_len,
+            exp: jwt.exp.unwrap_or(0),
+            iat: jwt.iat.unwrap_or(0),
+        }
+    }
+
+    /// Free the claim strings
+    ///
+    /// # Safety
+    /// The strings must have been created by `from_claims` and not freed yet
+    pub(crate) unsafe fn free_strings(&self) {
+        for text in [self.alg, self.iss, self.aud] {
+            if !text.is_null() {
+                drop(CString::from_raw(text));
+            }
+        }
+    }
+}
+
+/// What a value's PEM blocks hold: a private key if there is one, else the
+/// first certificate or request (the leaf of a chain), else the first block
+#[repr(C)]
+#[derive(Debug)]
+pub struct ShelterPem {
+    /// Block label, e.g. `EC PRIVATE KEY` (null-terminated)
+    pub label: *mut c_char,
+    /// Length of label
+    pub label_len: usize,
+    /// Subject of a certificate or request, e.g. `O=Example,
+    /// CN=auth.example.com` (null-terminated, null if unknown)
+    pub subject: *mut c_char,
+    /// Length of subject
+    pub subject_len: usize,
+    /// Issuer of a certificate (null-terminated, null if unknown)
+    pub issuer: *mut c_char,
+    /// Length of issuer
+    pub issuer_len: usize,
+    /// Certificate expiry (`notAfter`) in Unix seconds (0 if unknown)
+    pub not_after: i64,
+    /// Number of PEM blocks in the value
+    pub blocks: usize,
+    /// What the block holds (see `ShelterPemKind`)
+    pub kind: u8,
+}
+
+impl ShelterPem {
+    pub(crate) fn from_pem(pem: &Pem) -> Self {
+        // Labels are uppercase ASCII and names are stripped of control
+        // characters, but a NUL must never reach a C string with its length
+        let text = |field: Option<&String>| match field {
+            Some(text) if !text.contains('\0') => (
+                CString::new(text.as_str()).unwrap_or_default().into_raw(),
+                text.len(),
+            ),
+            _ => (ptr::null_mut(), 0),
+        };
+        let certificate = pem.certificate.as_ref();
+        let (label, label_len) = text(Some(&pem.label));
+        let (subject, subject_len) = text(certificate.and_then(|c| c.subject.as_ref()));
+        let (issuer, issuer_len) = text(certificate.and_then(|c| c.issuer.as_ref()));
+        ShelterPem {
+            label,
+            label_len,
+            subject,
+            subject_len,
+            issuer,
+            issuer_len,
+            not_after: certificate.and_then(|c| c.not_after).unwrap_or(0),
+            blocks: pem.blocks,
+            kind: pem.kind as u8,
+        }
+    }
+
+    /// Free the label, subject and issuer
+    ///
+    /// # Safety
+    /// The strings must have been created by `from_pem` and not freed yet
+    pub(crate) unsafe fn free_strings(&self) {
+        for text in [self.label, self.subject, self.issuer] {
+            if !text.is_null() {
+                drop(CString::from_raw(text));
+            }
+        }
+    }
+}
+
+/// What an encoded value carries
+#[repr(C)]
+pub struct ShelterPayload {
+    /// Summary, e.g. `base64 → JSON with private_key` (null-terminated, null
+    /// if the value is not encoded)
+    pub summary: *mut c_char,
+    /// Length of summary
+    pub summary_len: usize,
+    /// What makes the payload secret: a secret type id, JSON key path or URL
+    /// component (null-terminated, null if none was found)
+    pub secret: *mut c_char,
+    /// Length of secret
+    pub secret_len: usize,
+    /// Innermost decoded bytes (null unless requested; wiped when freed)
+    pub decoded: *mut u8,
+    /// Length of decoded
+    pub decoded_len: usize,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+    /// Outermost encoding (see `ShelterEncoding`)
+    pub encoding: u8,
+    /// Number of encoded layers decoded
+    pub layers: u8,
+    /// Type of the decoded payload (see `ShelterValueType`; `None` when it is
+    /// binary)
+    pub payload_type: u8,
+}
+
+impl ShelterPayload {
+    /// Create a successful result, with the decoded bytes only if requested
+    pub(crate) fn ok(payload: Option<Payload>, include_decoded: bool) -> *mut Self {
+        let Some(payload) = payload else {
+            return Box::into_raw(Box::new(Self::empty(ptr::null_mut())));
+        };
+        let summary = payload.summary();
+        let (decoded, decoded_len) = if include_decoded {
+            let bytes = payload.decoded.as_bytes().to_vec().into_boxed_slice();
+            let len = bytes.len();
+            (Box::into_raw(bytes) as *mut u8, len)
+        } else {
+            (ptr::null_mut(), 0)
+        };
+        Box::into_raw(Box::new(ShelterPayload {
+            summary_len: summary.len(),
+            summary: CString::new(summary).unwrap_or_default().into_raw(),
+            secret_len: payload.secret.as_ref().map_or(0, String::len),
+            secret: payload.secret.map_or(ptr::null_mut(), |secret| {
+                CString::new(secret).unwrap_or_default().into_raw()
+            }),
+            decoded,
+            decoded_len,
+            error: ptr::null_mut(),
+            encoding: payload.layers[0] as u8,
+            layers: payload.layers.len() as u8,
+            payload_type: payload.kind as u8,
+        }))
+    }
+
+    /// Create an error result
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+        Box::into_raw(Box::new(Self::empty(error)))
+    }
+
+    fn empty(error: *mut c_char) -> Self {
+        ShelterPayload {
+            summary: ptr::null_mut(),
+            summary_len: 0,
+            secret: ptr::null_mut(),
+            secret_len: 0,
+            decoded: ptr::null_mut(),
+            decoded_len: 0,
+            error,
+            encoding: ShelterEncoding::None as u8,
+            layers: 0,
+            payload_type: ShelterValueType::None as u8,
+        }
+    }
+}
+
+/// What `shelter_verify_value` may disclose besides match/no-match
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShelterVerifyPolicy {
+    /// Report how many leading characters match (lets a guesser recover the
+    /// value one character at a time, so off by default)
+    pub report_prefix: u8,
+}
+
+/// Result of checking a candidate against a hidden value
+#[repr(C)]
+pub struct ShelterVerifyResult {
+    /// Index of the compared entry (`usize::MAX` if the key was not found)
+    pub entry_index: usize,
+    /// Leading characters shared with the value (0 unless `prefix_reported`)
+    pub prefix_length: usize,
+    /// Whether an uncommented entry with the key exists
+    pub found: u8,
+    /// Whether the candidate equals the value
+    pub matched: u8,
+    /// Whether `prefix_length` was computed (policy allowed it)
+    pub prefix_reported: u8,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterVerifyResult {
+    /// Create a successful result
+    #[inline]
+    pub fn ok(
+        entry_index: Option<usize>,
+        matched: bool,
+        prefix_length: Option<usize>,
+    ) -> *mut Self {
+        Box::into_raw(Box::new(ShelterVerifyResult {
+            entry_index: entry_index.unwrap_or(usize::MAX),
+            prefix_length: prefix_length.unwrap_or(0),
+            found: entry_index.is_some() as u8,
+            matched: matched as u8,
+            prefix_reported: prefix_length.is_some() as u8,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error result
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterVerifyResult {
+            entry_index: usize::MAX,
+            prefix_length: 0,
+            found: 0,
+            matched: 0,
+            prefix_reported: 0,
+            error,
+        }))
+    }
+}
+
+/// Stage of mode selection a trace step belongs to
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelterTraceStage {
+    /// A policy rule
+    Policy = 0,
+    /// A key pattern
+    KeyPattern = 1,
+    /// A source pattern
+    SourcePattern = 2,
+    /// The default mode
+    Default = 3,
+    /// An inline `# shelter:` directive
+    Directive = 4,
+    /// A public key prefix (see `public`)
+    PublicPrefix = 5,
+}
+
+/// One rule considered while choosing an entry's mode
+#[repr(C)]
+#[derive(Debug)]
+pub struct ShelterTraceStep {
+    /// Pattern, policy rule conditions, directive line or default mode name
+    /// (null-terminated)
+    pub rule: *mut c_char,
+    /// Mode the rule selects (null-terminated)
+    pub mode: *mut c_char,
+    /// Condition a policy rule failed on, or why a directive was ignored (null
+    /// otherwise)
+    pub detail: *mut c_char,
+    /// Index of the rule within its stage (`usize::MAX` for the default and
+    /// directives)
+    pub rule_index: usize,
+    /// Priority for policy rules, specificity for patterns
+    pub rank: i64,
+    /// Stage (see `ShelterTraceStage`)
+    pub stage: u8,
+    /// Whether the rule matched the entry
+    pub matched: u8,
+    /// Whether this rule decided the mode
+    pub chosen: u8,
+}
+
+/// Trace of the mode decision for one entry
+#[repr(C)]
+pub struct ShelterExplanation {
+    /// Steps in evaluation order: directive, policy, key patterns, source
+    /// patterns, default
+    pub steps: *mut ShelterTraceStep,
+    /// Number of steps
+    pub count: usize,
+    /// Chosen mode name (null-terminated, null on error)
+    pub mode: *mut c_char,
+    /// Secret type of the value (null if unclassified)
+    pub secret_type: *mut c_char,
+    /// Shannon entropy of the value in bits per character
+    pub entropy: f64,
+    /// Entropy relative to the value's charset and length, 0 to 1
+    pub randomness: f64,
+    /// Stage of the deciding step (see `ShelterTraceStage`)
+    pub stage: u8,
+    /// Whether the chosen mode is unregistered (rendered as full)
+    pub unknown_mode: u8,
+    /// Confidence of secret_type (see `ShelterConfidence`)
+    pub secret_confidence: u8,
+    /// Whether the value looks like a random token
+    pub looks_random: u8,
+    /// Error message (null if no error)
+    pub error: *mut c_char,
+}
+
+impl ShelterExplanation {
+    /// Create a successful explanation
+    #[inline]
+    pub(crate) fn ok(
+        steps: Vec<ShelterTraceStep>,
+        mode: &str,
+        stage: ShelterTraceStage,
+        unknown_mode: bool,
+        secret_type: Option<&str>,
+        secret_confidence: ShelterConfidence,
+        randomness: &Randomness,
+    ) -> *mut Self {
+        let count = steps.len();
+        let steps_ptr = if steps.is_empty() {
+            ptr::null_mut()
+        } else {
+            Box::into_raw(steps.into_boxed_slice()) as *mut ShelterTraceStep
+        };
+
+        Box::into_raw(Box::new(ShelterExplanation {
+            steps: steps_ptr,
+            count,
+            mode: CString::new(mode).unwrap_or_default().into_raw(),
+            secret_type: secret_type.map_or(ptr::null_mut(), |kind| {
+                CString::new(kind).unwrap_or_default().into_raw()
+            }),
+            entropy: randomness.entropy,
+            randomness: randomness.score,
+            stage: stage as u8,
+            unknown_mode: unknown_mode as u8,
+            secret_confidence: secret_confidence as u8,
+            looks_random: randomness.looks_random as u8,
+            error: ptr::null_mut(),
+        }))
+    }
+
+    /// Create an error explanation
+    #[inline]
+    pub fn err(message: &str) -> *mut Self {
+        let error = CString::new(message)
+            .unwrap_or_else(|_| CString::new("Unknown error").unwrap())
+            .into_raw();
+
+        Box::into_raw(Box::new(ShelterExplanation {
+            steps: ptr::null_mut(),
+            count: 0,
+            mode: ptr::null_mut(),
+            secret_type: ptr::null_mut(),
+            entropy: 0.0,
+            randomness: 0.0,
+            stage: ShelterTraceStage::Default as u8,
+            unknown_mode: 0,
+            secret_confidence: ShelterConfidence::None as u8,
+            looks_random: 0,
+            error,
+        }))
+    }
+}
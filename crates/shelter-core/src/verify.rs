@@ -0,0 +1,184 @@
+//! Overlay leak verification
+//!
+//! Simulates each displayed line with the planned overlays drawn on top and
+//! reports every byte of a masked value that would still be visible. A
+//! character counts as hidden only when all of its cells lie under an overlay,
+//! so half-covered wide characters and tabs are reported too.
+
+use crate::display::{char_cells, layout, Cell};
+use crate::document::Document;
+use crate::render::{hidden_raw_ranges, line_end, RenderSettings};
+use crate::types::ShelterLeak;
+use std::collections::HashMap;
+
+/// An overlay as placed in the buffer (0-based row, byte column)
+pub(crate) struct PlacedOverlay<'a> {
+    pub row: usize,
+    pub col: usize,
+    pub text: &'a str,
+}
+
+/// Lazily computed line layouts and covered cell ranges, keyed by 0-based row
+struct Screen<'a> {
+    input: &'a str,
+    doc: &'a Document<'a>,
+    tabstop: usize,
+    layouts: HashMap<usize, Vec<Cell>>,
+    covered: HashMap<usize, Vec<(usize, usize)>>,
+}
+
+impl<'a> Screen<'a> {
+    fn layout(&mut self, row: usize) -> &[Cell] {
+        let (input, doc, tabstop) = (self.input, self.doc, self.tabstop);
+        self.layouts.entry(row).or_insert_with(|| {
+            let start = doc.line_start(row + 1);
+            let end = line_end(input, doc, row + 1);
+            layout(input.get(start..end).unwrap_or(""), tabstop)
+        })
+    }
+
+    /// Record the cells an overlay covers
+    fn place(&mut self, overlay: &PlacedOverlay) {
+        let tabstop = self.tabstop;
+        let cells = self.layout(overlay.row);
+        let index = cells.partition_point(|c| c.byte + c.len <= overlay.col);
+        let start = match cells.get(index) {
+            Some(cell) => cell.start,
+            None => cells.last().map_or(0, |c| c.end),
+        };
+        let end = overlay
+            .text
+            .chars()
+            .fold(start, |col, c| col + char_cells(c, col, tabstop));
+        if end > start {
+            self.covered
+                .entry(overlay.row)
+                .or_default()
+                .push((start, end));
+        }
+    }
+
+    /// Sort and merge covered ranges so containment is a single lookup
+    fn merge(&mut self) {
+        for ranges in self.covered.values_mut() {
+            ranges.sort_unstable();
+            let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+            for &(start, end) in ranges.iter() {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            *ranges = merged;
+        }
+    }
+
+    fn is_covered(&self, row: usize, cell: &Cell) -> bool {
+        // Zero-width characters are drawn with the preceding cell
+        let (start, end) = if cell.end > cell.start {
+            (cell.start, cell.end)
+        } else {
+            (cell.start.saturating_sub(1), cell.start.max(1))
+        };
+        self.covered.get(&row).is_some_and(|ranges| {
+            let i = ranges.partition_point(|r| r.1 < end);
+            ranges.get(i).is_some_and(|r| r.0 <= start && end <= r.1)
+        })
+    }
+}
+
+/// Report visible bytes of masked values, merged into per-row ranges
+pub(crate) fn verify_overlays(
+    input: &str,
+    doc: &Document,
+    settings: &RenderSettings,
+    overlays: &[PlacedOverlay],
+    tabstop: usize,
+) -> Vec<ShelterLeak> {
+    let mut screen = Screen {
+        input,
+        doc,
+        tabstop,
+        layouts: HashMap::new(),
+        covered: HashMap::new(),
+    };
+    for overlay in overlays {
+        screen.place(overlay);
+    }
+    screen.merge();
+
+    let mut leaks: Vec<ShelterLeak> = Vec::new();
+    for (index, pair) in doc.pairs.iter().enumerate() {
+        if pair.kv.is_comment && settings.skip_comments {
+            continue;
+        }
+        let (content_start, content_end) = pair.content_range();
+        if content_end <= content_start || content_end > input.len() {
+            continue;
+        }
+
+        // Values the mode or a reveal leaves untouched are meant to be visible
+        let mode = settings.select(pair).mode;
+        let reveal = settings.reveal_for(pair.id);
+        if mode.leaves_visible(&pair.kv.value)
+            || reveal.is_full()
+            || (pair.value_end_line <= pair.line_number
+                && reveal.apply(mode, &pair.kv.value) == input[content_start..content_end])
+        {
+            continue;
+        }
+
+        // Structured values only hide their credentials (see `render_pair`)
+        let hidden = hidden_raw_ranges(&input[content_start..content_end], pair, mode, reveal).map(
+            |ranges| {
+                ranges
+                    .into_iter()
+                    .map(|(start, end)| (content_start + start, content_start + end))
+                    .collect::<Vec<_>>()
+            },
+        );
+
+        for line_number in pair.line_number..=pair.value_end_line {
+            let row = line_number - 1;
+            let line_start = doc.line_start(line_number);
+            let start = content_start.max(line_start) - line_start;
+            let end = content_end.min(line_end(input, doc, line_number)) - line_start;
+
+            let cells: Vec<Cell> = screen
+                .layout(row)
+                .iter()
+                .filter(|c| c.byte >= start && c.byte < end)
+                .filter(|c| {
+                    let byte = line_start + c.byte;
+                    hidden.as_ref().is_none_or(|ranges| {
+                        ranges
+                            .iter()
+                            .any(|&(start, end)| byte >= start && byte < end)
+                    })
+                })
+                .copied()
+                .collect();
+            for cell in cells {
+                if screen.is_covered(row, &cell) {
+                    continue;
+                }
+                match leaks.last_mut() {
+                    Some(leak)
+                        if leak.entry_index == index
+                            && leak.row == row
+                            && leak.end_col == cell.byte =>
+                    {
+                        leak.end_col = cell.byte + cell.len;
+                    }
+                    _ => leaks.push(ShelterLeak {
+                        row,
+                        col: cell.byte,
+                        end_col: cell.byte + cell.len,
+                        entry_index: index,
+                    }),
+                }
+            }
+        }
+    }
+    leaks
+}
@@ -0,0 +1,183 @@
+//! Weak and placeholder values
+//!
+//! Reports values that were never filled in or are insecure defaults, the
+//! usual result of copying `.env.example` to `.env`. At most one diagnostic is
+//! reported per entry, checked in this order:
+//! - `placeholder-value`: `changeme`, `your-api-key-here`, `<TOKEN>`, `xxx`,
+//!   `sk_test_xxxx`, ... (any key; phrases like `your name` or `<token>` only
+//!   for secret keys)
+//! - `default-credential`: `password`, `secret`, `admin`, ... (secret keys)
+//! - `repeated-characters`: one character repeated, e.g. `aaaaaaaa` (secret
+//!   keys)
+//! - `empty-secret` and `short-secret`: shorter than the minimum length
+//!   (secret keys)
+//!
+//! Secret keys are recognised by name: the last word is a secret word
+//! (`DB_PASSWORD`, `GITHUB_TOKEN`) or `KEY` qualified by one
+//! (`STRIPE_SECRET_KEY`, `API_KEY`). References such as `${OTHER}` are skipped.
+
+use crate::diagnostics::Diagnostic;
+use crate::types::ShelterSeverity;
+
+/// Minimum length of secret values when none is configured
+pub(crate) const DEFAULT_MIN_SECRET_LENGTH: usize = 12;
+
+/// Last words of keys that hold secrets
+const SECRET_WORDS: &[&str] = &[
+    "password",
+    "passwd",
+    "pwd",
+    "pass",
+    "passphrase",
+    "secret",
+    "token",
+    "apikey",
+    "credential",
+    "credentials",
+];
+
+/// Words that make a trailing `KEY` secret (`PRIVATE_KEY`, `ACCESS_KEY`, ...)
+const KEY_QUALIFIERS: &[&str] = &[
+    "api",
+    "private",
+    "access",
+    "secret",
+    "signing",
+    "encryption",
+    "master",
+    "auth",
+];
+
+/// Whole values that mark a template entry (lowercase, without separators)
+const PLACEHOLDERS: &[&str] = &["changeme", "replaceme", "placeholder", "tbd", "fixme"];
+
+/// Well-known default credentials (lowercase)
+const DEFAULT_CREDENTIALS: &[&str] = &[
+    "password", "passwd", "pass", "secret", "admin", "root", "default", "test", "qwerty",
+    "letmein", "123456", "12345678",
+];
+
+/// Whether a key names a secret
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let words: Vec<String> = key
+        .split(['_', '-', '.'])
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    match words.as_slice() {
+        [.., last] if SECRET_WORDS.contains(&last.as_str()) => true,
+        [.., qualifier, last] if last == "key" => {
+            KEY_QUALIFIERS.contains(&qualifier.as_str())
+                || SECRET_WORDS.contains(&qualifier.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Whether a value is an unfilled placeholder
+///
+/// Template phrases (`<...>`, `your ...`, `... here`) also occur in ordinary
+/// text, so for other keys they must be a single `<NAME>` or dashed token.
+fn is_placeholder(value: &str, secret: bool) -> bool {
+    if let Some(name) = value
+        .strip_prefix('<')
+        .and_then(|rest| rest.strip_suffix('>'))
+    {
+        let template = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '));
+        let shouted = name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        return template && (secret || shouted);
+    }
+    let lower = value.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(['-', '_', '.', ' ', ':', '/'])
+        .filter(|word| !word.is_empty())
+        .collect();
+    let joined = words.concat();
+    let token = !value.chars().any(char::is_whitespace);
+    PLACEHOLDERS.contains(&joined.as_str())
+        // `your-api-key-here`, `your_secret`, `put-token-here`
+        || ((secret || token)
+            && words.len() >= 2
+            && (words[0] == "your" || words[words.len() - 1] == "here"))
+        // `xxx`, `xxxx-xxxx`, `sk_test_xxxxxxxx`
+        || words
+            .iter()
+            .any(|word| word.len() >= 3 && word.bytes().all(|b| b == b'x'))
+}
+
+/// Whether a value is one character repeated
+fn is_repeated(value: &str) -> bool {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => value.len() >= 3 && chars.all(|c| c == first),
+        None => false,
+    }
+}
+
+/// A diagnostic for a weak or placeholder value, if it is one
+pub(crate) fn weak_value_diagnostic(
+    key: &str,
+    value: &str,
+    min_length: usize,
+    line_number: usize,
+    (start, end): (usize, usize),
+    entry_index: usize,
+) -> Option<Diagnostic> {
+    if value.contains("${") {
+        return None;
+    }
+    let secret = is_secret_key(key);
+    let length = value.chars().count();
+    let (code, message, severity) = if is_placeholder(value, secret) {
+        (
+            "placeholder-value",
+            format!("{} looks like an unfilled placeholder", key),
+            ShelterSeverity::Warning,
+        )
+    } else if !secret {
+        return None;
+    } else if DEFAULT_CREDENTIALS.contains(&value.to_lowercase().as_str()) {
+        (
+            "default-credential",
+            format!("{} is set to a well-known default", key),
+            ShelterSeverity::Warning,
+        )
+    } else if is_repeated(value) {
+        (
+            "repeated-characters",
+            format!("{} is one character repeated", key),
+            ShelterSeverity::Warning,
+        )
+    } else if length == 0 {
+        (
+            "empty-secret",
+            format!("{} is empty", key),
+            ShelterSeverity::Warning,
+        )
+    } else if length < min_length {
+        (
+            "short-secret",
+            format!(
+                "{} is {} characters long, shorter than {}",
+                key, length, min_length
+            ),
+            ShelterSeverity::Info,
+        )
+    } else {
+        return None;
+    };
+    Some(Diagnostic {
+        code,
+        message,
+        severity,
+        line_number,
+        start,
+        end,
+        entry_index: Some(entry_index),
+    })
+}
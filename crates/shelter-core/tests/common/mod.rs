@@ -0,0 +1,70 @@
+//! FFI helpers shared by the integration tests
+
+// Each test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use std::ffi::{c_char, CStr};
+use std::slice;
+
+use shelter_core::*;
+
+/// Pointer and length of a string
+pub fn s(text: &'static str) -> (*const c_char, usize) {
+    (text.as_ptr() as *const c_char, text.len())
+}
+
+/// A key or source pattern choosing `mode`
+pub fn rule(pattern: &'static str, mode: &'static str) -> ShelterPatternRule {
+    ShelterPatternRule {
+        pattern: pattern.as_ptr() as *const c_char,
+        pattern_len: pattern.len(),
+        mode: mode.as_ptr() as *const c_char,
+        mode_len: mode.len(),
+    }
+}
+
+/// Text of a null-terminated string (None if null)
+pub unsafe fn text(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_str().unwrap().to_owned())
+}
+
+/// Text of a null-terminated string that must be `len` bytes long (None if
+/// null)
+pub unsafe fn sized_text(ptr: *const c_char, len: usize) -> Option<String> {
+    let text = text(ptr);
+    if let Some(text) = &text {
+        assert_eq!(text.len(), len);
+    }
+    text
+}
+
+/// `count` elements at `ptr` (which may be null when there are none)
+pub unsafe fn items<'a, T>(ptr: *const T, count: usize) -> &'a [T] {
+    if count == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, count)
+    }
+}
+
+/// Parse content with analysis options, asserting it succeeded
+pub unsafe fn parse_with(content: &str, analysis: &ShelterAnalysisOptions) -> *mut ShelterResult {
+    let result = shelter_parse_with(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        ShelterParseOptions::default(),
+        analysis,
+    );
+    assert!((*result).error.is_null());
+    result
+}
+
+/// Entries of a parse result
+pub unsafe fn entries<'a>(result: *mut ShelterResult) -> &'a [ShelterEntry] {
+    items((*result).entries, (*result).count)
+}
+
+/// Diagnostics of a parse result
+pub unsafe fn diagnostics<'a>(result: *mut ShelterResult) -> &'a [ShelterDiagnostic] {
+    items((*result).diagnostics, (*result).diagnostic_count)
+}
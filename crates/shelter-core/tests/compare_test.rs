@@ -0,0 +1,122 @@
+//! Integration tests for constant-time value checks
+
+use std::ffi::c_char;
+
+use shelter_core::*;
+
+/// Verify a candidate, returning (found, matched, prefix length if reported)
+unsafe fn verify(
+    content: &str,
+    key: &str,
+    candidate: &str,
+    report_prefix: bool,
+) -> (bool, bool, Option<usize>) {
+    let policy = ShelterVerifyPolicy {
+        report_prefix: report_prefix as u8,
+    };
+    let result = shelter_verify_value(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        key.as_ptr() as *const c_char,
+        key.len(),
+        candidate.as_ptr() as *const c_char,
+        candidate.len(),
+        &policy,
+    );
+    assert!((*result).error.is_null());
+
+    let r = &*result;
+    let out = (
+        r.found == 1,
+        r.matched == 1,
+        (r.prefix_reported == 1).then_some(r.prefix_length),
+    );
+    shelter_free_verify_result(result);
+    out
+}
+
+// =============================================================================
+// Match Tests
+// =============================================================================
+
+#[test]
+fn test_verify_match_and_mismatch() {
+    let content = "API_KEY='sk_live_abc123'\nOTHER=x";
+    unsafe {
+        assert_eq!(
+            verify(content, "API_KEY", "sk_live_abc123", false),
+            (true, true, None)
+        );
+        assert_eq!(
+            verify(content, "API_KEY", "sk_live_abc124", false),
+            (true, false, None)
+        );
+        assert_eq!(
+            verify(content, "API_KEY", "sk_live_abc1234", false),
+            (true, false, None)
+        );
+    }
+}
+
+#[test]
+fn test_verify_missing_key() {
+    let result = unsafe { verify("A=1\n#B=2", "B", "2", true) };
+    assert_eq!(result, (false, false, None));
+}
+
+#[test]
+fn test_verify_uses_last_uncommented_entry() {
+    let content = "TOKEN=old\nTOKEN=new\n#TOKEN=commented";
+    unsafe {
+        assert!(verify(content, "TOKEN", "new", false).1);
+        assert!(!verify(content, "TOKEN", "old", false).1);
+        assert!(!verify(content, "TOKEN", "commented", false).1);
+    }
+}
+
+// =============================================================================
+// Prefix Policy Tests
+// =============================================================================
+
+#[test]
+fn test_verify_prefix_only_with_policy() {
+    let content = "API_KEY=sk_live_abc123";
+    unsafe {
+        assert_eq!(verify(content, "API_KEY", "sk_test", false).2, None);
+        assert_eq!(verify(content, "API_KEY", "sk_test", true).2, Some(3));
+        assert_eq!(
+            verify(content, "API_KEY", "sk_live_abc123", true),
+            (true, true, Some(14))
+        );
+        assert_eq!(verify(content, "API_KEY", "", true).2, Some(0));
+    }
+}
+
+#[test]
+fn test_verify_prefix_counts_characters() {
+    // "п" and "р" share their first UTF-8 byte
+    let result = unsafe { verify("PASS=пароль", "PASS", "паро", true) };
+    assert_eq!(result, (true, false, Some(4)));
+    let result = unsafe { verify("PASS=пароль", "PASS", "пар", true) };
+    assert_eq!(result.2, Some(3));
+    let result = unsafe { verify("PASS=пароль", "PASS", "пп", true) };
+    assert_eq!(result.2, Some(1));
+}
+
+#[test]
+fn test_verify_null_input() {
+    unsafe {
+        let result = shelter_verify_value(
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+        );
+        assert!(!(*result).error.is_null());
+        assert_eq!((*result).found, 0);
+        shelter_free_verify_result(result);
+    }
+}
@@ -0,0 +1,206 @@
+//! Integration tests for inline `# shelter:` directives
+
+use std::ffi::c_char;
+
+mod common;
+
+use common::*;
+use shelter_core::*;
+
+/// An entry as (key, directive mode)
+type Entry = (String, Option<String>);
+
+/// A diagnostic as (code, line)
+type Diagnostic = (String, usize);
+
+/// Parse content, returning its entries and diagnostics
+unsafe fn parse(content: &str) -> (Vec<Entry>, Vec<Diagnostic>) {
+    parse_analysed(content, &ShelterAnalysisOptions::default())
+}
+
+/// Parse content with analysis options, returning its entries and diagnostics
+unsafe fn parse_analysed(
+    content: &str,
+    analysis: &ShelterAnalysisOptions,
+) -> (Vec<Entry>, Vec<Diagnostic>) {
+    let result = parse_with(content, analysis);
+
+    let found = entries(result)
+        .iter()
+        .map(|entry| (text(entry.key).unwrap(), text(entry.directive_mode)))
+        .collect();
+    let reported = diagnostics(result)
+        .iter()
+        .map(|diagnostic| (text(diagnostic.code).unwrap(), diagnostic.line_number))
+        .collect();
+    shelter_free_result(result);
+    (found, reported)
+}
+
+fn entry(key: &str, mode: Option<&str>) -> Entry {
+    (key.to_owned(), mode.map(str::to_owned))
+}
+
+// =============================================================================
+// Directive Tests
+// =============================================================================
+
+#[test]
+fn test_directive_next_entry_and_blocks() {
+    let content = "\
+# shelter: mode=partial
+A=1
+B=2
+# shelter: begin none
+C=3
+# shelter: mode=full
+D=4
+# shelter: end
+E=5";
+    let (entries, diagnostics) = unsafe { parse(content) };
+    assert_eq!(
+        entries,
+        vec![
+            entry("A", Some("partial")),
+            entry("B", None),
+            entry("C", Some("none")),
+            entry("D", Some("full")),
+            entry("E", None),
+        ]
+    );
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn test_directive_ignore_file() {
+    let content = "A=1\n# shelter: ignore-file\n# shelter: mode=full\nB=2";
+    let (entries, _) = unsafe { parse(content) };
+    assert_eq!(
+        entries,
+        vec![entry("A", Some("none")), entry("B", Some("full"))]
+    );
+}
+
+#[test]
+fn test_directive_lines_are_not_entries() {
+    // `mode=partial` inside the directive is not a commented-out entry
+    let content = "# shelter: mode=partial\n#OLD=x\n# regular comment\nNEW=y";
+    let (entries, _) = unsafe { parse(content) };
+    assert_eq!(
+        entries,
+        vec![entry("OLD", Some("partial")), entry("NEW", None)]
+    );
+}
+
+#[test]
+fn test_directive_diagnostics() {
+    let content = "\
+# shelter: frobnicate
+# shelter: mode=
+# shelter: end
+# shelter: begin partial
+A=1
+# shelter: mode=none";
+    let (entries, diagnostics) = unsafe { parse(content) };
+    assert_eq!(entries, vec![entry("A", Some("partial"))]);
+    assert_eq!(
+        diagnostics,
+        vec![
+            ("unknown-directive".to_owned(), 1),
+            ("unknown-directive".to_owned(), 2),
+            ("unmatched-end".to_owned(), 3),
+            ("unclosed-block".to_owned(), 4),
+            ("unused-directive".to_owned(), 6),
+        ]
+    );
+}
+
+// =============================================================================
+// Rendering Tests
+// =============================================================================
+
+#[test]
+fn test_directive_unknown_modes() {
+    let content = "\
+# shelter: begin none
+# shelter: mode=fulll
+A=1
+# shelter: begin bogus
+B=2
+# shelter: end
+# shelter: end
+C=3";
+    let (names, names_len) = s("none\npartial\nfull");
+    let analysis = ShelterAnalysisOptions {
+        mode_names: names,
+        mode_names_len: names_len,
+        ..Default::default()
+    };
+    let (entries, diagnostics) = unsafe { parse_analysed(content, &analysis) };
+    assert_eq!(
+        entries,
+        vec![
+            entry("A", Some("none")),
+            entry("B", Some("none")),
+            entry("C", None),
+        ]
+    );
+    assert_eq!(
+        diagnostics,
+        vec![
+            ("unknown-directive".to_owned(), 2),
+            ("unknown-directive".to_owned(), 4),
+        ]
+    );
+
+    // Without mode names any mode is accepted
+    let (entries, diagnostics) = unsafe { parse(content) };
+    assert_eq!(entries[0], entry("A", Some("fulll")));
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn test_render_honours_directives() {
+    let content = "# shelter: mode=none\nPORT=3000\nKEY=secret";
+    let render = |ignore_directives| unsafe {
+        let config = ShelterRenderConfig {
+            ignore_directives,
+            ..Default::default()
+        };
+        let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), &config);
+        assert!((*plan).error.is_null());
+        let rows: Vec<usize> = (0..(*plan).count)
+            .map(|i| (*(*plan).overlays.add(i)).row)
+            .collect();
+        shelter_free_render_plan(plan);
+        rows
+    };
+
+    assert_eq!(render(0), vec![2]);
+    assert_eq!(render(1), vec![1, 2]);
+}
+
+#[test]
+fn test_diff_reports_directive_changes() {
+    let old = "PORT=3000";
+    let new = "# shelter: mode=none\nPORT=3000";
+    unsafe {
+        let parse = |content: &str| {
+            shelter_parse(
+                content.as_ptr() as *const c_char,
+                content.len(),
+                Default::default(),
+            )
+        };
+        let (old, new) = (parse(old), parse(new));
+        let diff = shelter_diff_results(old, new);
+        assert_eq!((*diff).count, 1);
+        assert_eq!(
+            (*(*diff).changes).kind,
+            ShelterChangeKind::ValueChanged as u8
+        );
+        shelter_free_diff(diff);
+        shelter_free_result(old);
+        shelter_free_result(new);
+    }
+}
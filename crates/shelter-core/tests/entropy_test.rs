@@ -0,0 +1,145 @@
+//! Integration tests for entropy and randomness scoring
+
+use std::ffi::c_char;
+
+use shelter_core::*;
+
+/// A value's analysis as (charset, randomness, looks random)
+type Scored = (u8, f64, bool);
+
+/// Parse with entropy analysis, returning each entry's score
+unsafe fn score(content: &str) -> Vec<Scored> {
+    let analysis = ShelterAnalysisOptions {
+        entropy: 1,
+        ..Default::default()
+    };
+    let result = shelter_parse_with(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        Default::default(),
+        &analysis,
+    );
+    assert!((*result).error.is_null());
+
+    let scored = (0..(*result).count)
+        .map(|i| {
+            let entry = &*(*result).entries.add(i);
+            assert!((0.0..=1.0).contains(&entry.randomness));
+            (entry.charset, entry.randomness, entry.looks_random != 0)
+        })
+        .collect();
+    shelter_free_result(result);
+    scored
+}
+
+const DIGITS: u8 = ShelterCharset::Digits as u8;
+const HEX: u8 = ShelterCharset::Hex as u8;
+const ALNUM: u8 = ShelterCharset::Alphanumeric as u8;
+const BASE64: u8 = ShelterCharset::Base64 as u8;
+const OTHER: u8 = ShelterCharset::Other as u8;
+
+// =============================================================================
+// Scoring Tests
+// =============================================================================
+
+#[test]
+fn test_charsets() {
+    let content = "A=3000\nB=deadbeef\nC=localhost\nD=abc+/def=\nE=http://localhost\nF=";
+    let charsets: Vec<u8> = unsafe { score(content) }.iter().map(|s| s.0).collect();
+    assert_eq!(
+        charsets,
+        vec![
+            DIGITS,
+            HEX,
+            ALNUM,
+            BASE64,
+            OTHER,
+            ShelterCharset::None as u8
+        ]
+    );
+}
+
+#[test]
+fn test_random_tokens_look_random() {
+    let content = "\
+HEX=241e8957a8c12f2f2d8a674a6275c7a3
+ALNUM=q8Zr2LxW0pKv9TnY
+B64=CiGfaYRWrHCv35lfIE1ZUsymz5kuuPc-TMYG5aIa";
+    for (charset, randomness, looks_random) in unsafe { score(content) } {
+        assert!(looks_random, "charset {} scored {}", charset, randomness);
+        assert!(randomness > 0.8);
+    }
+}
+
+#[test]
+fn test_ordinary_values_do_not_look_random() {
+    let content = "\
+BOOL=true
+PORT=3000
+HOST=localhost
+REPEATED=aaaaaaaaaaaaaaaaaaaaaaaa
+NAME=this_is_a_long_variable_name
+URL=https://api.example.com/v1
+NUMBER=1234567890123456
+SENTENCE=\"Hello World this is a sentence\"";
+    for (i, (_, randomness, looks_random)) in unsafe { score(content) }.into_iter().enumerate() {
+        assert!(!looks_random, "entry {} scored {}", i, randomness);
+    }
+}
+
+#[test]
+fn test_entropy_not_requested() {
+    let content = "KEY=q8Zr2LxW0pKv9TnY";
+    unsafe {
+        let result = shelter_parse(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            Default::default(),
+        );
+        let entry = &*(*result).entries;
+        assert_eq!((entry.entropy, entry.randomness), (0.0, 0.0));
+        assert_eq!(entry.charset, ShelterCharset::None as u8);
+        assert_eq!(entry.looks_random, 0);
+        shelter_free_result(result);
+    }
+}
+
+// =============================================================================
+// Policy Tests
+// =============================================================================
+
+#[test]
+fn test_policy_looks_random_condition() {
+    let mode = "full";
+    let rules = [ShelterPolicyRule {
+        looks_random: ShelterFlagCondition::Set as u8,
+        mode: mode.as_ptr() as *const c_char,
+        mode_len: mode.len(),
+        ..Default::default()
+    }];
+    let content = "INNOCUOUS=q8Zr2LxW0pKv9TnY\nHOST=localhost";
+    unsafe {
+        let policy = shelter_policy_new(rules.as_ptr(), rules.len());
+        assert!(!policy.is_null());
+        let render = ShelterRenderConfig {
+            policy,
+            ..Default::default()
+        };
+        let analysis = ShelterAnalysisOptions {
+            render: &render,
+            ..Default::default()
+        };
+        let result = shelter_parse_with(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            Default::default(),
+            &analysis,
+        );
+        let chosen: Vec<usize> = (0..(*result).count)
+            .map(|i| (*(*result).entries.add(i)).policy_rule)
+            .collect();
+        assert_eq!(chosen, vec![0, usize::MAX]);
+        shelter_free_result(result);
+        shelter_policy_free(policy);
+    }
+}
@@ -0,0 +1,154 @@
+//! Integration tests for mode decision traces
+
+use std::ffi::c_char;
+
+mod common;
+
+use common::*;
+use shelter_core::*;
+
+/// A step as (stage, rule, matched, chosen)
+type Step = (u8, String, bool, bool);
+
+/// Explain an entry, returning the chosen mode, its stage and all steps
+unsafe fn explain(
+    content: &str,
+    index: usize,
+    config: &ShelterRenderConfig,
+) -> (String, u8, Vec<Step>) {
+    let result = shelter_explain(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        index,
+        config,
+    );
+    assert!((*result).error.is_null());
+    let steps = (0..(*result).count)
+        .map(|i| {
+            let step = &*(*result).steps.add(i);
+            (
+                step.stage,
+                text(step.rule).unwrap(),
+                step.matched != 0,
+                step.chosen != 0,
+            )
+        })
+        .collect();
+    let explained = (text((*result).mode).unwrap(), (*result).stage, steps);
+    shelter_free_explanation(result);
+    explained
+}
+
+const KEY: u8 = ShelterTraceStage::KeyPattern as u8;
+const SOURCE: u8 = ShelterTraceStage::SourcePattern as u8;
+const DEFAULT: u8 = ShelterTraceStage::Default as u8;
+
+#[test]
+fn test_explain_key_and_source_patterns() {
+    let keys = [rule("*_KEY", "partial"), rule("*_PUBLIC*", "none")];
+    let sources = [rule("*.env", "full")];
+    let (source, source_len) = s("/srv/app/.env");
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        source_patterns: sources.as_ptr(),
+        source_pattern_count: sources.len(),
+        source,
+        source_len,
+        ..Default::default()
+    };
+    let content = "API_PUBLIC_KEY=abc\nOTHER=abc";
+
+    let (mode, stage, steps) = unsafe { explain(content, 0, &config) };
+    assert_eq!(mode, "none");
+    assert_eq!(stage, KEY);
+    assert_eq!(
+        steps,
+        vec![
+            (KEY, "*_KEY".to_owned(), true, false),
+            (KEY, "*_PUBLIC*".to_owned(), true, true),
+            (SOURCE, "*.env".to_owned(), true, false),
+            (DEFAULT, "full".to_owned(), true, false),
+        ]
+    );
+
+    let (mode, stage, steps) = unsafe { explain(content, 1, &config) };
+    assert_eq!((mode.as_str(), stage), ("full", SOURCE));
+    assert_eq!(steps.iter().filter(|step| step.3).count(), 1);
+}
+
+#[test]
+fn test_explain_policy_and_classifier() {
+    let (secret_type, secret_type_len) = s("stripe");
+    let (mode, mode_len) = s("fingerprint");
+    let (other, other_len) = s("none");
+    let rules = [
+        ShelterPolicyRule {
+            is_exported: ShelterFlagCondition::Set as u8,
+            mode: other,
+            mode_len: other_len,
+            ..Default::default()
+        },
+        ShelterPolicyRule {
+            secret_type,
+            secret_type_len,
+            mode,
+            mode_len,
+            priority: 5,
+            ..Default::default()
+        },
+    ];
+    let keys = [rule("*", "partial")];
+    unsafe {
+        let policy = shelter_policy_new(rules.as_ptr(), rules.len());
+        let config = ShelterRenderConfig {
+            policy,
+            key_patterns: keys.as_ptr(),
+            key_pattern_count: keys.len(),
+            ..Default::default()
+        };
+        let content = "KEY=sk_live_abcdef";
+        let result = shelter_explain(content.as_ptr() as *const c_char, content.len(), 0, &config);
+        assert!((*result).error.is_null());
+        assert_eq!(text((*result).mode).as_deref(), Some("fingerprint"));
+        assert_eq!((*result).stage, ShelterTraceStage::Policy as u8);
+        assert_eq!(text((*result).secret_type).as_deref(), Some("stripe"));
+        assert_eq!((*result).secret_confidence, ShelterConfidence::Low as u8);
+        assert!((*result).entropy > 0.0);
+
+        // Higher priority first; the failing condition is named
+        let first = &*(*result).steps;
+        let second = &*(*result).steps.add(1);
+        assert_eq!((first.rule_index, first.rank, first.chosen), (1, 5, 1));
+        assert_eq!(second.rule_index, 0);
+        assert_eq!(text(second.detail).as_deref(), Some("is_exported"));
+
+        // The key pattern still matched but did not decide
+        let key = &*(*result).steps.add(2);
+        assert_eq!((key.matched, key.chosen), (1, 0));
+
+        shelter_free_explanation(result);
+        shelter_policy_free(policy);
+    }
+}
+
+#[test]
+fn test_explain_unknown_mode_and_errors() {
+    let keys = [rule("KEY", "missing")];
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+    let content = "KEY=abc";
+    unsafe {
+        let result = shelter_explain(content.as_ptr() as *const c_char, content.len(), 0, &config);
+        assert_eq!((*result).unknown_mode, 1);
+        assert!((*result).secret_type.is_null());
+        shelter_free_explanation(result);
+
+        let result = shelter_explain(content.as_ptr() as *const c_char, content.len(), 1, &config);
+        assert!(!(*result).error.is_null());
+        shelter_free_explanation(result);
+    }
+}
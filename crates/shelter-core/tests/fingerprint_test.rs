@@ -0,0 +1,164 @@
+//! Integration tests for keyed fingerprints
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+const KEY: &str = "per-user key";
+
+fn fingerprint(value: &str, key: &str) -> u64 {
+    unsafe {
+        shelter_fingerprint(
+            value.as_ptr() as *const c_char,
+            value.len(),
+            key.as_ptr() as *const c_char,
+            key.len(),
+        )
+    }
+}
+
+/// Parse content with fingerprints under `key`, returning (key, fingerprint)
+/// pairs
+unsafe fn parse_fingerprints(content: &str, key: &str) -> Vec<(String, u64)> {
+    let analysis = ShelterAnalysisOptions {
+        fingerprint_key: key.as_ptr() as *const c_char,
+        fingerprint_key_len: key.len(),
+        fingerprints: 1,
+        ..Default::default()
+    };
+    let result = shelter_parse_with(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        Default::default(),
+        &analysis,
+    );
+    assert!((*result).error.is_null());
+
+    let pairs = (0..(*result).count)
+        .map(|i| {
+            let entry = &*(*result).entries.add(i);
+            let key = CStr::from_ptr(entry.key).to_str().unwrap().to_owned();
+            (key, entry.fingerprint)
+        })
+        .collect();
+    shelter_free_result(result);
+    pairs
+}
+
+/// Mask a value with a mode from a table
+unsafe fn mask(table: *const ShelterModeTable, mode: &str, value: &str) -> String {
+    let masked = shelter_mask_value(
+        table,
+        mode.as_ptr() as *const c_char,
+        mode.len(),
+        value.as_ptr() as *const c_char,
+        value.len(),
+    );
+    assert!(!masked.is_null());
+    let out = CStr::from_ptr(masked).to_str().unwrap().to_owned();
+    shelter_free_string(masked);
+    out
+}
+
+// =============================================================================
+// Fingerprint Tests
+// =============================================================================
+
+#[test]
+fn test_fingerprint_is_deterministic_and_keyed() {
+    let a = fingerprint("sk_live_abc", KEY);
+    assert_eq!(a, fingerprint("sk_live_abc", KEY));
+    assert_ne!(a, fingerprint("sk_live_abd", KEY));
+    assert_ne!(a, fingerprint("sk_live_abc", "other key"));
+}
+
+#[test]
+fn test_fingerprint_null_value_is_empty() {
+    let empty = unsafe {
+        shelter_fingerprint(
+            std::ptr::null(),
+            0,
+            KEY.as_ptr() as *const c_char,
+            KEY.len(),
+        )
+    };
+    assert_eq!(empty, fingerprint("", KEY));
+}
+
+#[test]
+fn test_parse_fingerprints_compare_across_files() {
+    let local = unsafe { parse_fingerprints("STRIPE_KEY=sk_live_1\nDB=a", KEY) };
+    let staging = unsafe { parse_fingerprints("DB=b\nSTRIPE_KEY='sk_live_1'", KEY) };
+
+    assert_eq!(local[0].1, staging[1].1);
+    assert_ne!(local[1].1, staging[0].1);
+    assert_eq!(local[0].1, fingerprint("sk_live_1", KEY));
+}
+
+#[test]
+fn test_parse_without_fingerprints_leaves_zero() {
+    let content = "A=secret";
+    unsafe {
+        let result = shelter_parse(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            Default::default(),
+        );
+        assert_eq!((*(*result).entries).fingerprint, 0);
+        shelter_free_result(result);
+    }
+}
+
+// =============================================================================
+// Fingerprint Mode Tests
+// =============================================================================
+
+#[test]
+fn test_fingerprint_mode_renders_short_fingerprint() {
+    let table = shelter_mode_table_new();
+    unsafe {
+        shelter_mode_table_set_key(table, KEY.as_ptr() as *const c_char, KEY.len());
+
+        let hex = format!("{:016x}", fingerprint("sk_live_abc", KEY));
+        assert_eq!(
+            mask(table, "fingerprint", "sk_live_abc"),
+            format!("••••[{}]", &hex[..4])
+        );
+        // The mask does not depend on the value's length
+        assert_eq!(mask(table, "fingerprint", "x").chars().count(), 10);
+
+        let options = ShelterModeOptions {
+            kind: ShelterModeKind::Fingerprint as u8,
+            fixed_length: 2,
+            fingerprint_length: 8,
+            ..Default::default()
+        };
+        assert_eq!(
+            shelter_mode_table_set(table, "fp".as_ptr() as *const c_char, 2, &options),
+            1
+        );
+        assert_eq!(
+            mask(table, "fp", "sk_live_abc"),
+            format!("••[{}]", &hex[..8])
+        );
+
+        shelter_mode_table_free(table);
+    }
+}
+
+#[test]
+fn test_fingerprint_length_validated() {
+    let table = shelter_mode_table_new();
+    for fingerprint_length in [0, 17] {
+        let options = ShelterModeOptions {
+            kind: ShelterModeKind::Fingerprint as u8,
+            fingerprint_length,
+            ..Default::default()
+        };
+        assert_eq!(
+            unsafe { shelter_mode_table_set(table, "fp".as_ptr() as *const c_char, 2, &options) },
+            0
+        );
+    }
+    unsafe { shelter_mode_table_free(table) };
+}
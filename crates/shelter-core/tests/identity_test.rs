@@ -0,0 +1,128 @@
+//! Integration tests for stable entry identities and result diffing
+
+use std::ffi::c_char;
+
+use shelter_core::*;
+
+/// Parse content and return the raw result (caller frees)
+unsafe fn parse_raw(content: &str) -> *mut ShelterResult {
+    let opts = ShelterParseOptions {
+        include_comments: 1,
+        track_positions: 1,
+    };
+    let result = shelter_parse(content.as_ptr() as *const c_char, content.len(), opts);
+    assert!(!result.is_null());
+    assert!((*result).error.is_null());
+    result
+}
+
+unsafe fn ids(result: *const ShelterResult) -> Vec<u64> {
+    let result = &*result;
+    (0..result.count)
+        .map(|i| (*result.entries.add(i)).id)
+        .collect()
+}
+
+/// Diff two documents, returning (kind, old_index, new_index) triples
+unsafe fn diff(old: &str, new: &str) -> Vec<(u8, usize, usize)> {
+    let old = parse_raw(old);
+    let new = parse_raw(new);
+    let diff = shelter_diff_results(old, new);
+    assert!((*diff).error.is_null());
+
+    let changes = (0..(*diff).count)
+        .map(|i| {
+            let c = &*(*diff).changes.add(i);
+            (c.kind, c.old_index, c.new_index)
+        })
+        .collect();
+
+    shelter_free_diff(diff);
+    shelter_free_result(old);
+    shelter_free_result(new);
+    changes
+}
+
+// =============================================================================
+// Identity Tests
+// =============================================================================
+
+#[test]
+fn test_ids_stable_across_unrelated_edits() {
+    unsafe {
+        let a = parse_raw("API_KEY=one\nDB_PASS=two");
+        let b = parse_raw("# header\n\nNEW=x\nAPI_KEY=changed\nDB_PASS=two");
+
+        let a_ids = ids(a);
+        let b_ids = ids(b);
+        assert_eq!(a_ids[0], b_ids[1]);
+        assert_eq!(a_ids[1], b_ids[2]);
+
+        shelter_free_result(a);
+        shelter_free_result(b);
+    }
+}
+
+#[test]
+fn test_ids_distinguish_duplicate_keys_and_comments() {
+    unsafe {
+        let result = parse_raw("KEY=a\nKEY=b\n#KEY=c");
+        let result_ids = ids(result);
+        assert_eq!(result_ids.len(), 3);
+        assert_ne!(result_ids[0], result_ids[1]);
+        assert_ne!(result_ids[0], result_ids[2]);
+        assert_eq!(result_ids[0], entry_id("KEY", 0, false));
+        assert_eq!(result_ids[1], entry_id("KEY", 1, false));
+        assert_eq!(result_ids[2], entry_id("KEY", 0, true));
+        shelter_free_result(result);
+    }
+}
+
+// =============================================================================
+// Diff Tests
+// =============================================================================
+
+#[test]
+fn test_diff_identical_is_empty() {
+    let changes = unsafe { diff("A=1\nB=2", "A=1\nB=2") };
+    assert!(changes.is_empty());
+}
+
+#[test]
+fn test_diff_added_and_removed() {
+    let changes = unsafe { diff("A=1\nB=2", "A=1\nC=3") };
+    assert_eq!(
+        changes,
+        vec![
+            (ShelterChangeKind::Removed as u8, 1, usize::MAX),
+            (ShelterChangeKind::Added as u8, usize::MAX, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_moved_and_value_changed() {
+    let changes = unsafe { diff("A=1\nB=2", "\nA=1\nB=3") };
+    assert_eq!(
+        changes,
+        vec![
+            (ShelterChangeKind::Moved as u8, 0, 0),
+            (ShelterChangeKind::ValueChanged as u8, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_quote_change_is_value_change() {
+    let changes = unsafe { diff("A=1", "A='1'") };
+    assert_eq!(changes, vec![(ShelterChangeKind::ValueChanged as u8, 0, 0)]);
+}
+
+#[test]
+fn test_diff_null_input() {
+    unsafe {
+        let diff = shelter_diff_results(std::ptr::null(), std::ptr::null());
+        assert!(!(*diff).error.is_null());
+        shelter_free_diff(diff);
+    }
+}
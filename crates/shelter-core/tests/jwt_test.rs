@@ -0,0 +1,147 @@
+//! Integration tests for JWT claims and expiry diagnostics
+
+mod common;
+
+use common::*;
+use shelter_core::*;
+
+/// RS256 token for `https://auth.example.com` (`\/` escaped), audiences
+/// `api` and `web`, expired at 1700000000
+const EXPIRED: &str = "eyJhbGciOiJSUzI1NiIsInR5cCI6IkpXVCJ9.eyJpc3MiOiJodHRwczpcL1wvYXV0aC5leGFtcGxlLmNvbSIsImF1ZCI6WyJhcGkiLCJ3ZWIiXSwiZXhwIjoxNzAwMDAwMDAwLCJpYXQiOjE2OTk5OTAwMDAuNX0.c2ln";
+
+/// Unsigned HS256 token without issuer, expiring at 1900000000
+const VALID: &str =
+    "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJkZXYiLCJhdWQiOiJhcGkiLCJleHAiOjE5MDAwMDAwMDB9.";
+
+/// Three days after `EXPIRED` expired
+const NOW: i64 = 1_700_000_000 + 3 * 86_400 + 5;
+
+unsafe fn parse(content: &str, jwts: bool) -> *mut ShelterResult {
+    parse_with(
+        content,
+        &ShelterAnalysisOptions {
+            jwts: jwts as u8,
+            now: NOW,
+            ..Default::default()
+        },
+    )
+}
+
+// =============================================================================
+// Claims Tests
+// =============================================================================
+
+#[test]
+fn test_jwt_claims() {
+    let content = format!(
+        "OLD_TOKEN={}\nTOKEN={}\nNOT_JWT=eyJhbGciOi.x.y",
+        EXPIRED, VALID
+    );
+    unsafe {
+        let result = parse(&content, true);
+        let claims: Vec<_> = entries(result)
+            .iter()
+            .map(|entry| {
+                entry.jwt.as_ref().map(|jwt| {
+                    (
+                        sized_text(jwt.alg, jwt.alg_len),
+                        sized_text(jwt.iss, jwt.iss_len),
+                        sized_text(jwt.aud, jwt.aud_len),
+                        jwt.exp,
+                        jwt.iat,
+                    )
+                })
+            })
+            .collect();
+        assert_eq!(
+            claims,
+            vec![
+                Some((
+                    Some("RS256".to_owned()),
+                    Some("https://auth.example.com".to_owned()),
+                    Some("api, web".to_owned()),
+                    1_700_000_000,
+                    1_699_990_000,
+                )),
+                Some((
+                    Some("HS256".to_owned()),
+                    None,
+                    Some("api".to_owned()),
+                    1_900_000_000,
+                    0,
+                )),
+                None,
+            ]
+        );
+        shelter_free_result(result);
+
+        // Not decoded unless requested
+        let result = parse(&content, false);
+        assert!((*(*result).entries).jwt.is_null());
+        assert_eq!((*result).diagnostic_count, 0);
+        shelter_free_result(result);
+    }
+}
+
+// =============================================================================
+// Diagnostic Tests
+// =============================================================================
+
+#[test]
+fn test_expired_token_diagnostic() {
+    let content = format!(
+        "# shelter: bogus\nTOKEN={}\nOLD_TOKEN=\"{}\"",
+        VALID, EXPIRED
+    );
+    unsafe {
+        let result = parse(&content, true);
+        let found: Vec<_> = diagnostics(result)
+            .iter()
+            .map(|d| {
+                (
+                    text(d.code).unwrap(),
+                    text(d.message).unwrap(),
+                    d.line_number,
+                    d.entry_index,
+                )
+            })
+            .collect();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, "unknown-directive");
+        assert_eq!(
+            found[1],
+            (
+                "expired-token".to_owned(),
+                "JWT expired 3 days ago".to_owned(),
+                3,
+                1
+            )
+        );
+
+        // The diagnostic covers the quoted value
+        let expired = &diagnostics(result)[1];
+        assert_eq!(
+            &content[expired.start..expired.end],
+            format!("\"{}\"", EXPIRED)
+        );
+        shelter_free_result(result);
+    }
+}
+
+#[test]
+fn test_hostile_claims() {
+    // `"iss":"\u0000evil.example.com"` and `"exp":-1e300`
+    let token = "eyJhbGciOiJub25lIn0.eyJpc3MiOiJcdTAwMDBldmlsLmV4YW1wbGUuY29tIiwiZXhwIjotMWUzMDB9.";
+    unsafe {
+        let result = parse(&format!("TOKEN={}", token), true);
+        let jwt = &*(*(*result).entries).jwt;
+        // A claim with a NUL cannot be a C string and is dropped
+        assert!(jwt.iss.is_null());
+        assert_eq!(jwt.iss_len, 0);
+        assert_eq!(sized_text(jwt.alg, jwt.alg_len).as_deref(), Some("none"));
+        // An expiry far in the past does not overflow
+        assert_eq!(jwt.exp, i64::MIN);
+        assert_eq!((*result).diagnostic_count, 1);
+        shelter_free_result(result);
+    }
+}
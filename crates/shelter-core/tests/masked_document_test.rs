@@ -0,0 +1,178 @@
+//! Integration tests for fully masked document rendering
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+/// Render a masked copy of content, returning the text and its spans
+unsafe fn render(content: &str, config: &ShelterRenderConfig) -> (String, Vec<ShelterOffsetSpan>) {
+    let doc = shelter_render_masked(content.as_ptr() as *const c_char, content.len(), config);
+    assert!(!doc.is_null());
+    assert!((*doc).error.is_null(), "render_masked returned an error");
+
+    let text = CStr::from_ptr((*doc).text).to_str().unwrap().to_owned();
+    assert_eq!(text.len(), (*doc).text_len);
+    let spans = (0..(*doc).span_count)
+        .map(|i| *(*doc).spans.add(i))
+        .collect();
+
+    shelter_free_masked_document(doc);
+    (text, spans)
+}
+
+fn rule(pattern: &'static str, mode: &'static str) -> ShelterPatternRule {
+    ShelterPatternRule {
+        pattern: pattern.as_ptr() as *const c_char,
+        pattern_len: pattern.len(),
+        mode: mode.as_ptr() as *const c_char,
+        mode_len: mode.len(),
+    }
+}
+
+// =============================================================================
+// Structure Tests
+// =============================================================================
+
+#[test]
+fn test_masked_preserves_structure() {
+    let content = "# Database\nexport DB_PASS='hunter2' # inline\n\nAPI_KEY=\"abc\"\r\nEMPTY=\n";
+    let (text, spans) = unsafe { render(content, &Default::default()) };
+
+    assert_eq!(
+        text,
+        "# Database\nexport DB_PASS='*******' # inline\n\nAPI_KEY=\"***\"\r\nEMPTY=\n"
+    );
+    assert_eq!(spans.len(), 2);
+    assert_eq!(text.lines().count(), content.lines().count());
+}
+
+#[test]
+fn test_masked_spans_map_to_original() {
+    let keys = [rule("SHORT", "short")];
+    let modes = shelter_mode_table_new();
+    let options = ShelterModeOptions {
+        fixed_length: 2,
+        ..Default::default()
+    };
+    unsafe {
+        shelter_mode_table_set(modes, "short".as_ptr() as *const c_char, 5, &options);
+    }
+    let config = ShelterRenderConfig {
+        modes,
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+
+    let content = "SHORT=longsecret\nKEY=value";
+    let (text, spans) = unsafe { render(content, &config) };
+    assert_eq!(text, "SHORT=**\nKEY=*****");
+
+    for span in &spans {
+        assert_eq!(
+            &content[..span.original_start][span.original_start.saturating_sub(4)..],
+            &text[..span.masked_start][span.masked_start.saturating_sub(4)..]
+        );
+    }
+    assert_eq!(
+        spans[1],
+        ShelterOffsetSpan {
+            original_start: 21,
+            original_end: 26,
+            masked_start: 13,
+            masked_end: 18,
+            entry_index: 1,
+        }
+    );
+
+    unsafe { shelter_mode_table_free(modes) };
+}
+
+#[test]
+fn test_masked_multiline_keeps_line_breaks() {
+    let content = "CERT=\"line one\nline two\"\nNEXT=x";
+    let (text, spans) = unsafe { render(content, &Default::default()) };
+    assert_eq!(text, "CERT=\"********\n********\"\nNEXT=*");
+    assert_eq!(spans[0].entry_index, 0);
+}
+
+#[test]
+fn test_masked_escapes_double_quoted_masks() {
+    let modes = shelter_mode_table_new();
+    let mask = "\"";
+    let options = ShelterModeOptions {
+        mask_char: mask.as_ptr() as *const c_char,
+        mask_char_len: mask.len(),
+        ..Default::default()
+    };
+    unsafe {
+        shelter_mode_table_set(modes, "full".as_ptr() as *const c_char, 4, &options);
+    }
+    let config = ShelterRenderConfig {
+        modes,
+        ..Default::default()
+    };
+
+    let (text, _) = unsafe { render("A=\"ab\"\nB='ab'", &config) };
+    assert_eq!(text, "A=\"\\\"\\\"\"\nB='\"\"'");
+
+    unsafe { shelter_mode_table_free(modes) };
+}
+
+// =============================================================================
+// Mode Selection Tests
+// =============================================================================
+
+#[test]
+fn test_masked_respects_modes_and_comments() {
+    let keys = [rule("PUBLIC_*", "none"), rule("*_TOKEN", "partial")];
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+    let content = "PUBLIC_URL=https://x\nGH_TOKEN=ghp_abcdef123\n#OLD=secret";
+    let (text, spans) = unsafe { render(content, &config) };
+    assert_eq!(
+        text,
+        "PUBLIC_URL=https://x\nGH_TOKEN=ghp*******123\n#OLD=secret"
+    );
+    assert_eq!(spans.len(), 1);
+
+    let config = ShelterRenderConfig {
+        skip_comments: 0,
+        ..config
+    };
+    let (text, _) = unsafe { render(content, &config) };
+    assert!(text.ends_with("#OLD=******"));
+}
+
+#[test]
+fn test_masked_null_input() {
+    unsafe {
+        let doc = shelter_render_masked(std::ptr::null(), 0, std::ptr::null());
+        assert!(!(*doc).error.is_null());
+        assert!((*doc).text.is_null());
+        shelter_free_masked_document(doc);
+    }
+}
+
+#[test]
+fn test_masked_nul_byte() {
+    // A NUL kept in a comment cannot be returned as a C string
+    let content = "# note\0\nA=1\n";
+    unsafe {
+        let doc = shelter_render_masked(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            std::ptr::null(),
+        );
+        assert_eq!(
+            CStr::from_ptr((*doc).error).to_str().unwrap(),
+            "Masked document contains a NUL byte"
+        );
+        assert!((*doc).text.is_null());
+        assert_eq!((*doc).text_len, 0);
+        shelter_free_masked_document(doc);
+    }
+}
@@ -0,0 +1,178 @@
+//! Integration tests for compiled pattern sets
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+fn rule(pattern: &'static str, mode: &'static str) -> ShelterPatternRule {
+    ShelterPatternRule {
+        pattern: pattern.as_ptr() as *const c_char,
+        pattern_len: pattern.len(),
+        mode: mode.as_ptr() as *const c_char,
+        mode_len: mode.len(),
+    }
+}
+
+/// Compile patterns (all mapped to the same mode)
+fn compile(patterns: &[&'static str], options: ShelterPatternOptions) -> *mut ShelterPatternSet {
+    let rules: Vec<_> = patterns.iter().map(|p| rule(p, "mode")).collect();
+    let set = unsafe { shelter_pattern_set_new(rules.as_ptr(), rules.len(), &options) };
+    assert!(!set.is_null(), "failed to compile {:?}", patterns);
+    set
+}
+
+/// Index of the winning pattern, if any
+fn best(set: *const ShelterPatternSet, text: &str) -> Option<usize> {
+    let index =
+        unsafe { shelter_pattern_set_match(set, text.as_ptr() as *const c_char, text.len()) };
+    (index != usize::MAX).then_some(index)
+}
+
+/// Whether a single pattern matches text
+fn matches(pattern: &'static str, text: &str, options: ShelterPatternOptions) -> bool {
+    let set = compile(&[pattern], options);
+    let found = best(set, text).is_some();
+    unsafe { shelter_pattern_set_free(set) };
+    found
+}
+
+const KEYS: ShelterPatternOptions = ShelterPatternOptions {
+    case_insensitive: 0,
+    paths: 0,
+};
+
+const PATHS: ShelterPatternOptions = ShelterPatternOptions {
+    case_insensitive: 0,
+    paths: 1,
+};
+
+// =============================================================================
+// Glob Syntax Tests
+// =============================================================================
+
+#[test]
+fn test_pattern_wildcards() {
+    assert!(matches("*_KEY", "API_KEY", KEYS));
+    assert!(matches("*", "", KEYS));
+    assert!(!matches("*_KEY", "API_KEYS", KEYS));
+    assert!(matches("DB_?", "DB_1", KEYS));
+    assert!(!matches("DB_?", "DB_12", KEYS));
+    assert!(matches("A.B", "A.B", KEYS));
+    assert!(!matches("A.B", "AxB", KEYS));
+}
+
+#[test]
+fn test_pattern_character_classes() {
+    assert!(matches("KEY_[0-9]", "KEY_7", KEYS));
+    assert!(!matches("KEY_[0-9]", "KEY_X", KEYS));
+    assert!(matches("KEY_[!0-9]", "KEY_X", KEYS));
+    assert!(matches("KEY_[^0-9]", "KEY_X", KEYS));
+    assert!(matches("[]A]", "]", KEYS));
+    assert!(matches("[A-]", "-", KEYS));
+    assert!(matches("\\*", "*", KEYS));
+    assert!(!matches("\\*", "A", KEYS));
+}
+
+#[test]
+fn test_pattern_paths() {
+    assert!(matches(".env.*", "/home/me/app/.env.local", PATHS));
+    assert!(!matches("*.env", "/home/me/app.env/x", PATHS));
+    assert!(matches("config/*.env", "/srv/config/prod.env", PATHS));
+    assert!(!matches("config/*.env", "/srv/config/eu/prod.env", PATHS));
+    assert!(matches("config/**/*.env", "/srv/config/eu/prod.env", PATHS));
+    assert!(matches("config/**/*.env", "/srv/config/prod.env", PATHS));
+    assert!(matches("/srv/**", "/srv/a/b/.env", PATHS));
+    assert!(!matches("/srv/**", "/home/srv/.env", PATHS));
+    assert!(matches("config/*.env", "C:\\srv\\config\\prod.env", PATHS));
+}
+
+#[test]
+fn test_pattern_case_insensitive() {
+    let options = ShelterPatternOptions {
+        case_insensitive: 1,
+        ..KEYS
+    };
+    assert!(!matches("*_key", "API_KEY", KEYS));
+    assert!(matches("*_key", "API_KEY", options));
+}
+
+#[test]
+fn test_pattern_invalid() {
+    let invalid = "KEY_[0-9";
+    unsafe {
+        let rules = [rule(invalid, "none")];
+        assert!(shelter_pattern_set_new(rules.as_ptr(), 1, std::ptr::null()).is_null());
+
+        let error =
+            shelter_pattern_validate(invalid.as_ptr() as *const c_char, invalid.len(), &KEYS);
+        assert!(!error.is_null());
+        assert!(CStr::from_ptr(error).to_str().unwrap().contains("Unclosed"));
+        shelter_free_string(error);
+
+        let valid = "KEY_[0-9]";
+        assert!(
+            shelter_pattern_validate(valid.as_ptr() as *const c_char, valid.len(), &KEYS).is_null()
+        );
+    }
+}
+
+// =============================================================================
+// Precedence Tests
+// =============================================================================
+
+#[test]
+fn test_pattern_precedence() {
+    let set = compile(
+        &["*", "SECRET_*", "SECRET_KEY", "SECRET_???", "*_KEY*"],
+        KEYS,
+    );
+
+    // SECRET_KEY and SECRET_??? are equally specific; the earlier one wins
+    assert_eq!(best(set, "SECRET_KEY"), Some(2));
+    assert_eq!(best(set, "SECRET_ABC"), Some(3));
+    assert_eq!(best(set, "SECRET_ABCD"), Some(1));
+    assert_eq!(best(set, "API_KEYS"), Some(4));
+    assert_eq!(best(set, "OTHER"), Some(0));
+
+    unsafe { shelter_pattern_set_free(set) };
+}
+
+#[test]
+fn test_pattern_precedence_ties() {
+    // Same specificity: fewer wildcards wins, then the earlier pattern
+    let set = compile(&["*AB*", "A*B", "AB*"], KEYS);
+    assert_eq!(best(set, "AB"), Some(1));
+    unsafe { shelter_pattern_set_free(set) };
+
+    let set = compile(&["AB*", "*AB"], KEYS);
+    assert_eq!(best(set, "AB"), Some(0));
+    unsafe { shelter_pattern_set_free(set) };
+
+    let empty = compile(&[], KEYS);
+    assert_eq!(best(empty, "AB"), None);
+    unsafe { shelter_pattern_set_free(empty) };
+}
+
+#[test]
+fn test_render_uses_compiled_sets() {
+    let options = ShelterPatternOptions {
+        case_insensitive: 1,
+        ..KEYS
+    };
+    let rules = [rule("*_token", "none")];
+    let content = "GH_TOKEN=abc\nOTHER=abc";
+    unsafe {
+        let keys = shelter_pattern_set_new(rules.as_ptr(), rules.len(), &options);
+        let config = ShelterRenderConfig {
+            key_set: keys,
+            ..Default::default()
+        };
+        let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), &config);
+        assert!((*plan).error.is_null());
+        assert_eq!((*plan).count, 1);
+        assert_eq!((*(*plan).overlays).row, 1);
+
+        shelter_free_render_plan(plan);
+        shelter_pattern_set_free(keys);
+    }
+}
@@ -0,0 +1,244 @@
+//! Integration tests for native render plans
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    row: usize,
+    col: usize,
+    end_col: usize,
+    text: String,
+    entry_index: usize,
+    hl_id: i32,
+    is_continuation: bool,
+}
+
+/// Render content with a config and collect the overlays
+unsafe fn render(content: &str, config: &ShelterRenderConfig) -> Vec<Chunk> {
+    let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), config);
+    assert!(!plan.is_null());
+    assert!((*plan).error.is_null(), "render_plan returned an error");
+
+    let chunks = (0..(*plan).count)
+        .map(|i| {
+            let o = &*(*plan).overlays.add(i);
+            Chunk {
+                row: o.row,
+                col: o.col,
+                end_col: o.end_col,
+                text: CStr::from_ptr(o.text).to_string_lossy().into_owned(),
+                entry_index: o.entry_index,
+                hl_id: o.hl_id,
+                is_continuation: o.is_continuation != 0,
+            }
+        })
+        .collect();
+
+    shelter_free_render_plan(plan);
+    chunks
+}
+
+fn rule(pattern: &'static str, mode: &'static str) -> ShelterPatternRule {
+    ShelterPatternRule {
+        pattern: pattern.as_ptr() as *const c_char,
+        pattern_len: pattern.len(),
+        mode: mode.as_ptr() as *const c_char,
+        mode_len: mode.len(),
+    }
+}
+
+fn texts(chunks: &[Chunk]) -> Vec<&str> {
+    chunks.iter().map(|c| c.text.as_str()).collect()
+}
+
+// =============================================================================
+// Basic Plan Tests
+// =============================================================================
+
+#[test]
+fn test_plan_single_line_positions() {
+    let config = ShelterRenderConfig {
+        highlight_id: 42,
+        ..Default::default()
+    };
+    let chunks = unsafe { render("A=1\nAPI_KEY=secret", &config) };
+
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(
+        chunks[1],
+        Chunk {
+            row: 1,
+            col: 8,
+            end_col: 14,
+            text: "******".to_owned(),
+            entry_index: 1,
+            hl_id: 42,
+            is_continuation: false,
+        }
+    );
+}
+
+#[test]
+fn test_plan_preserves_quotes() {
+    let chunks = unsafe { render("KEY='secret'\nOTHER=\"x y\"", &Default::default()) };
+    assert_eq!((chunks[0].col, chunks[0].end_col), (5, 11));
+    assert_eq!((chunks[1].col, chunks[1].end_col), (7, 10));
+    assert_eq!(texts(&chunks), vec!["******", "***"]);
+}
+
+#[test]
+fn test_plan_skips_empty_and_comments() {
+    let content = "EMPTY=\n#COMMENTED=secret\nKEY=value";
+    let chunks = unsafe { render(content, &Default::default()) };
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].row, 2);
+
+    let config = ShelterRenderConfig {
+        skip_comments: 0,
+        ..Default::default()
+    };
+    let chunks = unsafe { render(content, &config) };
+    assert_eq!(chunks.len(), 2);
+}
+
+#[test]
+fn test_plan_multiline_continuations() {
+    let content = "JSON=\"{\n  a: 1\n}\"\nNEXT=x";
+    let chunks = unsafe { render(content, &Default::default()) };
+
+    let json: Vec<&Chunk> = chunks.iter().filter(|c| c.entry_index == 0).collect();
+    assert_eq!(json.len(), 3);
+    assert_eq!((json[0].row, json[0].col, json[0].end_col), (0, 6, 7));
+    assert!(!json[0].is_continuation);
+    assert_eq!((json[1].row, json[1].col, json[1].end_col), (1, 0, 6));
+    assert!(json[1].is_continuation);
+    // Last line stops before the closing quote
+    assert_eq!((json[2].row, json[2].col, json[2].end_col), (2, 0, 1));
+    assert_eq!(json[1].text, "******");
+}
+
+#[test]
+fn test_plan_pads_short_masks() {
+    let modes = shelter_mode_table_new();
+    let options = ShelterModeOptions {
+        fixed_length: 3,
+        ..Default::default()
+    };
+    unsafe {
+        shelter_mode_table_set(modes, "full".as_ptr() as *const c_char, 4, &options);
+    }
+
+    let config = ShelterRenderConfig {
+        modes,
+        ..Default::default()
+    };
+    let chunks = unsafe { render("KEY=verylongsecret", &config) };
+    assert_eq!(chunks[0].text, "***           ");
+    assert_eq!(chunks[0].end_col - chunks[0].col, chunks[0].text.len());
+
+    unsafe { shelter_mode_table_free(modes) };
+}
+
+#[test]
+fn test_plan_measures_display_cells() {
+    // Wide characters and tabs: overlays must cover every cell of the value
+    let content = "KEY=密码\nTAB=\"a\tb\"";
+    let chunks = unsafe { render(content, &Default::default()) };
+    assert_eq!(chunks[0].text, "****");
+    // The tab at cell 6 runs to cell 8, so `a<tab>b` spans cells 5..9
+    assert_eq!(chunks[1].text, "****");
+
+    let config = ShelterRenderConfig {
+        tabstop: 3,
+        ..Default::default()
+    };
+    let chunks = unsafe { render(content, &config) };
+    // With tabstop 3 the tab runs to cell 9 instead
+    assert_eq!(chunks[1].text, "*****");
+}
+
+// =============================================================================
+// Mode Selection Tests
+// =============================================================================
+
+#[test]
+fn test_plan_key_patterns_most_specific_wins() {
+    let keys = [rule("*_KEY", "partial"), rule("PUBLIC_*", "none")];
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+    let content = "API_KEY=secret12345\nPUBLIC_URL=https://x\nPUBLIC_KEY=abcdefghij";
+    let chunks = unsafe { render(content, &config) };
+
+    // PUBLIC_URL is visible (none → no overlay); PUBLIC_* beats *_KEY
+    assert_eq!(texts(&chunks), vec!["sec*****345"]);
+}
+
+#[test]
+fn test_plan_source_patterns_and_default() {
+    let sources = [rule(".env.local", "none")];
+    let default_mode = "partial";
+    let source = "/home/me/project/.env.local";
+    let config = ShelterRenderConfig {
+        source_patterns: sources.as_ptr(),
+        source_pattern_count: sources.len(),
+        source: source.as_ptr() as *const c_char,
+        source_len: source.len(),
+        ..Default::default()
+    };
+    assert!(unsafe { render("KEY=secret", &config) }.is_empty());
+
+    let config = ShelterRenderConfig {
+        default_mode: default_mode.as_ptr() as *const c_char,
+        default_mode_len: default_mode.len(),
+        ..Default::default()
+    };
+    assert_eq!(
+        texts(&unsafe { render("KEY=secret1234", &config) }),
+        vec!["sec****234"]
+    );
+}
+
+#[test]
+fn test_plan_unknown_mode_falls_back_to_full() {
+    let keys = [rule("*", "redact")];
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+    assert_eq!(
+        texts(&unsafe { render("KEY=secret", &config) }),
+        vec!["******"]
+    );
+}
+
+#[test]
+fn test_plan_rejects_nul_in_overlay_text() {
+    let default_mode = "partial";
+    let config = ShelterRenderConfig {
+        default_mode: default_mode.as_ptr() as *const c_char,
+        default_mode_len: default_mode.len(),
+        ..Default::default()
+    };
+    let content = "KEY=a\0bcdefghijk";
+    unsafe {
+        let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), &config);
+        assert!(!(*plan).error.is_null());
+        assert_eq!((*plan).count, 0);
+        shelter_free_render_plan(plan);
+    }
+}
+
+#[test]
+fn test_plan_null_input() {
+    unsafe {
+        let plan = shelter_render_plan(std::ptr::null(), 0, std::ptr::null());
+        assert!(!(*plan).error.is_null());
+        shelter_free_render_plan(plan);
+    }
+}
@@ -0,0 +1,154 @@
+//! Integration tests for progressive reveal levels
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+/// Overlay texts of a render plan
+unsafe fn plan_texts(content: &str, config: &ShelterRenderConfig) -> Vec<String> {
+    let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), config);
+    assert!((*plan).error.is_null(), "render_plan returned an error");
+    let texts = (0..(*plan).count)
+        .map(|i| {
+            let o = &*(*plan).overlays.add(i);
+            CStr::from_ptr(o.text).to_string_lossy().into_owned()
+        })
+        .collect();
+    shelter_free_render_plan(plan);
+    texts
+}
+
+fn reveal(key: &str, kind: ShelterRevealKind, leading: usize, trailing: usize) -> ShelterReveal {
+    ShelterReveal {
+        id: entry_id(key, 0, false),
+        leading,
+        trailing,
+        kind: kind as u8,
+    }
+}
+
+fn config(reveals: &[ShelterReveal]) -> ShelterRenderConfig {
+    ShelterRenderConfig {
+        reveals: reveals.as_ptr(),
+        reveal_count: reveals.len(),
+        ..Default::default()
+    }
+}
+
+/// Mask a value with a built-in mode and a reveal level
+unsafe fn mask(mode: &str, value: &str, reveal: &ShelterReveal) -> Option<String> {
+    let table = shelter_mode_table_new();
+    let masked = shelter_mask_value_revealed(
+        table,
+        mode.as_ptr() as *const c_char,
+        mode.len(),
+        value.as_ptr() as *const c_char,
+        value.len(),
+        reveal,
+    );
+    let out = (!masked.is_null()).then(|| CStr::from_ptr(masked).to_str().unwrap().to_owned());
+    shelter_free_string(masked);
+    shelter_mode_table_free(table);
+    out
+}
+
+// =============================================================================
+// Render Plan Tests
+// =============================================================================
+
+#[test]
+fn test_reveal_trailing_characters() {
+    let reveals = [reveal("TOKEN", ShelterRevealKind::Edges, 0, 4)];
+    let texts = unsafe { plan_texts("TOKEN=abcdef123456\nOTHER=secret", &config(&reveals)) };
+    assert_eq!(texts, vec!["********3456", "******"]);
+}
+
+#[test]
+fn test_reveal_survives_edits_elsewhere() {
+    let reveals = [reveal("TOKEN", ShelterRevealKind::Edges, 2, 0)];
+    let content = "# added\nNEW=x\nTOKEN=abcdef";
+    let texts = unsafe { plan_texts(content, &config(&reveals)) };
+    assert_eq!(texts, vec!["*", "ab****"]);
+}
+
+#[test]
+fn test_reveal_prefix_and_full() {
+    let reveals = [
+        reveal("STRIPE", ShelterRevealKind::Prefix, 0, 0),
+        reveal("PLAIN", ShelterRevealKind::Prefix, 0, 0),
+        reveal("SHOWN", ShelterRevealKind::Full, 0, 0),
+    ];
+    let content = "STRIPE=sk_live_abc123\nPLAIN=hunter2\nSHOWN=visible";
+    let texts = unsafe { plan_texts(content, &config(&reveals)) };
+    // Unrecognised values keep their mask; fully revealed ones get no overlay
+    assert_eq!(texts, vec!["sk_live_******", "*******"]);
+}
+
+#[test]
+fn test_partial_reveal_keeps_multiline_masked() {
+    let reveals = [reveal("CERT", ShelterRevealKind::Edges, 2, 2)];
+    let texts = unsafe { plan_texts("CERT=\"line one\nline two\"", &config(&reveals)) };
+    assert_eq!(texts, vec!["********", "********"]);
+}
+
+#[test]
+fn test_unknown_reveal_kind_is_an_error() {
+    let reveals = [ShelterReveal {
+        kind: 9,
+        ..reveal("A", ShelterRevealKind::Full, 0, 0)
+    }];
+    let content = "A=1";
+    unsafe {
+        let plan = shelter_render_plan(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            &config(&reveals),
+        );
+        assert!(!(*plan).error.is_null());
+        shelter_free_render_plan(plan);
+    }
+}
+
+// =============================================================================
+// Masking API Tests
+// =============================================================================
+
+#[test]
+fn test_mask_value_revealed() {
+    unsafe {
+        let edges = reveal("", ShelterRevealKind::Edges, 1, 2);
+        assert_eq!(mask("full", "пароль123", &edges).unwrap(), "п******23");
+        assert_eq!(mask("full", "abc", &edges).unwrap(), "abc");
+        assert_eq!(mask("none", "abc", &edges).unwrap(), "abc");
+
+        let hidden = reveal("", ShelterRevealKind::Hidden, 1, 2);
+        assert_eq!(
+            mask("partial", "abcdefghij", &hidden).unwrap(),
+            "abc****hij"
+        );
+
+        let bad = ShelterReveal { kind: 7, ..hidden };
+        assert!(mask("full", "abc", &bad).is_none());
+
+        // The masked value could not be read back with a NUL in it
+        assert!(mask("none", "a\0c", &edges).is_none());
+    }
+}
+
+#[test]
+fn test_masked_document_applies_reveals() {
+    let reveals = [reveal("KEY", ShelterRevealKind::Edges, 0, 2)];
+    let content = "KEY=\"abcdef\"\nOTHER=xyz";
+    unsafe {
+        let doc = shelter_render_masked(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            &config(&reveals),
+        );
+        assert_eq!(
+            CStr::from_ptr((*doc).text).to_str().unwrap(),
+            "KEY=\"****ef\"\nOTHER=***"
+        );
+        shelter_free_masked_document(doc);
+    }
+}
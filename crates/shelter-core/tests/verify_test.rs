@@ -0,0 +1,176 @@
+//! Integration tests for the overlay leak verifier
+
+use std::ffi::c_char;
+
+use shelter_core::*;
+
+/// Build a render plan for content, verify it, and return the leaks
+unsafe fn verify_plan(content: &str, config: &ShelterRenderConfig) -> Vec<ShelterLeak> {
+    let plan = shelter_render_plan(content.as_ptr() as *const c_char, content.len(), config);
+    assert!((*plan).error.is_null());
+    let leaks = verify(content, (*plan).overlays, (*plan).count, config, 0);
+    shelter_free_render_plan(plan);
+    leaks
+}
+
+unsafe fn verify(
+    content: &str,
+    overlays: *const ShelterOverlay,
+    count: usize,
+    config: &ShelterRenderConfig,
+    tabstop: usize,
+) -> Vec<ShelterLeak> {
+    let report = shelter_verify_overlays(
+        content.as_ptr() as *const c_char,
+        content.len(),
+        overlays,
+        count,
+        config,
+        tabstop,
+    );
+    assert!(!report.is_null());
+    assert!(
+        (*report).error.is_null(),
+        "verify_overlays returned an error"
+    );
+    let leaks = (0..(*report).count)
+        .map(|i| *(*report).leaks.add(i))
+        .collect();
+    shelter_free_leak_report(report);
+    leaks
+}
+
+fn overlay(row: usize, col: usize, end_col: usize, text: &'static str) -> ShelterOverlay {
+    ShelterOverlay {
+        text: text.as_ptr() as *mut c_char,
+        text_len: text.len(),
+        row,
+        col,
+        end_col,
+        entry_index: 0,
+        hl_id: 0,
+        is_continuation: 0,
+    }
+}
+
+// =============================================================================
+// Plan Verification Tests
+// =============================================================================
+
+#[test]
+fn test_plan_has_no_leaks() {
+    let content = "A=1\nexport KEY='secret'\nMULTI=\"one\ntwo\"\n#C=x\nEMPTY=";
+    let leaks = unsafe { verify_plan(content, &Default::default()) };
+    assert!(leaks.is_empty(), "unexpected leaks: {:?}", leaks);
+}
+
+#[test]
+fn test_plan_covers_wide_characters_and_tabs() {
+    let content = "WIDE=密码\nMIXED=пароль密码123\nTAB=\"a\tb\"";
+    let leaks = unsafe { verify_plan(content, &Default::default()) };
+    assert!(leaks.is_empty(), "unexpected leaks: {:?}", leaks);
+}
+
+#[test]
+fn test_no_overlays_leaks_every_value() {
+    let content = "KEY=secret\nOTHER='x'";
+    let leaks = unsafe { verify(content, std::ptr::null(), 0, &Default::default(), 0) };
+    assert_eq!(
+        leaks,
+        vec![
+            ShelterLeak {
+                row: 0,
+                col: 4,
+                end_col: 10,
+                entry_index: 0,
+            },
+            ShelterLeak {
+                row: 1,
+                col: 7,
+                end_col: 8,
+                entry_index: 1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_visible_modes_are_not_leaks() {
+    let keys = [ShelterPatternRule {
+        pattern: "PUBLIC".as_ptr() as *const c_char,
+        pattern_len: 6,
+        mode: "none".as_ptr() as *const c_char,
+        mode_len: 4,
+    }];
+    let config = ShelterRenderConfig {
+        key_patterns: keys.as_ptr(),
+        key_pattern_count: keys.len(),
+        ..Default::default()
+    };
+    let leaks = unsafe { verify("PUBLIC=hello", std::ptr::null(), 0, &config, 0) };
+    assert!(leaks.is_empty());
+}
+
+// =============================================================================
+// Display Simulation Tests
+// =============================================================================
+
+#[test]
+fn test_narrow_overlay_leaks_tail() {
+    let content = "KEY=verylongsecret";
+    let overlays = [overlay(0, 4, 18, "***")];
+    let leaks = unsafe { verify(content, overlays.as_ptr(), 1, &Default::default(), 0) };
+    assert_eq!(leaks.len(), 1);
+    assert_eq!((leaks[0].col, leaks[0].end_col), (7, 18));
+}
+
+#[test]
+fn test_wide_characters_need_two_cells_each() {
+    // Each CJK character is 3 bytes and 2 cells wide
+    let content = "KEY=密码";
+    let narrow = [overlay(0, 4, 10, "**")];
+    let leaks = unsafe { verify(content, narrow.as_ptr(), 1, &Default::default(), 0) };
+    assert_eq!(leaks.len(), 1);
+    assert_eq!((leaks[0].col, leaks[0].end_col), (7, 10));
+
+    let wide = [overlay(0, 4, 10, "****")];
+    let leaks = unsafe { verify(content, wide.as_ptr(), 1, &Default::default(), 0) };
+    assert!(leaks.is_empty());
+}
+
+#[test]
+fn test_tabs_expand_to_tabstop() {
+    // The tab starts at cell 5 and spans to cell 8, so the value takes 6 cells
+    let content = "K=\"ab\tc\"";
+    let overlays = [overlay(0, 3, 7, "******")];
+    let leaks = unsafe { verify(content, overlays.as_ptr(), 1, &Default::default(), 8) };
+    assert!(leaks.is_empty());
+
+    // With tabstop 2 the tab ends at cell 6, so four cells suffice
+    let overlays = [overlay(0, 3, 7, "****")];
+    let leaks = unsafe { verify(content, overlays.as_ptr(), 1, &Default::default(), 2) };
+    assert!(leaks.is_empty());
+
+    let overlays = [overlay(0, 3, 7, "****")];
+    let leaks = unsafe { verify(content, overlays.as_ptr(), 1, &Default::default(), 8) };
+    assert_eq!(
+        leaks.iter().map(|l| (l.col, l.end_col)).collect::<Vec<_>>(),
+        vec![(5, 7)]
+    );
+}
+
+#[test]
+fn test_verify_null_input() {
+    unsafe {
+        let report = shelter_verify_overlays(
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+            0,
+        );
+        assert!(!(*report).error.is_null());
+        shelter_free_leak_report(report);
+    }
+}
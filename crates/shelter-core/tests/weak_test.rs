@@ -0,0 +1,196 @@
+//! Integration tests for weak, placeholder and default values
+
+mod common;
+
+use common::*;
+use shelter_core::*;
+
+/// Code, message and line of each diagnostic
+fn reported(content: &str, analysis: ShelterAnalysisOptions) -> Vec<(String, String, usize)> {
+    unsafe {
+        let result = parse_with(content, &analysis);
+        let found = diagnostics(result)
+            .iter()
+            .map(|d| {
+                (
+                    text(d.code).unwrap(),
+                    text(d.message).unwrap(),
+                    d.line_number,
+                )
+            })
+            .collect();
+        shelter_free_result(result);
+        found
+    }
+}
+
+fn weak() -> ShelterAnalysisOptions {
+    ShelterAnalysisOptions {
+        weak_values: 1,
+        ..Default::default()
+    }
+}
+
+/// Codes reported for a single `key=value` line
+fn codes(line: &str) -> Vec<String> {
+    reported(line, weak())
+        .into_iter()
+        .map(|(code, _, _)| code)
+        .collect()
+}
+
+// =============================================================================
+// Placeholder Tests
+// =============================================================================
+
+#[test]
+fn test_placeholders() {
+    for line in [
+        "API_KEY=changeme",
+        "API_KEY=CHANGE_ME",
+        "API_KEY=your-api-key-here",
+        "STRIPE_SECRET_KEY=<TOKEN>",
+        "API_KEY=xxx",
+        "STRIPE_SECRET_KEY=sk_test_xxxxxxxxxxxx",
+        "WEBHOOK_URL=https://hooks.example.com/xxxx",
+        // Any key, not only secret ones
+        "SENTRY_DSN=\"put-your-dsn-here\"",
+        "SENTRY_DSN=<SENTRY_DSN>",
+        // Phrases, for secret keys
+        "API_KEY=\"your api key\"",
+        "DB_PASSWORD=\"<db password>\"",
+    ] {
+        assert_eq!(codes(line), vec!["placeholder-value"], "{}", line);
+    }
+    // Ordinary text and markup for other keys
+    for line in [
+        "WELCOME_TEXT=\"Click here\"",
+        "GREETING=\"your order has shipped\"",
+        "BANNER=<b>hi</b>",
+        "FOOTER=<br>",
+    ] {
+        assert!(codes(line).is_empty(), "{}", line);
+    }
+    assert_eq!(
+        reported("A=1\nDB_PASSWORD=changeme", weak()),
+        vec![(
+            "placeholder-value".to_owned(),
+            "DB_PASSWORD looks like an unfilled placeholder".to_owned(),
+            2
+        )]
+    );
+}
+
+#[test]
+fn test_default_credentials() {
+    for line in [
+        "DB_PASSWORD=password",
+        "ADMIN_PASSWORD=Admin",
+        "JWT_SECRET=secret",
+        "ROOT_PWD=root",
+    ] {
+        assert_eq!(codes(line), vec!["default-credential"], "{}", line);
+    }
+    // Ordinary values for other keys
+    assert!(codes("ADMIN_USER=admin").is_empty());
+    assert!(codes("ROLE=secret").is_empty());
+}
+
+#[test]
+fn test_repeated_characters() {
+    assert_eq!(
+        codes("DB_PASSWORD=aaaaaaaaaaaaaaaa"),
+        vec!["repeated-characters"]
+    );
+    assert_eq!(
+        codes("API_TOKEN=0000000000000000"),
+        vec!["repeated-characters"]
+    );
+    assert!(codes("PADDING=0000").is_empty());
+}
+
+// =============================================================================
+// Length Tests
+// =============================================================================
+
+#[test]
+fn test_short_and_empty_secrets() {
+    assert_eq!(
+        reported("SESSION_SECRET=hunter2", weak()),
+        vec![(
+            "short-secret".to_owned(),
+            "SESSION_SECRET is 7 characters long, shorter than 12".to_owned(),
+            1
+        )]
+    );
+    assert_eq!(codes("GITHUB_TOKEN="), vec!["empty-secret"]);
+    assert_eq!(codes("GITHUB_TOKEN=\"\""), vec!["empty-secret"]);
+    assert!(codes("SESSION_SECRET=2b7e151628aed2a6abf71588").is_empty());
+
+    // Configurable minimum
+    let strict = ShelterAnalysisOptions {
+        min_secret_length: 32,
+        ..weak()
+    };
+    assert_eq!(
+        reported("SESSION_SECRET=2b7e151628aed2a6abf71588", strict)[0].0,
+        "short-secret"
+    );
+}
+
+#[test]
+fn test_secret_keys() {
+    for key in [
+        "DB_PASSWORD",
+        "GITHUB_TOKEN",
+        "STRIPE_SECRET_KEY",
+        "API_KEY",
+        "AWS_SECRET_ACCESS_KEY",
+        "SMTP_PASS",
+    ] {
+        assert_eq!(
+            codes(&format!("{}=abc", key)),
+            vec!["short-secret"],
+            "{}",
+            key
+        );
+    }
+    for key in [
+        "PORT",
+        "PUBLIC_KEY",
+        "PASSWORD_MIN_LENGTH",
+        "TOKEN_URL",
+        "CACHE_KEY",
+    ] {
+        assert!(codes(&format!("{}=abc", key)).is_empty(), "{}", key);
+    }
+}
+
+#[test]
+fn test_skipped_values() {
+    // References, comments, and analysis that was not requested
+    assert!(codes("API_KEY=${STRIPE_KEY}").is_empty());
+    assert!(codes("# API_KEY=changeme").is_empty());
+    assert!(reported("API_KEY=changeme", ShelterAnalysisOptions::default()).is_empty());
+}
+
+#[test]
+fn test_diagnostic_ranges() {
+    unsafe {
+        let content = "DB_PASSWORD=\"changeme\"\nGITHUB_TOKEN=\n";
+        let result = parse_with(content, &weak());
+        let ranges: Vec<_> = diagnostics(result)
+            .iter()
+            .map(|d| (&content[d.start..d.end], d.entry_index, d.severity))
+            .collect();
+        // The value, or the key when the value is empty
+        assert_eq!(
+            ranges,
+            vec![
+                ("\"changeme\"", 0, ShelterSeverity::Warning as u8),
+                ("GITHUB_TOKEN", 1, ShelterSeverity::Warning as u8),
+            ]
+        );
+        shelter_free_result(result);
+    }
+}
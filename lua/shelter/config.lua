@@ -6,12 +6,16 @@ local M = {}
 ---Detailed configuration for the files module (buffer masking)
 ---@field shelter_on_leave? boolean Re-shelter when leaving buffer (default: true)
 ---@field disable_cmp? boolean Disable nvim-cmp/blink-cmp in sheltered buffers (default: true)
+---@field verify_overlays? boolean Debug: check placed masks for visible secret bytes (default: false)
 
 ---@class ShelterEcologModuleConfig
 ---Detailed configuration for the ecolog integration module
 ---@field cmp? boolean Mask in completion (default: true)
 ---@field peek? boolean Mask in hover (default: true)
 
+---@class ShelterVerifyConfig
+---@field report_prefix? boolean :Shelter verify reports how many leading characters match (default: false)
+
 ---@class ShelterModulesConfig
 ---@field files boolean|ShelterFilesModuleConfig Buffer masking (boolean or detailed config)
 ---@field telescope_previewer boolean Telescope preview masking
@@ -29,6 +33,22 @@ local M = {}
 ---@field schema? table<string, table> Option schema
 ---@field [string] any Mode-specific options
 
+---@class ShelterPolicyRuleConfig
+---@field mode string Mode to use when every condition holds
+---@field highlight? string Highlight group for the masked value
+---@field priority? number Higher runs first, ties in list order (default: 0)
+---@field key? string Key glob
+---@field key_regex? string Key regex
+---@field value_regex? string Value regex
+---@field source? string Source path glob
+---@field min_entropy? number Minimum Shannon entropy of the value (bits per character)
+---@field secret_type? string Detected token type glob, e.g. "github" or "*" for any
+---@field min_confidence? "low"|"medium"|"high" Minimum confidence of the detected token type
+---@field looks_random? boolean Value does (or does not) look like a random token
+---@field quote? ("none"|"single"|"double")[] Allowed quote types
+---@field is_comment? boolean
+---@field is_exported? boolean
+
 ---@class ShelterUserConfig
 ---@field mask_char? string Character used for masking (default: "*")
 ---@field highlight_group? string Highlight group for masked text
@@ -38,8 +58,15 @@ local M = {}
 ---@field env_filetypes? string[] Filetypes to mask (default: {"dotenv", "edf"})
 ---@field patterns? table<string, string> Key patterns to mode mapping
 ---@field sources? table<string, string> Source file patterns to mode mapping
+---@field policy? ShelterPolicyRuleConfig[] Rules tried before the patterns, by descending priority
+---@field pattern_ignore_case? boolean Match key and source patterns regardless of case (default: false)
+---@field directives? boolean Honour inline `# shelter:` directives in env files (default: true)
+---@field min_secret_length? number Secret values shorter than this are reported (default: 12)
+---@field public_prefixes? string[] Key prefixes bundled into client code: visible unless secret, and reported when secret
 ---@field modules? ShelterModulesConfig Module toggles
 ---@field buffer? ShelterBufferConfig Buffer-specific settings
+---@field verify? ShelterVerifyConfig Settings for :Shelter verify
+---@field key_file? string Per-user key for fingerprints and keyed mask lengths (default: stdpath("data")/shelter/key)
 
 ---@type ShelterUserConfig
 local DEFAULT_CONFIG = {
@@ -60,10 +87,28 @@ local DEFAULT_CONFIG = {
 			fallback_mode = "full",
 		},
 		none = {},
+		fingerprint = {
+			mask_char = "•",
+			fingerprint_length = 4,
+		},
+		smart = {
+			mask_char = "*",
+			preserve_length = true,
+		},
+		structured = {
+			mask_char = "*",
+			fallback_mode = "full",
+			json_leaves = "secret",
+		},
 	},
 	env_filetypes = { "dotenv", "edf" },
 	patterns = {},
 	sources = {},
+	policy = {},
+	pattern_ignore_case = false,
+	directives = true,
+	min_secret_length = 12,
+	public_prefixes = { "NEXT_PUBLIC_", "VITE_", "REACT_APP_", "EXPO_PUBLIC_", "PUBLIC_" },
 	modules = {
 		files = true, -- Can be boolean or { shelter_on_leave = true, disable_cmp = true }
 		telescope_previewer = false,
@@ -73,6 +118,10 @@ local DEFAULT_CONFIG = {
 	buffer = {
 		shelter_on_leave = true, -- Deprecated, use modules.files.shelter_on_leave
 	},
+	verify = {
+		-- Disclosing the matching prefix lets a guesser recover a value one character at a time
+		report_prefix = false,
+	},
 }
 
 ---@type ShelterUserConfig
@@ -108,11 +157,25 @@ function M.validate()
 		env_filetypes = { config.env_filetypes, "table" },
 		patterns = { config.patterns, "table" },
 		sources = { config.sources, "table" },
+		policy = { config.policy, "table" },
+		pattern_ignore_case = { config.pattern_ignore_case, "boolean" },
+		directives = { config.directives, "boolean" },
+		min_secret_length = { config.min_secret_length, "number" },
+		public_prefixes = { config.public_prefixes, "table" },
 		modules = { config.modules, "table" },
 		buffer = { config.buffer, "table" },
 		modes = { config.modes, "table" },
+		verify = { config.verify, "table" },
+		key_file = { config.key_file, "string", true },
 	})
 
+	-- Validate policy rules (conditions are checked when the policy is compiled)
+	for i, rule in ipairs(config.policy) do
+		if type(rule) ~= "table" or type(rule.mode) ~= "string" then
+			error(string.format("shelter.nvim: Policy rule %d must be a table with a mode", i))
+		end
+	end
+
 	-- Validate modes table
 	for name, mode_config in pairs(config.modes) do
 		if type(mode_config) ~= "table" then
@@ -137,7 +200,7 @@ function M.is_module_enabled(module)
 end
 
 ---Get files module configuration (normalized)
----Returns a table with shelter_on_leave and disable_cmp
+---Returns a table with shelter_on_leave, disable_cmp and verify_overlays
 ---@return ShelterFilesModuleConfig
 function M.get_files_config()
 	local files = config.modules.files
@@ -146,6 +209,7 @@ function M.get_files_config()
 	local defaults = {
 		shelter_on_leave = true,
 		disable_cmp = true,
+		verify_overlays = false,
 	}
 
 	-- If files is boolean or nil, return defaults
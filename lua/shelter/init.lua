@@ -189,13 +189,19 @@ function M._setup_commands()
 			M._handle_disable(target)
 		elseif subcommand == "peek" then
 			M.peek()
+		elseif subcommand == "copy" then
+			M.copy_masked(target)
+		elseif subcommand == "verify" then
+			M.verify_value(target)
+		elseif subcommand == "reveal" then
+			M.reveal(target, tonumber(subcontext))
 		elseif subcommand == "build" then
 			M.build()
 		elseif subcommand == "info" then
 			M.info()
 		else
 			vim.notify(
-				"shelter.nvim: Unknown subcommand. Use: toggle, enable, disable, peek, build, info",
+				"shelter.nvim: Unknown subcommand. Use: toggle, enable, disable, peek, reveal, copy, verify, build, info",
 				vim.log.levels.ERROR
 			)
 		end
@@ -206,10 +212,15 @@ function M._setup_commands()
 
 			if #args <= 2 then
 				-- Complete subcommand
-				local subcommands = { "toggle", "enable", "disable", "peek", "build", "info" }
+				local subcommands = { "toggle", "enable", "disable", "peek", "reveal", "copy", "verify", "build", "info" }
 				return vim.tbl_filter(function(cmd)
 					return cmd:find(arglead, 1, true) == 1
 				end, subcommands)
+			elseif #args == 3 and args[2] == "reveal" then
+				local levels = { "last", "first", "prefix", "full", "hide", "reset" }
+				return vim.tbl_filter(function(level)
+					return level:find(arglead, 1, true) == 1
+				end, levels)
 			elseif #args == 3 and vim.tbl_contains({ "toggle", "enable", "disable" }, args[2]) then
 				-- Complete module name (include "ecolog" for subcontext support)
 				local modules = vim.list_extend({ "ecolog" }, module_validation.VALID_MODULES)
@@ -226,7 +237,7 @@ function M._setup_commands()
 
 			return {}
 		end,
-		desc = "Shelter command: toggle, enable, disable, peek, build, info",
+		desc = "Shelter command: toggle, enable, disable, peek, reveal, copy, verify, build, info",
 	})
 end
 
@@ -267,6 +278,78 @@ function M.build()
 	})
 end
 
+-- Entries listed by :Shelter info for the current buffer
+local INFO_MAX_ENTRIES = 20
+
+---Describe a mode decision in one line, e.g.
+---"API_KEY → none because `*_PUBLIC*` matched (specificity 7)"
+---@param key string
+---@param explanation ShelterExplanation
+---@return string
+local function explain_line(key, explanation)
+	local reason = "by default"
+	for _, step in ipairs(explanation.steps) do
+		if step.chosen and step.stage == "directive" then
+			reason = string.format("because of the directive on %s", step.rule)
+		elseif step.chosen and step.stage == "policy" then
+			reason = string.format(
+				"because policy rule %d (`%s`) matched (priority %d)",
+				step.rule_index,
+				step.rule,
+				step.rank
+			)
+		elseif step.chosen and step.stage == "public" then
+			reason = string.format("because `%s` keys are public", step.rule)
+		elseif step.chosen and step.stage ~= "default" then
+			reason = string.format(
+				"because %s`%s` matched (specificity %d)",
+				step.stage == "source" and "source " or "",
+				step.rule,
+				step.rank
+			)
+		end
+	end
+	local line = string.format("%s → %s %s", key, explanation.mode, reason)
+	if explanation.unknown_mode then
+		line = line .. " (unknown mode, masked as full)"
+	end
+	return line
+end
+
+---Mode decisions for entries of the current buffer
+---@return string[]
+local function buffer_decisions()
+	local bufnr = vim.api.nvim_get_current_buf()
+	if not require("shelter.utils.env_file").is_env_buffer(bufnr) then
+		return {}
+	end
+	local content = table.concat(vim.api.nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+	local source = vim.api.nvim_buf_get_name(bufnr)
+	local masking = require("shelter.masking")
+	local native = require("shelter.native")
+	local skip_comments = config.get().skip_comments
+
+	local lines = {}
+	-- Masking skips the analysis only described here
+	local entries = native.parse(content, { value_types = true, payloads = true, jwts = true, pems = true }).entries
+	for index, entry in ipairs(entries) do
+		if not (entry.is_comment and skip_comments) then
+			if #lines == INFO_MAX_ENTRIES then
+				lines[#lines + 1] = string.format("    … %d more", #entries - index + 1)
+				break
+			end
+			local ok, explanation = pcall(masking.explain, content, source, index)
+			local kind = entry.jwt and native.describe_jwt(entry.jwt)
+				or entry.pem and native.describe_pem(entry.pem)
+				or entry.payload
+				or (entry.value_type and (entry.value_type:gsub("_", " ")))
+			local label = kind and string.format("%s (%s)", entry.key, kind) or entry.key
+			lines[#lines + 1] = "    " .. (ok and explain_line(label, explanation) or label .. " → ?")
+		end
+	end
+	return lines
+end
+
 ---Show plugin info
 function M.info()
 	local info = { "shelter.nvim" }
@@ -321,6 +404,15 @@ function M.info()
 		end
 	end
 
+	-- Why each entry of the current buffer gets its mode
+	if native_ok and native.is_available() then
+		local decisions = buffer_decisions()
+		if #decisions > 0 then
+			table.insert(info, "  Current buffer:")
+			vim.list_extend(info, decisions)
+		end
+	end
+
 	vim.notify(table.concat(info, "\n"), vim.log.levels.INFO)
 end
 
@@ -381,23 +473,123 @@ function M.mask_value(value, opts)
 	local mask_char = opts.mask_char or "*"
 	local mode = opts.mode or "full"
 
+	local text = require("shelter.utils.text")
+
 	if mode == "partial" then
 		local show_start = opts.show_start or 3
 		local show_end = opts.show_end or 3
 		local min_mask = opts.min_mask or 3
-		local value_len = #value
+		local chars = text.chars(value)
+		local value_len = #chars
 
 		if value_len <= show_start + show_end + min_mask then
-			return string.rep(mask_char, value_len)
+			return text.fill(mask_char, text.width(value))
 		end
 
-		local mask_len = value_len - show_start - show_end
-		return value:sub(1, show_start) .. string.rep(mask_char, mask_len) .. value:sub(-show_end)
+		local hidden = table.concat(chars, "", show_start + 1, value_len - show_end)
+		return table.concat(chars, "", 1, show_start)
+			.. text.fill(mask_char, text.width(hidden))
+			.. table.concat(chars, "", value_len - show_end + 1, value_len)
 	end
 
 	-- Default: full mask
-	local output_len = opts.mask_length or #value
-	return string.rep(mask_char, output_len)
+	if opts.mask_length then
+		return string.rep(mask_char, opts.mask_length)
+	end
+	return text.fill(mask_char, text.width(value))
+end
+
+---Copy a masked copy of the current buffer into a register
+---@param register? string Register name (default: unnamed register)
+function M.copy_masked(register)
+	local bufnr = vim.api.nvim_get_current_buf()
+	local lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+	local source = vim.api.nvim_buf_get_name(bufnr)
+
+	local ok, masked = pcall(require("shelter.masking").render_masked, table.concat(lines, "\n"), source)
+	if not ok or not masked then
+		vim.notify(
+			"shelter.nvim: Cannot copy masked buffer (requires the native library and built-in modes)",
+			vim.log.levels.ERROR
+		)
+		return
+	end
+
+	register = register or '"'
+	vim.fn.setreg(register, masked.text, "l")
+	vim.notify(
+		string.format("shelter.nvim: Copied masked buffer to register %s (%d values masked)", register, #masked.spans),
+		vim.log.levels.INFO
+	)
+end
+
+---Check a typed guess against a value in the current buffer without revealing it
+---@param key? string Entry key (default: the entry on the cursor line)
+function M.verify_value(key)
+	local native = require("shelter.native")
+	local bufnr = vim.api.nvim_get_current_buf()
+	local content = table.concat(vim.api.nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+
+	if not key then
+		local row = vim.api.nvim_win_get_cursor(0)[1]
+		for _, entry in ipairs(native.parse(content).entries) do
+			if not entry.is_comment and row >= entry.line_number and row <= entry.value_end_line then
+				key = entry.key
+				break
+			end
+		end
+		if not key then
+			vim.notify("shelter.nvim: No entry on the cursor line", vim.log.levels.WARN)
+			return
+		end
+	end
+
+	local candidate = vim.fn.inputsecret(string.format("Value to check against %s: ", key))
+	local outcome = native.verify_value(content, key, candidate, config.get().verify)
+
+	local message
+	if not outcome.found then
+		message = string.format("%s not found", key)
+	elseif outcome.matched then
+		message = string.format("%s matches", key)
+	elseif outcome.prefix_length then
+		message = string.format("%s does not match (first %d characters match)", key, outcome.prefix_length)
+	else
+		message = string.format("%s does not match", key)
+	end
+	vim.notify("shelter.nvim: " .. message, outcome.matched and vim.log.levels.INFO or vim.log.levels.WARN)
+end
+
+---Reveal part of the entry on the cursor line
+---The level sticks to the entry (by stable id) until hidden or the buffer is left
+---@param how? "last"|"first"|"prefix"|"full"|"hide"|"reset" What to show (default: "last")
+---@param count? number Characters for "last"/"first" (default: 4)
+function M.reveal(how, count)
+	local buffer = require("shelter.integrations.buffer")
+	how = how or "last"
+	count = count or 4
+
+	if how == "reset" then
+		buffer.hide_reveals()
+		return
+	end
+
+	local levels = {
+		last = { kind = "edges", leading = 0, trailing = count },
+		first = { kind = "edges", leading = count, trailing = 0 },
+		prefix = { kind = "prefix" },
+		full = { kind = "full" },
+		hide = { kind = "hidden" },
+	}
+	local level = levels[how]
+	if not level then
+		vim.notify("shelter.nvim: Unknown reveal level. Use: last, first, prefix, full, hide, reset", vim.log.levels.ERROR)
+		return
+	end
+
+	if not buffer.reveal_entry(nil, nil, level) then
+		vim.notify("shelter.nvim: No entry on the cursor line", vim.log.levels.WARN)
+	end
 end
 
 ---Peek at current line (temporarily reveal for 3 seconds)
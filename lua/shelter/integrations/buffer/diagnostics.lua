@@ -0,0 +1,34 @@
+---@class ShelterBufferDiagnostics
+---Publishes parse diagnostics (e.g. unknown `# shelter:` directives) with vim.diagnostic
+local M = {}
+
+local ns = vim.api.nvim_create_namespace("shelter_diagnostics")
+
+---Publish the diagnostics of a parse result
+---@param bufnr number
+---@param parsed ShelterParsedContent
+function M.publish(bufnr, parsed)
+	local line_offsets = parsed.line_offsets
+	local items = {}
+	for i, diagnostic in ipairs(parsed.diagnostics or {}) do
+		local line_start = line_offsets[diagnostic.line_number] or 0
+		items[i] = {
+			lnum = diagnostic.line_number - 1,
+			col = diagnostic.start_offset - line_start,
+			end_col = diagnostic.end_offset - line_start,
+			severity = diagnostic.severity,
+			message = diagnostic.message,
+			code = diagnostic.code,
+			source = "shelter",
+		}
+	end
+	vim.diagnostic.set(ns, bufnr, items)
+end
+
+---Remove the diagnostics of a buffer
+---@param bufnr number
+function M.clear(bufnr)
+	vim.diagnostic.reset(ns, bufnr)
+end
+
+return M
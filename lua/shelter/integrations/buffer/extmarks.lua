@@ -5,11 +5,15 @@ local M = {}
 
 local config = require("shelter.config")
 local state = require("shelter.state")
+local text = require("shelter.utils.text")
 
 -- Fast locals for hot path
 local api = vim.api
 local nvim_buf_is_valid = api.nvim_buf_is_valid
 local nvim_buf_set_extmark = api.nvim_buf_set_extmark
+local nvim_buf_del_extmark = api.nvim_buf_del_extmark
+local nvim_buf_get_extmark_by_id = api.nvim_buf_get_extmark_by_id
+local nvim_buf_get_extmarks = api.nvim_buf_get_extmarks
 local nvim_buf_clear_namespace = api.nvim_buf_clear_namespace
 local nvim_create_namespace = api.nvim_create_namespace
 local math_max = math.max
@@ -27,6 +31,16 @@ end
 -- Namespace for extmarks
 local ns_id = nil
 
+-- Namespace for private keys in comments, which belong to no entry
+local comment_keys_ns = nil
+
+-- Extmark ids per buffer, keyed by stable entry id (bufnr → entry id → mark ids)
+local marks_by_entry = {}
+
+-- Change kinds from native.diff (mirrors ShelterChangeKind)
+local CHANGE_REMOVED = 1
+local CHANGE_MOVED = 2
+
 ---Get or create the namespace
 ---@return number
 function M.get_namespace()
@@ -41,6 +55,10 @@ end
 function M.clear(bufnr)
 	local ns = M.get_namespace()
 	nvim_buf_clear_namespace(bufnr, ns, 0, -1)
+	if comment_keys_ns then
+		nvim_buf_clear_namespace(bufnr, comment_keys_ns, 0, -1)
+	end
+	marks_by_entry[bufnr] = nil
 end
 
 ---Clear extmarks in a specific line range
@@ -59,9 +77,20 @@ end
 ---@param mask_info ShelterMaskedLine
 ---@param lines string[]
 ---@param line_offsets number[]
----@param hl_group string
+---@param hl_group string Default highlight group (a policy rule's group takes precedence)
 ---@param mask_char string
 local function apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_group, mask_char)
+	hl_group = mask_info.hl_group or hl_group
+	local tracked = marks_by_entry[bufnr]
+	if not tracked then
+		tracked = {}
+		marks_by_entry[bufnr] = tracked
+	end
+	local mark_ids = {}
+	if mask_info.id then
+		tracked[mask_info.id] = mark_ids
+	end
+
 	local start_line_idx = mask_info.line_number - 1
 	local end_line_idx = mask_info.value_end_line - 1
 
@@ -125,12 +154,12 @@ local function apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_g
 			col_start = math_max(0, col_start)
 			col_end = math_max(col_start, col_end)
 
-			-- Generate mask using cached string
-			local line_content_len = col_end - col_start
-			local line_mask = get_cached_mask(mask_char, line_content_len)
+			-- Generate a mask as wide as the segment on screen
+			local segment_width = text.width(current_line:sub(col_start + 1, col_end))
+			local line_mask = text.fill(mask_char, segment_width, get_cached_mask)
 
 			-- Apply extmark directly (no intermediate table)
-			nvim_buf_set_extmark(bufnr, ns, line_idx, col_start, {
+			mark_ids[#mark_ids + 1] = nvim_buf_set_extmark(bufnr, ns, line_idx, col_start, {
 				end_col = col_end,
 				virt_text = { { line_mask, hl_group } },
 				virt_text_pos = "overlay",
@@ -161,10 +190,18 @@ local function apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_g
 		value_end_col = math_min(value_end_col, #start_line)
 		value_end_col = math_max(value_start_col, value_end_col)
 
+		-- Pad masks narrower than the value so no character shows through
+		local mask = mask_info.mask
+		local value_width = text.width(start_line:sub(value_start_col + 1, value_end_col))
+		local mask_width = text.width(mask)
+		if mask_width < value_width then
+			mask = mask .. string.rep(" ", value_width - mask_width)
+		end
+
 		-- Apply extmark directly (no intermediate table)
-		nvim_buf_set_extmark(bufnr, ns, start_line_idx, value_start_col, {
+		mark_ids[#mark_ids + 1] = nvim_buf_set_extmark(bufnr, ns, start_line_idx, value_start_col, {
 			end_col = value_end_col,
-			virt_text = { { mask_info.mask, hl_group } },
+			virt_text = { { mask, hl_group } },
 			virt_text_pos = "overlay",
 			hl_mode = "combine",
 			priority = 9999,
@@ -206,4 +243,187 @@ function M.apply_masks(bufnr, masks, line_offsets, lines, sync)
 	end
 end
 
+---Apply a native render plan (overlays are fully resolved in Rust)
+---Entries with any revealed row are left visible
+---@param bufnr number
+---@param overlays ShelterOverlayChunk[]
+---@param sync? boolean If true, apply synchronously
+function M.apply_plan(bufnr, overlays, sync)
+	local ns = M.get_namespace()
+
+	local function do_apply()
+		if not nvim_buf_is_valid(bufnr) then
+			return
+		end
+
+		-- Overlays without an entry (private keys in comments) are never revealed
+		local revealed_entries = {}
+		for _, overlay in ipairs(overlays) do
+			if overlay.entry_index and state.is_line_revealed(overlay.row + 1) then
+				revealed_entries[overlay.entry_index] = true
+			end
+		end
+
+		for _, overlay in ipairs(overlays) do
+			if not revealed_entries[overlay.entry_index] then
+				nvim_buf_set_extmark(bufnr, ns, overlay.row, overlay.col, {
+					end_col = overlay.end_col,
+					virt_text = { { overlay.text, overlay.hl_id } },
+					virt_text_pos = "overlay",
+					hl_mode = "combine",
+					priority = 9999,
+					strict = false,
+				})
+			end
+		end
+	end
+
+	if sync then
+		do_apply()
+	else
+		vim.schedule(do_apply)
+	end
+end
+
+---Mask private keys pasted into comments
+---They belong to no entry, so they live in their own namespace, are replaced
+---on every update and are never revealed
+---@param bufnr number
+---@param comment_keys ShelterLineSpan[] From the parse result
+---@param line_offsets number[] Pre-computed line offsets from Rust
+---@param sync? boolean If true, apply synchronously
+function M.apply_comment_keys(bufnr, comment_keys, line_offsets, sync)
+	if not comment_keys_ns then
+		if #comment_keys == 0 then
+			return
+		end
+		comment_keys_ns = nvim_create_namespace("shelter_comment_keys")
+	end
+	local cfg = config.get()
+	local hl_group = cfg.highlight_group or "Comment"
+	local mask_char = cfg.mask_char or "*"
+
+	local function do_apply()
+		if not nvim_buf_is_valid(bufnr) then
+			return
+		end
+		nvim_buf_clear_namespace(bufnr, comment_keys_ns, 0, -1)
+		for _, span in ipairs(comment_keys) do
+			local line_start = line_offsets[span.line_number] or 0
+			-- Base64 is ASCII: one cell per byte
+			local width = span.end_offset - span.start_offset
+			nvim_buf_set_extmark(bufnr, comment_keys_ns, span.line_number - 1, span.start_offset - line_start, {
+				end_col = span.end_offset - line_start,
+				virt_text = { { get_cached_mask(mask_char, width), hl_group } },
+				virt_text_pos = "overlay",
+				hl_mode = "combine",
+				priority = 9999,
+				strict = false,
+			})
+		end
+	end
+
+	if sync then
+		do_apply()
+	else
+		vim.schedule(do_apply)
+	end
+end
+
+---Check that an entry's tracked extmarks still exist at the expected row
+---@param bufnr number
+---@param ns number
+---@param mark_ids number[]|nil
+---@param row number 0-indexed row of the first mark
+---@return boolean
+local function marks_in_place(bufnr, ns, mark_ids, row)
+	if not mark_ids or #mark_ids == 0 then
+		return false
+	end
+	for _, mark_id in ipairs(mark_ids) do
+		if #nvim_buf_get_extmark_by_id(bufnr, ns, mark_id, {}) == 0 then
+			return false
+		end
+	end
+	return nvim_buf_get_extmark_by_id(bufnr, ns, mark_ids[1], {})[1] == row
+end
+
+---Update only the extmarks of entries that changed between two parses
+---Moved entries keep their extmarks (they follow the text) unless they went missing
+---@param bufnr number
+---@param changes ShelterEntryChange[] Changes from native.diff
+---@param masks ShelterMaskedLine[] Masks for the new parse result
+---@param line_offsets number[] Pre-computed line offsets from Rust
+---@param lines string[] Buffer lines (passed to avoid double read)
+---@param sync? boolean If true, apply synchronously
+function M.apply_changes(bufnr, changes, masks, line_offsets, lines, sync)
+	local ns = M.get_namespace()
+	local cfg = config.get()
+	local hl_group = cfg.highlight_group or "Comment"
+	local mask_char = cfg.mask_char or "*"
+
+	local function do_apply()
+		if not nvim_buf_is_valid(bufnr) then
+			return
+		end
+
+		local masks_by_id = {}
+		for _, mask_info in ipairs(masks) do
+			masks_by_id[mask_info.id] = mask_info
+		end
+
+		local tracked = marks_by_entry[bufnr] or {}
+		marks_by_entry[bufnr] = tracked
+
+		for _, change in ipairs(changes) do
+			local mask_info = masks_by_id[change.id]
+			local mark_ids = tracked[change.id]
+			local keep = change.kind == CHANGE_MOVED
+				and mask_info ~= nil
+				and marks_in_place(bufnr, ns, mark_ids, mask_info.line_number - 1)
+
+			if not keep then
+				if mark_ids then
+					for _, mark_id in ipairs(mark_ids) do
+						nvim_buf_del_extmark(bufnr, ns, mark_id)
+					end
+					tracked[change.id] = nil
+				end
+				if change.kind ~= CHANGE_REMOVED and mask_info then
+					apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_group, mask_char)
+				end
+			end
+		end
+	end
+
+	if sync then
+		do_apply()
+	else
+		vim.schedule(do_apply)
+	end
+end
+
+---Collect the overlays currently placed in a buffer
+---@param bufnr number
+---@return {row: number, col: number, text: string}[]
+function M.get_overlays(bufnr)
+	local overlays = {}
+	if not nvim_buf_is_valid(bufnr) then
+		return overlays
+	end
+
+	local marks = nvim_buf_get_extmarks(bufnr, M.get_namespace(), 0, -1, { details = true })
+	for _, mark in ipairs(marks) do
+		local details = mark[4]
+		if details.virt_text and details.virt_text_pos == "overlay" then
+			local chunks = {}
+			for i, chunk in ipairs(details.virt_text) do
+				chunks[i] = chunk[1]
+			end
+			overlays[#overlays + 1] = { row = mark[2], col = mark[3], text = table.concat(chunks) }
+		end
+	end
+	return overlays
+end
+
 return M
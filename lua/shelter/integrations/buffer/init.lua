@@ -6,14 +6,17 @@ local M = {}
 local config = require("shelter.config")
 local state = require("shelter.state")
 local masking = require("shelter.masking")
+local native = require("shelter.native")
 local env_file = require("shelter.utils.env_file")
 
 -- Sub-modules
 local extmarks = require("shelter.integrations.buffer.extmarks")
 local peek = require("shelter.integrations.buffer.peek")
+local reveal = require("shelter.integrations.buffer.reveal")
 local completion = require("shelter.integrations.buffer.completion")
 local paste = require("shelter.integrations.buffer.paste")
 local autocmds = require("shelter.integrations.buffer.autocmds")
+local diagnostics = require("shelter.integrations.buffer.diagnostics")
 
 -- Fast locals for hot path
 local api = vim.api
@@ -46,6 +49,7 @@ end
 ---@field masks ShelterMaskedLine[]
 ---@field line_offsets number[]
 ---@field line_count number
+---@field parsed ShelterParsedContent|nil Parse result behind the applied masks (for diffing)
 
 ---Get buffer mask cache
 ---@param bufnr number
@@ -59,11 +63,13 @@ end
 ---@param masks ShelterMaskedLine[]
 ---@param line_offsets number[]
 ---@param line_count number
-local function set_buffer_cache(bufnr, masks, line_offsets, line_count)
+---@param parsed? ShelterParsedContent
+local function set_buffer_cache(bufnr, masks, line_offsets, line_count, parsed)
 	buffer_mask_cache[bufnr] = {
 		masks = masks,
 		line_offsets = line_offsets,
 		line_count = line_count,
+		parsed = parsed,
 	}
 end
 
@@ -109,8 +115,8 @@ local function attach_buffer(bufnr)
 			-- Check if line count changed (lines added/removed)
 			if last_line ~= last_line_updated then
 				-- Line count changed - must do full re-mask
+				-- (mask cache is kept so the full path can diff against it)
 				buffer_content_hashes[buf] = nil
-				invalidate_buffer_cache(buf)
 				vim.schedule(function()
 					if nvim_buf_is_valid(buf) and state.is_enabled("files") then
 						M.shelter_buffer(buf, true)
@@ -134,6 +140,73 @@ local function attach_buffer(bufnr)
 	buffer_attached[bufnr] = true
 end
 
+---Debug check: warn when placed overlays leave secret bytes visible
+---Runs after pending extmark updates; rows revealed by peek are ignored
+---@param bufnr number
+local function verify_buffer(bufnr)
+	if not config.get_files_config().verify_overlays then
+		return
+	end
+
+	vim.schedule(function()
+		if not nvim_buf_is_valid(bufnr) then
+			return
+		end
+		local content = table_concat(nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+		local ok, leaks = pcall(
+			masking.verify_overlays,
+			content,
+			nvim_buf_get_name(bufnr),
+			extmarks.get_overlays(bufnr),
+			vim.bo[bufnr].tabstop
+		)
+		if not ok or not leaks then
+			return
+		end
+
+		local messages = {}
+		for _, leak in ipairs(leaks) do
+			if not state.is_line_revealed(leak.row + 1) then
+				messages[#messages + 1] =
+					string.format("  line %d, columns %d-%d", leak.row + 1, leak.col + 1, leak.end_col)
+			end
+		end
+		if #messages > 0 then
+			vim.notify(
+				"shelter.nvim: Secret bytes visible in buffer " .. bufnr .. ":\n" .. table_concat(messages, "\n"),
+				vim.log.levels.WARN
+			)
+		end
+	end)
+end
+
+---Whether an edit may change a `# shelter:` directive
+---Directives can change the mode of entries outside the edited range, so such
+---edits take the full path. True if the previous parse had directives or the
+---edited lines contain one.
+---@param lines string[]
+---@param line_range {min_line: number, max_line: number}
+---@param parsed ShelterParsedContent|nil Previous parse result
+---@return boolean
+local function edits_directive(lines, line_range, parsed)
+	if parsed then
+		if #(parsed.diagnostics or {}) > 0 then
+			return true
+		end
+		for _, entry in ipairs(parsed.entries) do
+			if entry.directive_mode then
+				return true
+			end
+		end
+	end
+	for i = math_max(1, line_range.min_line), math_min(#lines, line_range.max_line + 2) do
+		if lines[i]:find("^%s*#%s*shelter:") then
+			return true
+		end
+	end
+	return false
+end
+
 ---Shelter a buffer (apply masks)
 ---@param bufnr? number Buffer number (default: current)
 ---@param sync? boolean If true, apply masks synchronously (for paste protection)
@@ -172,7 +245,12 @@ function M.shelter_buffer(bufnr, sync, line_range)
 		invalidate_buffer_cache(bufnr)
 		buffer_content_hashes[bufnr] = nil
 		-- Fall through to FULL PATH below
-	elseif line_range and cache and cache.line_count == #lines then
+	elseif
+		line_range
+		and cache
+		and cache.line_count == #lines
+		and not edits_directive(lines, line_range, cache.parsed)
+	then
 		-- INCREMENTAL PATH: Only re-parse changed lines
 		local parse_start = math_max(1, line_range.min_line) -- 0-indexed to 1-indexed
 		local parse_end = math_min(#lines, line_range.max_line + 2) -- +1 for exclusive, +1 for buffer
@@ -185,14 +263,18 @@ function M.shelter_buffer(bufnr, sync, line_range)
 			content,
 			bufname,
 			{ min_line = parse_start, max_line = parse_end },
-			cache.masks
+			cache.masks,
+			reveal.get(bufnr)
 		)
 
 		-- Update cache with merged masks
-		set_buffer_cache(bufnr, result.masks, result.line_offsets, #lines)
+		set_buffer_cache(bufnr, result.masks, result.line_offsets, #lines, result.parsed)
 
 		-- Apply ONLY the new/changed masks
 		extmarks.apply_masks(bufnr, result.masks_to_apply, result.line_offsets, lines, sync)
+		extmarks.apply_comment_keys(bufnr, result.parsed.comment_keys, result.line_offsets, sync)
+		diagnostics.publish(bufnr, result.parsed)
+		verify_buffer(bufnr)
 		return
 	end
 
@@ -214,29 +296,44 @@ function M.shelter_buffer(bufnr, sync, line_range)
 		buffer_content_hashes[bufnr] = simple_hash
 	end
 
-	-- Invalidate cache and clear extmarks
-	invalidate_buffer_cache(bufnr)
-	extmarks.clear(bufnr)
-
 	-- Generate masks (includes pre-computed line_offsets from Rust)
-	local result = masking.generate_masks(content, bufname)
+	local result = masking.generate_masks(content, bufname, reveal.get(bufnr))
 
 	-- line_offsets must be provided by Rust - no fallbacks
 	local line_offsets = result.line_offsets
 	assert(line_offsets and #line_offsets > 0, "shelter.nvim: line_offsets not provided by native parser")
 
+	-- Previous masks still applied: update only the entries that changed
+	local previous = get_buffer_cache(bufnr)
+	if previous and previous.parsed then
+		local changes = native.diff(previous.parsed, result.parsed)
+		set_buffer_cache(bufnr, result.masks, line_offsets, #lines, result.parsed)
+		extmarks.apply_changes(bufnr, changes, result.masks, line_offsets, lines, sync)
+		extmarks.apply_comment_keys(bufnr, result.parsed.comment_keys, line_offsets, sync)
+		diagnostics.publish(bufnr, result.parsed)
+		verify_buffer(bufnr)
+		return
+	end
+
+	-- No usable cache: clear extmarks and apply everything
+	invalidate_buffer_cache(bufnr)
+	extmarks.clear(bufnr)
+
 	-- Cache for future incremental updates
-	set_buffer_cache(bufnr, result.masks, line_offsets, #lines)
+	set_buffer_cache(bufnr, result.masks, line_offsets, #lines, result.parsed)
 
 	-- Apply all masks
 	extmarks.apply_masks(bufnr, result.masks, line_offsets, lines, sync)
+	extmarks.apply_comment_keys(bufnr, result.parsed.comment_keys, line_offsets, sync)
+	diagnostics.publish(bufnr, result.parsed)
+	verify_buffer(bufnr)
 end
 
 ---Shelter a preview buffer (for picker integrations)
 ---Unlike shelter_buffer, this doesn't check "files" feature state
 ---and accepts filetype directly instead of checking buffer filetype
 ---@param bufnr number Buffer number
----@param filename string Filename for source tracking
+---@param filename string File path, matched against source patterns
 ---@param filetype? string Optional filetype override (for preview buffers)
 function M.shelter_preview_buffer(bufnr, filename, filetype)
 	-- Check if buffer is valid
@@ -257,6 +354,16 @@ function M.shelter_preview_buffer(bufnr, filename, filetype)
 	local lines = nvim_buf_get_lines(bufnr, 0, -1, false)
 	local content = table_concat(lines, "\n")
 
+	-- Built-in modes only: let Rust resolve everything and just place the overlays
+	-- (falls back to the Lua path when Rust cannot, e.g. for values holding a NUL)
+	if masking.can_render_natively() then
+		local ok, plan = pcall(masking.render_plan, content, filename, vim.bo[bufnr].tabstop)
+		if ok then
+			extmarks.apply_plan(bufnr, plan)
+			return
+		end
+	end
+
 	-- Generate masks (includes pre-computed line_offsets from Rust)
 	local result = masking.generate_masks(content, filename)
 
@@ -268,6 +375,7 @@ function M.shelter_preview_buffer(bufnr, filename, filetype)
 
 	-- Apply masks (pass lines to avoid double read)
 	extmarks.apply_masks(bufnr, result.masks, line_offsets, lines)
+	extmarks.apply_comment_keys(bufnr, result.parsed.comment_keys, line_offsets)
 end
 
 ---Unshelter a buffer (remove masks)
@@ -281,6 +389,7 @@ function M.unshelter_buffer(bufnr)
 	pcall(api.nvim_win_set_option, winid, "concealcursor", "")
 
 	extmarks.clear(bufnr)
+	diagnostics.clear(bufnr)
 	completion.restore(bufnr)
 
 	-- Clear content hash and mask cache so re-sheltering will apply masks
@@ -351,6 +460,41 @@ function M.toggle_peek(bufnr, line_num)
 	end)
 end
 
+-- ============================================================================
+-- Reveal Functions (Progressive per-entry reveal)
+-- ============================================================================
+
+---Set how much of the entry on a line to reveal
+---@param bufnr? number Buffer number (default: current)
+---@param line_num? number Line number (default: current cursor line)
+---@param level ShelterRevealLevel
+---@return string|nil key Key of the entry, nil if there is none on the line
+function M.reveal_entry(bufnr, line_num, level)
+	bufnr = bufnr or nvim_get_current_buf()
+	line_num = line_num or api.nvim_win_get_cursor(0)[1]
+
+	local content = table_concat(nvim_buf_get_lines(bufnr, 0, -1, false), "\n")
+	for _, entry in ipairs(masking.parse_content(content, nvim_buf_get_name(bufnr)).entries) do
+		if line_num >= entry.line_number and line_num <= entry.value_end_line then
+			reveal.set(bufnr, entry.id, level)
+			M.mark_needs_full_remask(bufnr)
+			M.shelter_buffer(bufnr, true)
+			return entry.key
+		end
+	end
+	return nil
+end
+
+---Hide every revealed entry of a buffer
+---@param bufnr? number Buffer number (default: current)
+function M.hide_reveals(bufnr)
+	bufnr = bufnr or nvim_get_current_buf()
+	if reveal.clear(bufnr) then
+		M.mark_needs_full_remask(bufnr)
+		M.shelter_buffer(bufnr, true)
+	end
+end
+
 -- ============================================================================
 -- Setup and Cleanup
 -- ============================================================================
@@ -405,6 +549,9 @@ function M.setup()
 			local files_config = config.get_files_config()
 			if files_config.shelter_on_leave then
 				state.reset_revealed_lines()
+				if reveal.clear(ev.buf) then
+					M.mark_needs_full_remask(ev.buf)
+				end
 				state.enable_all_user_modules()
 				if state.is_enabled("files") and nvim_buf_is_valid(ev.buf) then
 					M.shelter_buffer(ev.buf, true)
@@ -431,6 +578,7 @@ end
 function M.cleanup()
 	autocmds.cleanup()
 	peek.cleanup()
+	reveal.cleanup()
 	paste.cleanup()
 end
 
@@ -0,0 +1,49 @@
+---@class ShelterBufferReveal
+---Progressive reveal levels per entry
+---Levels are keyed by stable entry id, so they follow an entry when lines are
+---added or removed around it
+local M = {}
+
+---@type table<number, table<string, ShelterRevealLevel>>
+local levels = {}
+
+---Get the reveal levels of a buffer
+---@param bufnr number
+---@return table<string, ShelterRevealLevel>|nil levels Entry id → level
+function M.get(bufnr)
+	return levels[bufnr]
+end
+
+---Set the reveal level of an entry ("hidden" or nil clears it)
+---@param bufnr number
+---@param id string Stable entry id
+---@param level? ShelterRevealLevel
+function M.set(bufnr, id, level)
+	if not level or level.kind == "hidden" then
+		if levels[bufnr] then
+			levels[bufnr][id] = nil
+			if next(levels[bufnr]) == nil then
+				levels[bufnr] = nil
+			end
+		end
+		return
+	end
+	levels[bufnr] = levels[bufnr] or {}
+	levels[bufnr][id] = level
+end
+
+---Hide every revealed entry of a buffer
+---@param bufnr number
+---@return boolean had_reveals
+function M.clear(bufnr)
+	local had = levels[bufnr] ~= nil
+	levels[bufnr] = nil
+	return had
+end
+
+---Hide every revealed entry
+function M.cleanup()
+	levels = {}
+end
+
+return M
@@ -18,12 +18,11 @@ function M.apply_masking_if_env(bufnr, filepath)
 		return false
 	end
 
-	local basename = vim.fn.fnamemodify(filepath, ":t")
 	local filetype = vim.filetype.match({ filename = filepath })
 
 	if filetype and env_file.is_env_filetype(filetype) then
 		local buffer = require("shelter.integrations.buffer")
-		buffer.shelter_preview_buffer(bufnr, basename, filetype)
+		buffer.shelter_preview_buffer(bufnr, filepath, filetype)
 		return true
 	end
 
@@ -6,6 +6,7 @@ local config = require("shelter.config")
 local native = require("shelter.native")
 local modes = require("shelter.modes")
 local pattern_cache = require("shelter.utils.pattern_cache")
+local keyfile = require("shelter.utils.keyfile")
 
 -- LRU Cache for parsed content
 local LRU_SIZE = 200
@@ -94,38 +95,83 @@ end
 ---@class ShelterParsedContent
 ---@field entries ShelterParsedEntry[]
 ---@field line_offsets number[]
+---@field handle ffi.cdata* Native result, used for diffing
+---@field analysed? boolean Whether masked_length follows the configured modes
+
+---Compiled pattern sets for native calls (compiled on demand)
+---@return ffi.cdata*|nil key_set, ffi.cdata*|nil source_set
+local function pattern_sets()
+	if not pattern_cache.is_compiled() then
+		pattern_cache.compile(config.get())
+	end
+	return pattern_cache.get_sets()
+end
 
 ---Parse buffer content with caching
 ---Returns both entries and pre-computed line offsets from Rust
+---When a built-in mode hides value lengths, mask lengths are computed natively
+---for the configured modes, so the keyed strategy never runs in Lua. Likewise,
+---fingerprints are computed natively when the fingerprint mode is in use.
 ---@param content string
+---@param source? string
 ---@return ShelterParsedContent
-function M.parse_content(content)
+function M.parse_content(content, source)
+	local analyse = M.hides_lengths() or M.get_policy() ~= nil
 	local cache_key = hash_content(content)
+	if analyse then
+		cache_key = cache_key .. "\0" .. (source or "")
+	end
 	local cached = parsed_cache:get(cache_key)
 	if cached then
 		return cached
 	end
 
 	-- native.parse now returns {entries, line_offsets}
-	local result = native.parse(content)
+	local cfg = config.get()
+	local key_set, source_set = pattern_sets()
+	local result = native.parse(content, {
+		render = analyse and {
+			modes = M.get_native_modes(),
+			default_mode = cfg.default_mode,
+			key_set = key_set,
+			source_set = source_set,
+			policy = M.get_policy(),
+			source = source,
+			directives = cfg.directives,
+			public_prefixes = cfg.public_prefixes,
+		} or nil,
+		fingerprint_key = M.uses_fingerprints() and keyfile.get() or nil,
+		-- Policy conditions classify and score values natively, so only the
+		-- passes whose results are read here are requested
+		payloads = M.uses_payload_secrets(),
+		-- Expired tokens are reported as diagnostics
+		jwts = true,
+		pems = true,
+		weak_values = true,
+		min_secret_length = cfg.min_secret_length,
+		exposure = true,
+		public_prefixes = cfg.public_prefixes,
+		mode_names = modes.list(),
+	})
+	result.analysed = analyse
 	parsed_cache:put(cache_key, result)
 	return result
 end
 
 ---Determine masking mode for a key based on patterns (uses pattern cache)
 ---@param key string
----@param source_basename string|nil Pre-computed basename of source file
+---@param source string|nil Path of the source file
 ---@return string mode_name
-function M.determine_mode(key, source_basename)
+function M.determine_mode(key, source)
 	-- Use pre-compiled pattern cache if available
 	if pattern_cache.is_compiled() then
-		return pattern_cache.determine_mode(key, source_basename)
+		return pattern_cache.determine_mode(key, source)
 	end
 
 	-- Fallback: compile on demand (shouldn't happen after setup)
 	local cfg = config.get()
 	pattern_cache.compile(cfg)
-	return pattern_cache.determine_mode(key, source_basename)
+	return pattern_cache.determine_mode(key, source)
 end
 
 ---@class ShelterMaskContext
@@ -151,6 +197,7 @@ function M.mask_value(value, context, cfg, mode_name)
 end
 
 ---@class ShelterMaskedLine
+---@field id string Stable entry identity
 ---@field line_number number
 ---@field value_end_line number
 ---@field mask string
@@ -159,26 +206,89 @@ end
 ---@field value string
 ---@field is_comment boolean
 ---@field quote_type number 0=none, 1=single, 2=double
+---@field hl_group? string Highlight group chosen by a policy rule
 
 ---@class ShelterMaskResult
 ---@field masks ShelterMaskedLine[]
 ---@field line_offsets number[] Pre-computed line offsets from Rust
+---@field parsed ShelterParsedContent Parse result the masks were generated from (for diffing)
+
+---Mode name and highlight group for a parsed entry
+---Private keys always use `full`. Otherwise an inline directive wins, then
+---policy rules, then key and source patterns. Public keys without a secret
+---(see `public_prefixes`) fall back to `none` rather than the default mode.
+---Directives, policy rules and exposure can differ between entries with the
+---same key, so only pattern choices are memoized by key
+---@param entry ShelterParsedEntry
+---@param source string|nil
+---@param memo table<string, string> Key → mode name
+---@param cfg table
+---@return string mode_name, string|nil hl_group
+local function select_mode(entry, source, memo, cfg)
+	-- Private keys are masked whatever the directive, policy or pattern says
+	if entry.pem and entry.pem.kind == "private_key" then
+		return "full", entry.policy_rule and cfg.policy[entry.policy_rule].highlight
+	end
+	if entry.directive_mode and cfg.directives ~= false then
+		-- Like the native resolve, mask in full rather than fail on an unknown mode
+		if not modes.exists(entry.directive_mode) then
+			return "full", nil
+		end
+		return entry.directive_mode, nil
+	end
+	if entry.policy_rule then
+		local rule = cfg.policy[entry.policy_rule]
+		return rule.mode, rule.highlight
+	end
+	if
+		entry.exposure == "public"
+		and not pattern_cache.match_key(entry.key)
+		and not (source and pattern_cache.match_source(source))
+	then
+		return "none", nil
+	end
+	local mode_name = memo[entry.key]
+	if not mode_name then
+		mode_name = pattern_cache.determine_mode(entry.key, source)
+		memo[entry.key] = mode_name
+	end
+	return mode_name, nil
+end
+
+---Mask for an entry with a reveal level
+---Built-in modes reveal natively; custom modes can only be fully revealed
+---@param mode_name string
+---@param mode ShelterModeBase
+---@param context ShelterModeContext
+---@param level ShelterRevealLevel
+---@return string
+local function apply_reveal(mode_name, mode, context, level)
+	if level.kind == "full" then
+		return context.value
+	end
+	if modes.is_builtin(mode_name) then
+		local masked = native.mask_value(M.get_native_modes(), mode_name, context.value, level)
+		if masked then
+			return masked
+		end
+	end
+	return mode:apply(context)
+end
 
 ---Generate masks for buffer content
 ---Returns masks and pre-computed line offsets for O(1) byte-to-column conversion
 ---@param content string
 ---@param source string|nil
+---@param reveals? table<string, ShelterRevealLevel> Entry id → reveal level
 ---@return ShelterMaskResult
-function M.generate_masks(content, source)
+function M.generate_masks(content, source, reveals)
 	local cfg = config.get()
 	local skip_comments = cfg.skip_comments
-	local parsed = M.parse_content(content)
+	local parsed = M.parse_content(content, source)
+	local analysed = parsed.analysed
 	local masks = {}
 	local mask_count = 0
 
-	-- Cache source basename once (avoid vim.fn.fnamemodify per entry)
-	local source_basename = source and vim.fn.fnamemodify(source, ":t") or nil
-
 	-- Memoize key→mode name mapping for this batch
 	local mode_name_memo = {}
 
@@ -192,6 +302,9 @@ function M.generate_masks(content, source)
 		line_number = nil,
 		quote_type = nil,
 		is_comment = nil,
+		masked_length = nil,
+		fingerprint = nil,
+		payload_secret = nil,
 		config = cfg,
 		value = nil,
 	}
@@ -202,12 +315,7 @@ function M.generate_masks(content, source)
 		local should_skip = entry.is_comment and skip_comments
 
 		if not should_skip then
-			-- Check memoized mode name first
-			local mode_name = mode_name_memo[entry.key]
-			if not mode_name then
-				mode_name = pattern_cache.determine_mode(entry.key, source_basename)
-				mode_name_memo[entry.key] = mode_name
-			end
+			local mode_name, hl_group = select_mode(entry, source, mode_name_memo, cfg)
 
 			-- Get cached mode instance (avoids modes.get lookup per entry)
 			local mode = mode_instance_cache[mode_name]
@@ -222,19 +330,25 @@ function M.generate_masks(content, source)
 			context.line_number = entry.line_number
 			context.quote_type = entry.quote_type
 			context.is_comment = entry.is_comment
+			context.masked_length = analysed and entry.masked_length or nil
+			context.fingerprint = entry.fingerprint
+			context.payload_secret = entry.payload_secret
 
 			-- Call mode:apply directly (skip modes.apply overhead)
-			local mask = mode:apply(context)
+			local level = reveals and reveals[entry.id]
+			local mask = level and apply_reveal(mode_name, mode, context, level) or mode:apply(context)
 
 			-- Direct indexed assignment (faster than #masks + 1)
 			mask_count = mask_count + 1
 			masks[mask_count] = {
+				id = entry.id,
 				line_number = entry.line_number,
 				value_end_line = entry.value_end_line,
 				mask = mask,
 				value_start = entry.value_start,
 				value_end = entry.value_end,
 				quote_type = entry.quote_type,
+				hl_group = hl_group,
 				value = entry.value, -- Keep for tests/diagnostics (string ref, no copy)
 			}
 		end
@@ -243,6 +357,7 @@ function M.generate_masks(content, source)
 	return {
 		masks = masks,
 		line_offsets = parsed.line_offsets,
+		parsed = parsed,
 	}
 end
 
@@ -252,11 +367,13 @@ end
 ---@param source string|nil
 ---@param line_range {min_line: number, max_line: number} 1-indexed line range
 ---@param cached_masks ShelterMaskedLine[] Previously cached masks
+---@param reveals? table<string, ShelterRevealLevel> Entry id → reveal level
 ---@return ShelterMaskResult
-function M.generate_masks_incremental(content, source, line_range, cached_masks)
+function M.generate_masks_incremental(content, source, line_range, cached_masks, reveals)
 	local cfg = config.get()
 	local skip_comments = cfg.skip_comments
-	local parsed = M.parse_content(content)
+	local parsed = M.parse_content(content, source)
+	local analysed = parsed.analysed
 
 	-- Filter entries to only those in affected range
 	local affected_entries = {}
@@ -269,7 +386,6 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 	end
 
 	-- Generate masks ONLY for affected entries
-	local source_basename = source and vim.fn.fnamemodify(source, ":t") or nil
 	local mode_name_memo = {}
 	local mode_instance_cache = {}
 	local new_masks = {}
@@ -282,6 +398,9 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 		line_number = nil,
 		quote_type = nil,
 		is_comment = nil,
+		masked_length = nil,
+		fingerprint = nil,
+		payload_secret = nil,
 		config = cfg,
 		value = nil,
 	}
@@ -289,11 +408,7 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 	for _, entry in ipairs(affected_entries) do
 		local should_skip = entry.is_comment and skip_comments
 		if not should_skip then
-			local mode_name = mode_name_memo[entry.key]
-			if not mode_name then
-				mode_name = pattern_cache.determine_mode(entry.key, source_basename)
-				mode_name_memo[entry.key] = mode_name
-			end
+			local mode_name, hl_group = select_mode(entry, source, mode_name_memo, cfg)
 
 			local mode = mode_instance_cache[mode_name]
 			if not mode then
@@ -306,17 +421,23 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 			context.line_number = entry.line_number
 			context.quote_type = entry.quote_type
 			context.is_comment = entry.is_comment
+			context.masked_length = analysed and entry.masked_length or nil
+			context.fingerprint = entry.fingerprint
+			context.payload_secret = entry.payload_secret
 
-			local mask = mode:apply(context)
+			local level = reveals and reveals[entry.id]
+			local mask = level and apply_reveal(mode_name, mode, context, level) or mode:apply(context)
 
 			new_mask_count = new_mask_count + 1
 			new_masks[new_mask_count] = {
+				id = entry.id,
 				line_number = entry.line_number,
 				value_end_line = entry.value_end_line,
 				mask = mask,
 				value_start = entry.value_start,
 				value_end = entry.value_end,
 				quote_type = entry.quote_type,
+				hl_group = hl_group,
 				value = entry.value, -- Keep for tests/diagnostics (string ref, no copy)
 			}
 		end
@@ -345,9 +466,223 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 		masks = merged_masks,
 		masks_to_apply = new_masks, -- Only these need extmark update
 		line_offsets = parsed.line_offsets,
+		parsed = parsed,
 	}
 end
 
+-- Native mode table mirroring the configured built-in modes
+local native_modes = nil
+
+-- Whether any built-in mode uses a length-hiding strategy (nil = not computed)
+local hides_lengths = nil
+
+-- Whether the fingerprint mode is selected by default or by a pattern (nil = not computed)
+local uses_fingerprints = nil
+
+-- Whether any built-in mode falls back to showing short values (nil = not computed)
+local uses_payload_secrets = nil
+
+-- Compiled policy rules (false = no rules configured, nil = not compiled)
+local native_policy = nil
+
+---Build the native mode table from the configured built-in mode instances
+---Custom Lua modes have no native equivalent and are left out
+local function build_native_modes()
+	local spec = {}
+	for _, name in ipairs(modes.list()) do
+		if modes.is_builtin(name) then
+			spec[name] = vim.tbl_extend("force", modes.get(name).options, { kind = name })
+		end
+	end
+	-- Only load (or create) the per-user key when a mode needs it
+	local key = (M.hides_lengths() or M.uses_fingerprints()) and keyfile.get() or nil
+	native_modes = native.new_mode_table(spec, key)
+end
+
+---Check whether any built-in mode hides value lengths (bucket or keyed strategy)
+---@return boolean
+function M.hides_lengths()
+	if hides_lengths == nil then
+		hides_lengths = false
+		for _, name in ipairs(modes.list()) do
+			local strategy = modes.is_builtin(name) and modes.get(name).options.length_strategy
+			if strategy and strategy ~= "preserve" then
+				hides_lengths = true
+				break
+			end
+		end
+	end
+	return hides_lengths
+end
+
+---Check whether the fingerprint mode can be selected for some entry
+---@return boolean
+function M.uses_fingerprints()
+	if uses_fingerprints == nil then
+		local cfg = config.get()
+		uses_fingerprints = cfg.default_mode == "fingerprint"
+			or vim.tbl_contains(vim.tbl_values(cfg.patterns or {}), "fingerprint")
+			or vim.tbl_contains(vim.tbl_values(cfg.sources or {}), "fingerprint")
+		for _, rule in ipairs(cfg.policy or {}) do
+			uses_fingerprints = uses_fingerprints or rule.mode == "fingerprint"
+		end
+	end
+	return uses_fingerprints
+end
+
+---Check whether any built-in mode falls back to `none`, which needs to know
+---whether a short value is an encoded secret
+---@return boolean
+function M.uses_payload_secrets()
+	if uses_payload_secrets == nil then
+		uses_payload_secrets = false
+		for _, name in ipairs(modes.list()) do
+			if modes.is_builtin(name) and modes.get(name).options.fallback_mode == "none" then
+				uses_payload_secrets = true
+				break
+			end
+		end
+	end
+	return uses_payload_secrets
+end
+
+---Get the compiled policy rules
+---@return ffi.cdata*|nil policy nil when no rules are configured
+function M.get_policy()
+	if native_policy == nil then
+		local cfg = config.get()
+		local rules = {}
+		for i, rule in ipairs(cfg.policy or {}) do
+			rules[i] = vim.tbl_extend("force", rule, {
+				highlight_id = rule.highlight and vim.api.nvim_get_hl_id_by_name(rule.highlight) or nil,
+			})
+		end
+		native_policy = #rules > 0 and native.new_policy(rules, { ignore_case = cfg.pattern_ignore_case }) or false
+	end
+	return native_policy or nil
+end
+
+---Get the native mode table (for masking without Lua mode instances)
+---@return ffi.cdata*
+function M.get_native_modes()
+	if not native_modes then
+		build_native_modes()
+	end
+	return native_modes
+end
+
+---Check whether the configured modes can all be rendered natively
+---Custom Lua modes (apply functions) and `none` transforms need the Lua path
+---@return boolean
+function M.can_render_natively()
+	local cfg = config.get()
+	for _, mode_opts in pairs(cfg.modes or {}) do
+		if type(mode_opts) == "table" and (mode_opts.apply or mode_opts.transform) then
+			return false
+		end
+	end
+	return true
+end
+
+---Build a native render plan for content (parse, mode selection and masking in Rust)
+---@param content string
+---@param source string|nil
+---@param tabstop? number Tab width of the target buffer
+---@return ShelterOverlayChunk[]
+function M.render_plan(content, source, tabstop)
+	local cfg = config.get()
+	local key_set, source_set = pattern_sets()
+	return native.render_plan(content, {
+		modes = M.get_native_modes(),
+		default_mode = cfg.default_mode,
+		key_set = key_set,
+		source_set = source_set,
+		policy = M.get_policy(),
+		source = source,
+		directives = cfg.directives,
+		public_prefixes = cfg.public_prefixes,
+		tabstop = tabstop,
+		highlight_id = vim.api.nvim_get_hl_id_by_name(cfg.highlight_group or "Comment"),
+		skip_comments = cfg.skip_comments,
+	})
+end
+
+---Render a fully masked copy of content natively
+---Returns nil when custom Lua modes are configured, since they cannot run natively
+---@param content string
+---@param source string|nil
+---@return ShelterMaskedDocument|nil
+function M.render_masked(content, source)
+	if not M.can_render_natively() then
+		return nil
+	end
+	local cfg = config.get()
+	local key_set, source_set = pattern_sets()
+	return native.render_masked(content, {
+		modes = M.get_native_modes(),
+		default_mode = cfg.default_mode,
+		key_set = key_set,
+		source_set = source_set,
+		policy = M.get_policy(),
+		source = source,
+		directives = cfg.directives,
+		public_prefixes = cfg.public_prefixes,
+		skip_comments = cfg.skip_comments,
+	})
+end
+
+---Check placed overlays for secret bytes that remain visible
+---Returns nil when custom Lua modes are configured, since they cannot run natively
+---@param content string
+---@param source string|nil
+---@param overlays {row: number, col: number, text: string}[]
+---@param tabstop? number
+---@return ShelterLeak[]|nil
+function M.verify_overlays(content, source, overlays, tabstop)
+	if not M.can_render_natively() then
+		return nil
+	end
+	local cfg = config.get()
+	local key_set, source_set = pattern_sets()
+	return native.verify_overlays(content, overlays, {
+		modes = M.get_native_modes(),
+		default_mode = cfg.default_mode,
+		key_set = key_set,
+		source_set = source_set,
+		policy = M.get_policy(),
+		source = source,
+		directives = cfg.directives,
+		public_prefixes = cfg.public_prefixes,
+		skip_comments = cfg.skip_comments,
+		tabstop = tabstop,
+	})
+end
+
+---Explain why an entry gets its mode
+---Traces the policy rules, key patterns and source patterns with the same
+---options used for rendering
+---@param content string
+---@param source string|nil
+---@param entry_index number 1-based index of the entry (as in parse().entries)
+---@return ShelterExplanation
+function M.explain(content, source, entry_index)
+	local cfg = config.get()
+	local key_set, source_set = pattern_sets()
+	local explanation = native.explain(content, entry_index, {
+		modes = M.get_native_modes(),
+		default_mode = cfg.default_mode,
+		key_set = key_set,
+		source_set = source_set,
+		policy = M.get_policy(),
+		source = source,
+		directives = cfg.directives,
+		public_prefixes = cfg.public_prefixes,
+	})
+	-- Custom Lua modes are not in the native table but still apply
+	explanation.unknown_mode = not modes.exists(explanation.mode)
+	return explanation
+end
+
 ---Initialize the pattern cache and modes from config (call at setup)
 function M.init()
 	local cfg = config.get()
@@ -355,6 +690,13 @@ function M.init()
 
 	-- Setup modes with config
 	modes.setup(cfg)
+	native_modes = nil
+	hides_lengths = nil
+	uses_fingerprints = nil
+	uses_payload_secrets = nil
+	native_policy = nil
+	keyfile.reset()
+	parsed_cache:clear()
 end
 
 ---Reload pattern cache and modes (call when config changes)
@@ -365,6 +707,13 @@ function M.reload_patterns()
 	-- Reload modes with updated config
 	modes.reset()
 	modes.setup(cfg)
+	native_modes = nil
+	hides_lengths = nil
+	uses_fingerprints = nil
+	uses_payload_secrets = nil
+	native_policy = nil
+	keyfile.reset()
+	parsed_cache:clear()
 end
 
 return M
@@ -11,6 +11,11 @@ M.determine_mode = engine.determine_mode
 M.mask_value = engine.mask_value
 M.generate_masks = engine.generate_masks
 M.generate_masks_incremental = engine.generate_masks_incremental
+M.render_plan = engine.render_plan
+M.render_masked = engine.render_masked
+M.verify_overlays = engine.verify_overlays
+M.explain = engine.explain
+M.can_render_natively = engine.can_render_natively
 M.clear_caches = engine.clear_caches
 M.init = engine.init
 M.reload_patterns = engine.reload_patterns
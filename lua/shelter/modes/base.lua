@@ -21,6 +21,9 @@ M.__index = M
 ---@field line_number number Line in file
 ---@field quote_type number 0=none, 1=single, 2=double
 ---@field is_comment boolean Whether in a comment
+---@field masked_length? number Mask cells computed natively for length-hiding strategies
+---@field fingerprint? string Keyed fingerprint of the value (16 hex digits), computed natively
+---@field payload_secret? boolean Whether the value is an encoded payload holding a secret, computed natively
 ---@field config table Full plugin config
 
 ---@class ShelterModeDefinition
@@ -0,0 +1,91 @@
+---@class ShelterFingerprintMode
+---Fingerprint mode - a short mask followed by a keyed fingerprint of the value
+---Equal values show equal fingerprints, so secrets can be compared across files
+---without unmasking them
+local Base = require("shelter.modes.base")
+local text = require("shelter.utils.text")
+
+-- Lazy-loaded engine for cached mask access
+local engine = nil
+local function get_engine()
+	if not engine then
+		engine = require("shelter.masking.engine")
+	end
+	return engine
+end
+
+---@type ShelterModeDefinition
+local definition = {
+	name = "fingerprint",
+	description = "Mask the value and show a short keyed fingerprint",
+
+	schema = {
+		mask_char = {
+			type = "string",
+			default = "•",
+			description = "Character used for masking (any single character, may be double-width)",
+		},
+		fixed_length = {
+			type = "number",
+			default = 4,
+			min = 1,
+			description = "Number of mask characters before the fingerprint",
+		},
+		fingerprint_length = {
+			type = "number",
+			default = 4,
+			min = 1,
+			description = "Hex digits of the fingerprint shown (at most 16)",
+		},
+	},
+
+	default_options = {
+		mask_char = "•",
+		fixed_length = 4,
+		fingerprint_length = 4,
+	},
+
+	---@param self ShelterModeBase
+	---@param ctx ShelterModeContext
+	---@return string
+	apply = function(self, ctx)
+		local opts = self.options
+		-- Prefer the fingerprint computed during parsing
+		local fingerprint = ctx.fingerprint
+		if not fingerprint then
+			fingerprint = require("shelter.native").fingerprint(ctx.value, require("shelter.utils.keyfile").get())
+		end
+		return get_engine().get_cached_mask(opts.mask_char, opts.fixed_length)
+			.. "["
+			.. fingerprint:sub(1, opts.fingerprint_length)
+			.. "]"
+	end,
+
+	---@param options table
+	---@return boolean, string?
+	validate = function(options)
+		if options.mask_char and not text.is_mask_glyph(options.mask_char) then
+			return false, "mask_char must be a single character"
+		end
+		if options.fingerprint_length and options.fingerprint_length > 16 then
+			return false, "fingerprint_length must be <= 16"
+		end
+		return true
+	end,
+}
+
+---Create a new fingerprint mode instance
+---@param options? table<string, any>
+---@return ShelterModeBase
+local function create(options)
+	local mode = Base.new(definition)
+	if options then
+		mode:configure(options)
+	end
+	return mode
+end
+
+return {
+	definition = definition,
+	create = create,
+}
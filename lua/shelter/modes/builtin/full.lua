@@ -1,6 +1,8 @@
 ---@class ShelterFullMode
 ---Full masking mode - replaces all characters with mask character
 local Base = require("shelter.modes.base")
+local text = require("shelter.utils.text")
+local length = require("shelter.modes.length")
 
 -- Lazy-loaded engine for cached mask access
 local engine = nil
@@ -16,11 +18,11 @@ local definition = {
 	name = "full",
 	description = "Replace all characters with mask character",
 
-	schema = {
+	schema = length.extend_schema({
 		mask_char = {
 			type = "string",
 			default = "*",
-			description = "Character used for masking",
+			description = "Character used for masking (any single character, may be double-width)",
 		},
 		preserve_length = {
 			type = "boolean",
@@ -33,7 +35,7 @@ local definition = {
 			min = 1,
 			description = "Fixed output length (overrides preserve_length)",
 		},
-	},
+	}),
 
 	default_options = {
 		mask_char = "*",
@@ -47,19 +49,23 @@ local definition = {
 		-- Direct property access - options pre-resolved at config time
 		local opts = self.options
 		local mask_char = opts.mask_char
-		local length = opts.fixed_length or #ctx.value
+		local get_cached_mask = get_engine().get_cached_mask
+
+		if opts.fixed_length then
+			return get_cached_mask(mask_char, opts.fixed_length)
+		end
 
-		-- Use cached mask strings to avoid repeated string.rep()
-		return get_engine().get_cached_mask(mask_char, length)
+		-- Width per the length strategy (cached for single-width glyphs)
+		return text.fill(mask_char, length.cells(opts, ctx.value, ctx.masked_length), get_cached_mask)
 	end,
 
 	---@param options table
 	---@return boolean, string?
 	validate = function(options)
-		if options.mask_char and #options.mask_char ~= 1 then
+		if options.mask_char and not text.is_mask_glyph(options.mask_char) then
 			return false, "mask_char must be a single character"
 		end
-		return true
+		return length.validate(options)
 	end,
 }
 
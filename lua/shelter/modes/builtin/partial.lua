@@ -1,6 +1,8 @@
 ---@class ShelterPartialMode
 ---Partial masking mode - shows start/end characters, masks middle
 local Base = require("shelter.modes.base")
+local text = require("shelter.utils.text")
+local length = require("shelter.modes.length")
 
 -- Lazy-loaded engine for cached mask access
 local engine = nil
@@ -16,11 +18,11 @@ local definition = {
 	name = "partial",
 	description = "Show start and end characters, mask the middle",
 
-	schema = {
+	schema = length.extend_schema({
 		mask_char = {
 			type = "string",
 			default = "*",
-			description = "Character used for masking",
+			description = "Character used for masking (any single character, may be double-width)",
 		},
 		show_start = {
 			type = "number",
@@ -46,7 +48,7 @@ local definition = {
 			enum = { "full", "none" },
 			description = "Mode to use when value is too short for partial masking",
 		},
-	},
+	}),
 
 	default_options = {
 		mask_char = "*",
@@ -69,29 +71,32 @@ local definition = {
 		local get_cached_mask = get_engine().get_cached_mask
 
 		local value = ctx.value
-		local value_len = #value
+		local chars = text.chars(value)
+		local value_len = #chars
 
 		-- Check if value is long enough for partial masking
 		local min_length = show_start + show_end + min_mask
 		if value_len < min_length then
-			-- Use fallback mode for short values
+			-- Use fallback mode for short values, masking encoded secrets anyway
 			local fallback = opts.fallback_mode
-			if fallback == "none" then
+			if fallback == "none" and not ctx.payload_secret then
 				return value
 			end
 			-- Full mask with cached string
-			return get_cached_mask(mask_char, value_len)
+			return text.fill(mask_char, length.cells(opts, value, ctx.masked_length), get_cached_mask)
 		end
 
-		-- Partial masking with cached mask string
-		local mask_len = value_len - show_start - show_end
-		return value:sub(1, show_start) .. get_cached_mask(mask_char, mask_len) .. value:sub(-show_end)
+		-- Whole characters around a mask sized by the length strategy
+		local hidden = table.concat(chars, "", show_start + 1, value_len - show_end)
+		return table.concat(chars, "", 1, show_start)
+			.. text.fill(mask_char, length.cells(opts, hidden, ctx.masked_length), get_cached_mask)
+			.. table.concat(chars, "", value_len - show_end + 1, value_len)
 	end,
 
 	---@param options table
 	---@return boolean, string?
 	validate = function(options)
-		if options.mask_char and #options.mask_char ~= 1 then
+		if options.mask_char and not text.is_mask_glyph(options.mask_char) then
 			return false, "mask_char must be a single character"
 		end
 		if options.show_start and options.show_start < 0 then
@@ -100,7 +105,7 @@ local definition = {
 		if options.show_end and options.show_end < 0 then
 			return false, "show_end must be >= 0"
 		end
-		return true
+		return length.validate(options)
 	end,
 }
 
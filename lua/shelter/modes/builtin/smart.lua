@@ -0,0 +1,114 @@
+---@class ShelterSmartMode
+---Smart mode - leaves obviously safe values visible and fully masks the rest
+---Safe values are booleans, small integers, loopback URLs, file paths and common
+---enum values, plus any configured safe value globs; recognised secret tokens
+---and random-looking values are always masked
+local Base = require("shelter.modes.base")
+local text = require("shelter.utils.text")
+local length = require("shelter.modes.length")
+
+-- Lazy-loaded engine for native mode table and cached mask access
+local engine = nil
+local function get_engine()
+	if not engine then
+		engine = require("shelter.masking.engine")
+	end
+	return engine
+end
+
+local SAFE_SHAPES = { "boolean", "integer", "loopback", "path", "enum" }
+
+---@type ShelterModeDefinition
+local definition = {
+	name = "smart",
+	description = "Leave obviously safe values visible and mask everything else",
+
+	schema = length.extend_schema({
+		mask_char = {
+			type = "string",
+			default = "*",
+			description = "Character used for masking (any single character, may be double-width)",
+		},
+		preserve_length = {
+			type = "boolean",
+			default = true,
+			description = "Whether to preserve original value length",
+		},
+		fixed_length = {
+			type = "number",
+			default = nil,
+			min = 1,
+			description = "Fixed output length (overrides preserve_length)",
+		},
+		safe_shapes = {
+			type = "table",
+			default = SAFE_SHAPES,
+			description = "Value shapes left visible: boolean, integer, loopback, path, enum",
+		},
+		safe_values = {
+			type = "table",
+			default = {},
+			description = "Globs of values left visible (case-insensitive)",
+		},
+	}),
+
+	default_options = {
+		mask_char = "*",
+		preserve_length = true,
+		safe_shapes = SAFE_SHAPES,
+		safe_values = {},
+	},
+
+	---@param self ShelterModeBase
+	---@param ctx ShelterModeContext
+	---@return string
+	apply = function(self, ctx)
+		-- Safe value detection is native only
+		local masked = require("shelter.native").mask_value(get_engine().get_native_modes(), "smart", ctx.value)
+		if masked then
+			return masked
+		end
+
+		local opts = self.options
+		local get_cached_mask = get_engine().get_cached_mask
+		if opts.fixed_length then
+			return get_cached_mask(opts.mask_char, opts.fixed_length)
+		end
+		return text.fill(opts.mask_char, length.cells(opts, ctx.value, ctx.masked_length), get_cached_mask)
+	end,
+
+	---@param options table
+	---@return boolean, string?
+	validate = function(options)
+		if options.mask_char and not text.is_mask_glyph(options.mask_char) then
+			return false, "mask_char must be a single character"
+		end
+		for _, shape in ipairs(options.safe_shapes or {}) do
+			if not vim.tbl_contains(SAFE_SHAPES, shape) then
+				return false, "Unknown safe shape: " .. tostring(shape)
+			end
+		end
+		for _, value in ipairs(options.safe_values or {}) do
+			if type(value) ~= "string" or value:find("\n", 1, true) then
+				return false, "safe_values must be single-line strings"
+			end
+		end
+		return length.validate(options)
+	end,
+}
+
+---Create a new smart mode instance
+---@param options? table<string, any>
+---@return ShelterModeBase
+local function create(options)
+	local mode = Base.new(definition)
+	if options then
+		mode:configure(options)
+	end
+	return mode
+end
+
+return {
+	definition = definition,
+	create = create,
+}
@@ -0,0 +1,101 @@
+---@class ShelterStructuredMode
+---Structured mode - masks only the credentials of URLs, connection strings and JSON
+---Passwords, token userinfo and secret parameters (password=, token=, ...) are
+---masked while scheme, host, port and database stay readable; JSON values keep
+---keys, braces and numbers visible; other values use the fallback mode
+local Base = require("shelter.modes.base")
+local text = require("shelter.utils.text")
+local length = require("shelter.modes.length")
+
+-- Lazy-loaded engine for native mode table and cached mask access
+local engine = nil
+local function get_engine()
+	if not engine then
+		engine = require("shelter.masking.engine")
+	end
+	return engine
+end
+
+---@type ShelterModeDefinition
+local definition = {
+	name = "structured",
+	description = "Mask only the credentials of URLs, connection strings and JSON",
+
+	schema = length.extend_schema({
+		mask_char = {
+			type = "string",
+			default = "*",
+			description = "Character used for masking (any single character, may be double-width)",
+		},
+		fallback_mode = {
+			type = "string",
+			default = "full",
+			enum = { "full", "none" },
+			description = "Mode to use for values that are not URLs, connection strings or JSON",
+		},
+		json_leaves = {
+			type = "string",
+			default = "secret",
+			enum = { "secret", "strings" },
+			description = "JSON strings to mask: under secret keys or known secrets, or all",
+		},
+		secret_keys = {
+			type = "table",
+			default = nil,
+			description = "Globs of JSON key paths to mask, e.g. '*.private_key' (case-insensitive)",
+		},
+	}),
+
+	default_options = {
+		mask_char = "*",
+		fallback_mode = "full",
+		json_leaves = "secret",
+	},
+
+	---@param self ShelterModeBase
+	---@param ctx ShelterModeContext
+	---@return string
+	apply = function(self, ctx)
+		-- Credential detection is native only
+		local masked = require("shelter.native").mask_value(get_engine().get_native_modes(), "structured", ctx.value)
+		if masked then
+			return masked
+		end
+
+		local opts = self.options
+		if opts.fallback_mode == "none" and not ctx.payload_secret then
+			return ctx.value
+		end
+		return text.fill(opts.mask_char, length.cells(opts, ctx.value, ctx.masked_length), get_engine().get_cached_mask)
+	end,
+
+	---@param options table
+	---@return boolean, string?
+	validate = function(options)
+		if options.mask_char and not text.is_mask_glyph(options.mask_char) then
+			return false, "mask_char must be a single character"
+		end
+		for _, key in ipairs(options.secret_keys or {}) do
+			if type(key) ~= "string" or key:find("\n", 1, true) then
+				return false, "secret_keys must be single-line strings"
+			end
+		end
+		return length.validate(options)
+	end,
+}
+
+---Create a new structured mode instance
+---@param options? table<string, any>
+---@return ShelterModeBase
+local function create(options)
+	local mode = Base.new(definition)
+	if options then
+		mode:configure(options)
+	end
+	return mode
+end
+
+return {
+	definition = definition,
+	create = create,
+}
@@ -28,6 +28,9 @@ local builtins = {
 	full = require("shelter.modes.builtin.full"),
 	partial = require("shelter.modes.builtin.partial"),
 	none = require("shelter.modes.builtin.none"),
+	fingerprint = require("shelter.modes.builtin.fingerprint"),
+	smart = require("shelter.modes.builtin.smart"),
+	structured = require("shelter.modes.builtin.structured"),
 }
 
 ---@type table<string, ShelterModeDefinition>
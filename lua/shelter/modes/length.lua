@@ -0,0 +1,81 @@
+---@class ShelterModeLength
+---Length strategies shared by the built-in modes
+---"preserve" keeps the value width; "bucket" rounds it up to a multiple of
+---bucket_size; "keyed" uses a width derived natively from a keyed hash of the
+---value (ctx.masked_length), so the true length is never disclosed
+local M = {}
+
+local text = require("shelter.utils.text")
+local math_ceil = math.ceil
+
+---Schema entries for the length options
+M.schema = {
+	length_strategy = {
+		type = "string",
+		default = "preserve",
+		enum = { "preserve", "bucket", "keyed" },
+		description = "How the mask width is chosen: value width, rounded bucket, or keyed hash",
+	},
+	bucket_size = {
+		type = "number",
+		default = 8,
+		min = 1,
+		description = "Bucket width in cells (bucket strategy)",
+	},
+	min_length = {
+		type = "number",
+		default = 12,
+		min = 1,
+		description = "Shortest mask in cells (keyed strategy)",
+	},
+	max_length = {
+		type = "number",
+		default = 24,
+		min = 1,
+		description = "Longest mask in cells (keyed strategy)",
+	},
+}
+
+---Add the length options to a mode schema
+---@param schema table<string, ShelterOptionSchema>
+---@return table<string, ShelterOptionSchema>
+function M.extend_schema(schema)
+	for name, option in pairs(M.schema) do
+		schema[name] = option
+	end
+	return schema
+end
+
+---Validate the length options
+---@param options table
+---@return boolean, string?
+function M.validate(options)
+	if options.min_length and options.max_length and options.min_length > options.max_length then
+		return false, "min_length must be <= max_length"
+	end
+	return true
+end
+
+---Mask cells for a hidden segment
+---Without native metadata the keyed strategy uses max_length, which still hides the length
+---@param opts table Mode options
+---@param hidden string Hidden part of the value
+---@param masked_length? number Width computed natively (ShelterEntry.masked_length)
+---@return number
+function M.cells(opts, hidden, masked_length)
+	local width = text.width(hidden)
+	local strategy = opts.length_strategy
+	if width == 0 or not strategy or strategy == "preserve" then
+		return width
+	end
+	if masked_length then
+		return masked_length
+	end
+	if strategy == "bucket" then
+		local size = opts.bucket_size or 8
+		return math_ceil(width / size) * size
+	end
+	return opts.max_length or 24
+end
+
+return M
@@ -10,6 +10,29 @@ local platform = require("shelter.utils.platform")
 pcall(
 	ffi.cdef,
 	[[
+typedef struct {
+    char* alg;
+    size_t alg_len;
+    char* iss;
+    size_t iss_len;
+    char* aud;
+    size_t aud_len;
+    int64_t exp;
+    int64_t iat;
+} ShelterJwt;
+
+typedef struct {
+    char* label;
+    size_t label_len;
+    char* subject;
+    size_t subject_len;
+    char* issuer;
+    size_t issuer_len;
+    int64_t not_after;
+    size_t blocks;
+    uint8_t kind;
+} ShelterPem;
+
 typedef struct {
     char* key;
     size_t key_len;
@@ -21,16 +44,57 @@ typedef struct {
     size_t value_end;
     size_t line_number;
     size_t value_end_line;
+    uint64_t id;
+    size_t masked_length;
+    uint64_t fingerprint;
+    size_t policy_rule;
+    char* directive_mode;
+    size_t directive_mode_len;
+    size_t directive_line;
+    char* secret_type;
+    size_t secret_type_len;
+    double entropy;
+    double randomness;
+    char* payload;
+    size_t payload_len;
+    ShelterJwt* jwt;
+    ShelterPem* pem;
     uint8_t quote_type;
     uint8_t is_exported;
     uint8_t is_comment;
+    uint8_t secret_confidence;
+    uint8_t charset;
+    uint8_t looks_random;
+    uint8_t value_type;
+    uint8_t payload_secret;
+    uint8_t exposure;
 } ShelterEntry;
 
+typedef struct {
+    char* code;
+    char* message;
+    size_t line_number;
+    size_t start;
+    size_t end;
+    size_t entry_index;
+    uint8_t severity;
+} ShelterDiagnostic;
+
+typedef struct {
+    size_t line_number;
+    size_t start;
+    size_t end;
+} ShelterLineSpan;
+
 typedef struct {
     ShelterEntry* entries;
     size_t count;
     size_t* line_offsets;
     size_t line_count;
+    ShelterDiagnostic* diagnostics;
+    size_t diagnostic_count;
+    ShelterLineSpan* comment_keys;
+    size_t comment_key_count;
     char* error;
 } ShelterResult;
 
@@ -39,10 +103,295 @@ typedef struct {
     uint8_t track_positions;
 } ShelterParseOptions;
 
+typedef struct {
+    uint64_t id;
+    size_t old_index;
+    size_t new_index;
+    uint8_t kind;
+} ShelterChange;
+
+typedef struct {
+    ShelterChange* changes;
+    size_t count;
+    char* error;
+} ShelterDiff;
+
+typedef struct {
+    const char* mask_char;
+    size_t mask_char_len;
+    size_t fixed_length;
+    size_t show_start;
+    size_t show_end;
+    size_t min_mask;
+    size_t bucket_size;
+    size_t min_length;
+    size_t max_length;
+    size_t fingerprint_length;
+    const char* safe_values;
+    size_t safe_values_len;
+    const char* secret_keys;
+    size_t secret_keys_len;
+    uint8_t kind;
+    uint8_t preserve_length;
+    uint8_t fallback_mode;
+    uint8_t length_strategy;
+    uint8_t safe_shapes;
+    uint8_t json_leaves;
+} ShelterModeOptions;
+
+typedef struct ShelterModeTable ShelterModeTable;
+typedef struct ShelterPatternSet ShelterPatternSet;
+typedef struct ShelterPolicy ShelterPolicy;
+
+typedef struct {
+    const char* pattern;
+    size_t pattern_len;
+    const char* mode;
+    size_t mode_len;
+} ShelterPatternRule;
+
+typedef struct {
+    uint8_t case_insensitive;
+    uint8_t paths;
+} ShelterPatternOptions;
+
+typedef struct {
+    const char* key_glob;
+    size_t key_glob_len;
+    const char* key_regex;
+    size_t key_regex_len;
+    const char* value_regex;
+    size_t value_regex_len;
+    const char* source_glob;
+    size_t source_glob_len;
+    const char* secret_type;
+    size_t secret_type_len;
+    const char* mode;
+    size_t mode_len;
+    double min_entropy;
+    int32_t priority;
+    int32_t highlight_id;
+    uint8_t quote_types;
+    uint8_t is_comment;
+    uint8_t is_exported;
+    uint8_t case_insensitive;
+    uint8_t min_confidence;
+    uint8_t looks_random;
+} ShelterPolicyRule;
+
+typedef struct {
+    uint64_t id;
+    size_t leading;
+    size_t trailing;
+    uint8_t kind;
+} ShelterReveal;
+
+typedef struct {
+    const ShelterModeTable* modes;
+    const char* default_mode;
+    size_t default_mode_len;
+    const ShelterPatternRule* key_patterns;
+    size_t key_pattern_count;
+    const ShelterPatternRule* source_patterns;
+    size_t source_pattern_count;
+    const ShelterPatternSet* key_set;
+    const ShelterPatternSet* source_set;
+    const ShelterPolicy* policy;
+    const char* source;
+    size_t source_len;
+    const char* public_prefixes;
+    size_t public_prefixes_len;
+    size_t tabstop;
+    const ShelterReveal* reveals;
+    size_t reveal_count;
+    int32_t highlight_id;
+    uint8_t skip_comments;
+    uint8_t ignore_directives;
+} ShelterRenderConfig;
+
+typedef struct {
+    const ShelterRenderConfig* render;
+    const char* fingerprint_key;
+    size_t fingerprint_key_len;
+    int64_t now;
+    size_t expiry_window_days;
+    size_t min_secret_length;
+    const char* public_prefixes;
+    size_t public_prefixes_len;
+    const char* mode_names;
+    size_t mode_names_len;
+    uint8_t fingerprints;
+    uint8_t classify;
+    uint8_t entropy;
+    uint8_t value_types;
+    uint8_t payloads;
+    uint8_t jwts;
+    uint8_t pems;
+    uint8_t weak_values;
+    uint8_t exposure;
+} ShelterAnalysisOptions;
+
+typedef struct {
+    char* text;
+    size_t text_len;
+    size_t row;
+    size_t col;
+    size_t end_col;
+    size_t entry_index;
+    int32_t hl_id;
+    uint8_t is_continuation;
+} ShelterOverlay;
+
+typedef struct {
+    ShelterOverlay* overlays;
+    size_t count;
+    char* error;
+} ShelterRenderPlan;
+
+typedef struct {
+    size_t original_start;
+    size_t original_end;
+    size_t masked_start;
+    size_t masked_end;
+    size_t entry_index;
+} ShelterOffsetSpan;
+
+typedef struct {
+    char* text;
+    size_t text_len;
+    ShelterOffsetSpan* spans;
+    size_t span_count;
+    char* error;
+} ShelterMaskedDocument;
+
+typedef struct {
+    size_t row;
+    size_t col;
+    size_t end_col;
+    size_t entry_index;
+} ShelterLeak;
+
+typedef struct {
+    ShelterLeak* leaks;
+    size_t count;
+    char* error;
+} ShelterLeakReport;
+
+typedef struct {
+    size_t start;
+    size_t end;
+    char* component;
+    size_t component_len;
+} ShelterSecretSpan;
+
+typedef struct {
+    ShelterSecretSpan* spans;
+    size_t count;
+    uint8_t kind;
+    char* error;
+} ShelterSecretSpans;
+
+typedef struct {
+    char* summary;
+    size_t summary_len;
+    char* secret;
+    size_t secret_len;
+    uint8_t* decoded;
+    size_t decoded_len;
+    char* error;
+    uint8_t encoding;
+    uint8_t layers;
+    uint8_t payload_type;
+} ShelterPayload;
+
+typedef struct {
+    uint8_t report_prefix;
+} ShelterVerifyPolicy;
+
+typedef struct {
+    size_t entry_index;
+    size_t prefix_length;
+    uint8_t found;
+    uint8_t matched;
+    uint8_t prefix_reported;
+    char* error;
+} ShelterVerifyResult;
+
+typedef struct {
+    char* rule;
+    char* mode;
+    char* detail;
+    size_t rule_index;
+    int64_t rank;
+    uint8_t stage;
+    uint8_t matched;
+    uint8_t chosen;
+} ShelterTraceStep;
+
+typedef struct {
+    ShelterTraceStep* steps;
+    size_t count;
+    char* mode;
+    char* secret_type;
+    double entropy;
+    double randomness;
+    uint8_t stage;
+    uint8_t unknown_mode;
+    uint8_t secret_confidence;
+    uint8_t looks_random;
+    char* error;
+} ShelterExplanation;
+
 // Parsing functions
 ShelterResult* shelter_parse(const char* input, size_t input_len, ShelterParseOptions options);
+ShelterResult* shelter_parse_with(const char* input, size_t input_len, ShelterParseOptions options, const ShelterAnalysisOptions* analysis);
 void shelter_free_result(ShelterResult* result);
 
+// Diffing functions
+ShelterDiff* shelter_diff_results(const ShelterResult* old, const ShelterResult* new);
+void shelter_free_diff(ShelterDiff* diff);
+
+// Masking functions
+ShelterModeTable* shelter_mode_table_new(void);
+uint8_t shelter_mode_table_set(ShelterModeTable* table, const char* name, size_t name_len, const ShelterModeOptions* options);
+void shelter_mode_table_free(ShelterModeTable* table);
+void shelter_mode_table_set_key(ShelterModeTable* table, const char* key, size_t key_len);
+uint64_t shelter_fingerprint(const char* value, size_t value_len, const char* key, size_t key_len);
+char* shelter_mask_value(const ShelterModeTable* table, const char* mode, size_t mode_len, const char* value, size_t value_len);
+char* shelter_mask_value_revealed(const ShelterModeTable* table, const char* mode, size_t mode_len, const char* value, size_t value_len, const ShelterReveal* reveal);
+void shelter_free_string(char* s);
+ShelterSecretSpans* shelter_secret_spans(const char* value, size_t value_len);
+void shelter_free_secret_spans(ShelterSecretSpans* result);
+ShelterPayload* shelter_inspect_payload(const char* value, size_t value_len, uint8_t include_decoded);
+void shelter_free_payload(ShelterPayload* result);
+
+// Pattern set functions
+ShelterPatternSet* shelter_pattern_set_new(const ShelterPatternRule* rules, size_t count, const ShelterPatternOptions* options);
+size_t shelter_pattern_set_match(const ShelterPatternSet* set, const char* text, size_t text_len);
+char* shelter_pattern_validate(const char* pattern, size_t pattern_len, const ShelterPatternOptions* options);
+void shelter_pattern_set_free(ShelterPatternSet* set);
+
+// Policy functions
+ShelterPolicy* shelter_policy_new(const ShelterPolicyRule* rules, size_t count);
+char* shelter_policy_validate(const ShelterPolicyRule* rule);
+void shelter_policy_free(ShelterPolicy* policy);
+
+// Rendering functions
+ShelterRenderPlan* shelter_render_plan(const char* input, size_t input_len, const ShelterRenderConfig* config);
+void shelter_free_render_plan(ShelterRenderPlan* plan);
+ShelterMaskedDocument* shelter_render_masked(const char* input, size_t input_len, const ShelterRenderConfig* config);
+void shelter_free_masked_document(ShelterMaskedDocument* doc);
+
+// Verification functions
+ShelterLeakReport* shelter_verify_overlays(const char* input, size_t input_len, const ShelterOverlay* overlays, size_t overlay_count, const ShelterRenderConfig* config, size_t tabstop);
+void shelter_free_leak_report(ShelterLeakReport* report);
+ShelterVerifyResult* shelter_verify_value(const char* input, size_t input_len, const char* key, size_t key_len, const char* candidate, size_t candidate_len, const ShelterVerifyPolicy* policy);
+void shelter_free_verify_result(ShelterVerifyResult* result);
+
+// Explain functions
+ShelterExplanation* shelter_explain(const char* input, size_t input_len, size_t entry_index, const ShelterRenderConfig* config);
+void shelter_free_explanation(ShelterExplanation* explanation);
+
 // Utility functions
 const char* shelter_version(void);
 ]]
@@ -51,6 +400,52 @@ const char* shelter_version(void);
 -- Library handle
 local lib = nil
 
+-- Fast locals
+local bit_tohex = bit.tohex
+
+-- usize::MAX, returned natively for "no index"
+local NO_INDEX = ffi.cast("size_t", -1)
+
+-- Classifier confidence levels (match ShelterConfidence; 0 = unclassified)
+local CONFIDENCE_LEVELS = { "low", "medium", "high" }
+local CONFIDENCE_VALUES = { low = 1, medium = 2, high = 3 }
+
+-- Value charsets (match ShelterCharset; 0 = empty or not analysed)
+local CHARSETS = { "digits", "hex", "alphanumeric", "base64", "other" }
+
+-- Inferred value types (match ShelterValueType; 0 = empty or not analysed)
+local VALUE_TYPES = {
+	"boolean",
+	"integer",
+	"float",
+	"url",
+	"connection_string",
+	"path",
+	"email",
+	"json",
+	"base64",
+	"pem",
+	"uuid",
+	"duration",
+	"opaque",
+}
+
+-- Whether a key is bundled into client code (match ShelterExposure)
+local EXPOSURES = {
+	[0] = "private",
+	"public",
+	"leaked",
+}
+
+-- What a PEM block holds (match ShelterPemKind)
+local PEM_KINDS = {
+	[0] = "other",
+	"private_key",
+	"certificate",
+	"certificate_request",
+	"public_key",
+}
+
 -- Find and load the native library
 local function find_library()
 	-- Get the plugin directory
@@ -114,6 +509,7 @@ function M.version()
 end
 
 ---@class ShelterParsedEntry
+---@field id string Stable entry identity (16 hex digits), derived from key and occurrence
 ---@field key string
 ---@field value string
 ---@field key_start number
@@ -122,17 +518,178 @@ end
 ---@field value_end number
 ---@field line_number number
 ---@field value_end_line number
+---@field masked_length number Mask cells for the value (its display width unless opts.render is given)
+---@field fingerprint? string Keyed fingerprint (16 hex digits), only with opts.fingerprint_key
+---@field policy_rule? number 1-based index of the policy rule that chose the mode (opts.render.policy)
+---@field directive_mode? string Mode set by an inline `# shelter:` directive
+---@field directive_line? number Line of that directive
+---@field secret_type? string Type id of a recognised credential format, only with opts.classify
+---@field secret_confidence? "low"|"medium"|"high" Confidence of secret_type
+---@field entropy? number Shannon entropy of the value (bits per character), only with opts.entropy
+---@field randomness? number Entropy relative to the value's charset and length, 0 to 1
+---@field charset? "digits"|"hex"|"alphanumeric"|"base64"|"other" Smallest charset of the value
+---@field looks_random? boolean Whether the value looks like a random token
+---@field value_type? string Inferred type, e.g. "url" or "connection_string", only with opts.value_types
+---@field payload? string What an encoded value carries, e.g. "base64 → JSON with private_key", only with opts.payloads
+---@field payload_secret? boolean Whether the encoded payload holds a secret
+---@field jwt? ShelterJwtClaims Claims of a JWT value, only with opts.jwts
+---@field pem? ShelterPemInfo PEM blocks of the value, only with opts.pems
+---@field exposure? "private"|"public"|"leaked" Whether the key has a public prefix and holds a secret, only with opts.exposure
 ---@field quote_type number
 ---@field is_exported boolean
 ---@field is_comment boolean
 
+---@class ShelterJwtClaims
+---@field alg? string Signing algorithm, e.g. "RS256"
+---@field iss? string Issuer
+---@field aud? string Audiences, joined by ", "
+---@field exp? number Expiry in Unix seconds
+---@field iat? number Issue time in Unix seconds
+
+---@class ShelterPemInfo
+---@field kind "private_key"|"certificate"|"certificate_request"|"public_key"|"other" What the value holds: a private key if any, else its first certificate or request
+---@field label string Block label, e.g. "EC PRIVATE KEY"
+---@field subject? string Subject of a certificate or request, e.g. "O=Example, CN=auth.example.com"
+---@field issuer? string Issuer of a certificate
+---@field not_after? number Certificate expiry in Unix seconds
+---@field blocks number Number of PEM blocks in the value (a chain has several)
+
+---@class ShelterDiagnostic
+---@field code string Stable code, e.g. "unknown-directive"
+---@field message string
+---@field severity number vim.diagnostic.severity value
+---@field line_number number 1-based line
+---@field start_offset number 0-based byte offset where the reported text starts
+---@field end_offset number Byte offset where it ends (exclusive)
+---@field entry_index? number 1-based index of the affected entry
+
+---@class ShelterLineSpan
+---@field line_number number 1-based line
+---@field start_offset number 0-based byte offset where the span starts
+---@field end_offset number Byte offset where it ends (exclusive)
+
 ---@class ShelterParseResult
 ---@field entries ShelterParsedEntry[]
+---@field diagnostics ShelterDiagnostic[] Problems found while parsing, in line order
+---@field comment_keys ShelterLineSpan[] Base64 lines of private keys pasted into comments, only with opts.pems
 ---@field line_offsets number[] Byte offset where each line starts (1-indexed, line_offsets[1] = offset of line 1)
+---@field handle ffi.cdata* Native result (freed on GC), used for diffing
+
+-- Defined with the rendering functions below
+local render_config
+
+---@class ShelterNativeParseOptions
+---@field include_comments? boolean
+---@field track_positions? boolean
+---@field render? ShelterRenderOptions Compute masked_length with these modes and patterns
+---@field fingerprint_key? string Compute entry fingerprints under this key
+---@field classify? boolean Classify values against known credential formats
+---@field entropy? boolean Score how random each value looks
+---@field value_types? boolean Infer each value's type
+---@field payloads? boolean Decode base64 values and report what they carry
+---@field jwts? boolean Decode JWT claims (signatures are not verified) and report expired tokens
+---@field pems? boolean Describe PEM keys, certificates and requests, report expired and expiring certificates and find private keys in comments
+---@field expiry_window_days? number Days before expiry from which certificates are reported (default: 30)
+---@field now? number Current time in Unix seconds for expiry checks (default: system clock)
+---@field weak_values? boolean Report placeholders, default credentials and short secrets
+---@field min_secret_length? number Length below which secret values are reported (default: 12)
+---@field exposure? boolean Find keys bundled into client code and report secrets they hold
+---@field public_prefixes? string[] Key prefixes bundled into client code (default: NEXT_PUBLIC_, VITE_, REACT_APP_, EXPO_PUBLIC_, PUBLIC_)
+---@field mode_names? string[] Modes directives may set; directives naming others are reported and ignored (default: any)
+
+---Convert native JWT claims
+---@param jwt ffi.cdata* ShelterJwt*
+---@return ShelterJwtClaims
+local function jwt_claims(jwt)
+	return {
+		alg = jwt.alg ~= nil and ffi.string(jwt.alg, jwt.alg_len) or nil,
+		iss = jwt.iss ~= nil and ffi.string(jwt.iss, jwt.iss_len) or nil,
+		aud = jwt.aud ~= nil and ffi.string(jwt.aud, jwt.aud_len) or nil,
+		exp = jwt.exp ~= 0 and tonumber(jwt.exp) or nil,
+		iat = jwt.iat ~= 0 and tonumber(jwt.iat) or nil,
+	}
+end
+
+---Convert native PEM fields
+---@param pem ffi.cdata* ShelterPem*
+---@return ShelterPemInfo
+local function pem_info(pem)
+	return {
+		kind = PEM_KINDS[pem.kind],
+		label = ffi.string(pem.label, pem.label_len),
+		subject = pem.subject ~= nil and ffi.string(pem.subject, pem.subject_len) or nil,
+		issuer = pem.issuer ~= nil and ffi.string(pem.issuer, pem.issuer_len) or nil,
+		not_after = pem.not_after ~= 0 and tonumber(pem.not_after) or nil,
+		blocks = tonumber(pem.blocks),
+	}
+end
+
+---Describe a span of seconds, e.g. "3 days"
+---@param seconds number
+---@return string
+local function describe_span(seconds)
+	local count, unit = seconds, "second"
+	if seconds >= 86400 then
+		count, unit = math.floor(seconds / 86400), "day"
+	elseif seconds >= 3600 then
+		count, unit = math.floor(seconds / 3600), "hour"
+	elseif seconds >= 60 then
+		count, unit = math.floor(seconds / 60), "minute"
+	end
+	return string.format("%d %s%s", count, unit, count == 1 and "" or "s")
+end
+
+---Describe JWT claims for hovers and `:Shelter info`, e.g.
+---"JWT, expires in 3 days, issuer auth.example.com"
+---@param jwt ShelterJwtClaims
+---@param now? number Current time in Unix seconds (default: os.time())
+---@return string
+function M.describe_jwt(jwt, now)
+	now = now or os.time()
+	local parts = { "JWT" }
+	if jwt.exp then
+		local delta = jwt.exp - now
+		parts[#parts + 1] = delta > 0 and ("expires in " .. describe_span(delta))
+			or ("expired " .. describe_span(-delta) .. " ago")
+	end
+	if jwt.iss then
+		parts[#parts + 1] = "issuer " .. jwt.iss:gsub("^%a[%w+.-]*://", ""):gsub("/$", "")
+	end
+	return table.concat(parts, ", ")
+end
+
+---Describe PEM fields for hovers and `:Shelter info`, e.g.
+---"certificate for auth.example.com, expires in 12 days" or "EC private key"
+---@param pem ShelterPemInfo
+---@param now? number Current time in Unix seconds (default: os.time())
+---@return string
+function M.describe_pem(pem, now)
+	now = now or os.time()
+	if pem.kind == "private_key" then
+		return (pem.label:gsub(" PRIVATE KEY", " private key"):gsub("^PRIVATE KEY", "private key"))
+	end
+	if pem.kind ~= "certificate" and pem.kind ~= "certificate_request" then
+		return "PEM " .. pem.label
+	end
+	local parts = { pem.kind == "certificate" and "certificate" or "certificate request" }
+	local name = pem.subject and (pem.subject:match("CN=([^,]+)") or pem.subject)
+	if name then
+		parts[1] = parts[1] .. " for " .. name
+	end
+	if pem.not_after then
+		local delta = pem.not_after - now
+		parts[#parts + 1] = delta > 0 and ("expires in " .. describe_span(delta))
+			or ("expired " .. describe_span(-delta) .. " ago")
+	end
+	if pem.blocks > 1 then
+		parts[#parts + 1] = string.format("%d blocks", pem.blocks)
+	end
+	return table.concat(parts, ", ")
+end
 
 ---Parse EDF content
 ---@param content string The content to parse
----@param opts? {include_comments?: boolean, track_positions?: boolean}
+---@param opts? ShelterNativeParseOptions
 ---@return ShelterParseResult
 function M.parse(content, opts)
 	local l = ensure_lib()
@@ -143,7 +700,53 @@ function M.parse(content, opts)
 		track_positions = opts.track_positions ~= false and 1 or 0,
 	})
 
-	local result = l.shelter_parse(content, #content, parse_opts)
+	local fingerprint_key = opts.fingerprint_key
+	local result
+	if
+		opts.render
+		or fingerprint_key
+		or opts.classify
+		or opts.entropy
+		or opts.value_types
+		or opts.payloads
+		or opts.jwts
+		or opts.pems
+		or opts.weak_values
+		or opts.exposure
+		or opts.mode_names
+	then
+		local config, anchors
+		if opts.render then
+			config, anchors = render_config(opts.render)
+		end
+		local public_prefixes = opts.public_prefixes and table.concat(opts.public_prefixes, "\n")
+		local mode_names = opts.mode_names and table.concat(opts.mode_names, "\n")
+		local analysis = ffi.new("ShelterAnalysisOptions", {
+			render = config,
+			fingerprint_key = fingerprint_key,
+			fingerprint_key_len = fingerprint_key and #fingerprint_key or 0,
+			fingerprints = fingerprint_key and 1 or 0,
+			classify = opts.classify and 1 or 0,
+			entropy = opts.entropy and 1 or 0,
+			value_types = opts.value_types and 1 or 0,
+			payloads = opts.payloads and 1 or 0,
+			jwts = opts.jwts and 1 or 0,
+			pems = opts.pems and 1 or 0,
+			expiry_window_days = opts.expiry_window_days or 0,
+			weak_values = opts.weak_values and 1 or 0,
+			min_secret_length = opts.min_secret_length or 0,
+			exposure = opts.exposure and 1 or 0,
+			public_prefixes = public_prefixes,
+			public_prefixes_len = public_prefixes and #public_prefixes or 0,
+			mode_names = mode_names,
+			mode_names_len = mode_names and #mode_names or 0,
+			now = opts.now or 0,
+		})
+		result = l.shelter_parse_with(content, #content, parse_opts, analysis)
+		local _ = { anchors, public_prefixes, mode_names }
+	else
+		result = l.shelter_parse(content, #content, parse_opts)
+	end
 
 	-- Check for errors
 	if result.error ~= nil then
@@ -152,12 +755,16 @@ function M.parse(content, opts)
 		error("Parse error: " .. err_msg)
 	end
 
+	-- Keep the native result alive for diffing; freed when the handle is collected
+	result = ffi.gc(result, l.shelter_free_result)
+
 	-- Convert entries to Lua tables
 	local entries = {}
 	local entry_count = tonumber(result.count)
 	for i = 0, entry_count - 1 do
 		local entry = result.entries[i]
 		entries[i + 1] = {
+			id = bit_tohex(entry.id, 16),
 			key = ffi.string(entry.key, entry.key_len),
 			value = ffi.string(entry.value, entry.value_len),
 			key_start = tonumber(entry.key_start),
@@ -166,10 +773,31 @@ function M.parse(content, opts)
 			value_end = tonumber(entry.value_end),
 			line_number = tonumber(entry.line_number),
 			value_end_line = tonumber(entry.value_end_line),
+			masked_length = tonumber(entry.masked_length),
+			fingerprint = fingerprint_key and bit_tohex(entry.fingerprint, 16) or nil,
+			policy_rule = entry.policy_rule ~= NO_INDEX and tonumber(entry.policy_rule) + 1 or nil,
+			directive_mode = entry.directive_mode ~= nil and ffi.string(entry.directive_mode, entry.directive_mode_len)
+				or nil,
+			directive_line = entry.directive_line ~= 0 and tonumber(entry.directive_line) or nil,
+			secret_type = entry.secret_type ~= nil and ffi.string(entry.secret_type, entry.secret_type_len) or nil,
+			secret_confidence = CONFIDENCE_LEVELS[entry.secret_confidence],
+			value_type = VALUE_TYPES[entry.value_type],
+			payload = entry.payload ~= nil and ffi.string(entry.payload, entry.payload_len) or nil,
+			payload_secret = entry.payload_secret ~= 0 or nil,
+			jwt = entry.jwt ~= nil and jwt_claims(entry.jwt) or nil,
+			pem = entry.pem ~= nil and pem_info(entry.pem) or nil,
+			exposure = opts.exposure and EXPOSURES[entry.exposure] or nil,
 			quote_type = tonumber(entry.quote_type),
 			is_exported = entry.is_exported ~= 0,
 			is_comment = entry.is_comment ~= 0,
 		}
+		if opts.entropy then
+			local parsed = entries[i + 1]
+			parsed.entropy = tonumber(entry.entropy)
+			parsed.randomness = tonumber(entry.randomness)
+			parsed.charset = CHARSETS[entry.charset]
+			parsed.looks_random = entry.looks_random ~= 0
+		end
 	end
 
 	-- Extract line offsets (pre-computed in Rust)
@@ -182,12 +810,840 @@ function M.parse(content, opts)
 		end
 	end
 
-	l.shelter_free_result(result)
+	local diagnostics = {}
+	for i = 0, tonumber(result.diagnostic_count) - 1 do
+		local diagnostic = result.diagnostics[i]
+		diagnostics[i + 1] = {
+			code = ffi.string(diagnostic.code),
+			message = ffi.string(diagnostic.message),
+			severity = tonumber(diagnostic.severity),
+			line_number = tonumber(diagnostic.line_number),
+			start_offset = tonumber(diagnostic.start),
+			end_offset = tonumber(diagnostic["end"]),
+			entry_index = diagnostic.entry_index ~= NO_INDEX and tonumber(diagnostic.entry_index) + 1 or nil,
+		}
+	end
+
+	local comment_keys = {}
+	for i = 0, tonumber(result.comment_key_count) - 1 do
+		local span = result.comment_keys[i]
+		comment_keys[i + 1] = {
+			line_number = tonumber(span.line_number),
+			start_offset = tonumber(span.start),
+			end_offset = tonumber(span["end"]),
+		}
+	end
 
 	return {
 		entries = entries,
+		diagnostics = diagnostics,
+		comment_keys = comment_keys,
 		line_offsets = line_offsets,
+		handle = result,
+	}
+end
+
+---Change kinds reported by diff (match ShelterChangeKind)
+M.CHANGE_ADDED = 0
+M.CHANGE_REMOVED = 1
+M.CHANGE_MOVED = 2
+M.CHANGE_VALUE_CHANGED = 3
+
+---@class ShelterEntryChange
+---@field id string Stable entry identity
+---@field kind number One of M.CHANGE_*
+---@field old_index number|nil 1-based index into old.entries (nil for added)
+---@field new_index number|nil 1-based index into new.entries (nil for removed)
+
+---Diff two parse results by stable entry identity
+---Unchanged entries are omitted; removed entries come first
+---@param old ShelterParseResult
+---@param new ShelterParseResult
+---@return ShelterEntryChange[]
+function M.diff(old, new)
+	local l = ensure_lib()
+
+	local diff = l.shelter_diff_results(old.handle, new.handle)
+	if diff.error ~= nil then
+		local err_msg = ffi.string(diff.error)
+		l.shelter_free_diff(diff)
+		error("Diff error: " .. err_msg)
+	end
+
+	local changes = {}
+	for i = 0, tonumber(diff.count) - 1 do
+		local change = diff.changes[i]
+		local kind = tonumber(change.kind)
+		changes[i + 1] = {
+			id = bit_tohex(change.id, 16),
+			kind = kind,
+			old_index = kind ~= M.CHANGE_ADDED and tonumber(change.old_index) + 1 or nil,
+			new_index = kind ~= M.CHANGE_REMOVED and tonumber(change.new_index) + 1 or nil,
+		}
+	end
+
+	l.shelter_free_diff(diff)
+	return changes
+end
+
+-- Mode kinds understood by the native mode table (match ShelterModeKind)
+local MODE_KINDS = { full = 0, partial = 1, none = 2, fingerprint = 3, smart = 4, structured = 5 }
+
+-- Shapes the smart mode leaves visible (bit positions match ShelterSafeShape)
+local SAFE_SHAPES = { boolean = 0, integer = 1, loopback = 2, path = 3, enum = 4 }
+
+-- Length strategies (match ShelterLengthStrategy)
+local LENGTH_STRATEGIES = { preserve = 0, bucket = 1, keyed = 2 }
+
+-- JSON leaves the structured mode masks (match ShelterJsonLeaves)
+local JSON_LEAVES = { secret = 0, strings = 1 }
+
+---@class ShelterNativeModeOptions
+---@field kind? "full"|"partial"|"none"|"fingerprint"|"smart"|"structured" Masking behaviour (defaults to the mode name)
+---@field mask_char? string
+---@field preserve_length? boolean
+---@field fixed_length? number
+---@field show_start? number
+---@field show_end? number
+---@field min_mask? number
+---@field fallback_mode? "full"|"none"
+---@field length_strategy? "preserve"|"bucket"|"keyed"
+---@field bucket_size? number
+---@field min_length? number
+---@field max_length? number
+---@field fingerprint_length? number
+---@field safe_shapes? ("boolean"|"integer"|"loopback"|"path"|"enum")[] Shapes the smart mode leaves visible (default: all)
+---@field safe_values? string[] Globs of values the smart mode leaves visible (case-insensitive)
+---@field secret_keys? string[] Globs of JSON key paths the structured mode masks (default: secret key names)
+---@field json_leaves? "secret"|"strings" JSON leaves the structured mode masks
+
+---Build ShelterModeOptions for a mode
+---The options point into `mask_char` and the returned strings, so the caller must keep both
+---referenced while they are used
+---@param name string
+---@param opts ShelterNativeModeOptions
+---@param mask_char? string nil for the mode's default
+---@return ffi.cdata*|nil options
+---@return string[]|nil strings
+local function mode_options(name, opts, mask_char)
+	local kind = MODE_KINDS[opts.kind or name]
+	if not kind then
+		return nil
+	end
+
+	local safe_shapes = 0
+	for _, shape in ipairs(opts.safe_shapes or vim.tbl_keys(SAFE_SHAPES)) do
+		-- Unknown shapes are passed through so the native validator rejects them
+		safe_shapes = bit.bor(safe_shapes, SAFE_SHAPES[shape] and bit.lshift(1, SAFE_SHAPES[shape]) or 255)
+	end
+	local safe_values = opts.safe_values and table.concat(opts.safe_values, "\n") or nil
+	local secret_keys = opts.secret_keys and table.concat(opts.secret_keys, "\n") or nil
+
+	return ffi.new("ShelterModeOptions", {
+		mask_char = mask_char,
+		mask_char_len = mask_char and #mask_char or 0,
+		fixed_length = opts.fixed_length or 0,
+		show_start = opts.show_start or 3,
+		show_end = opts.show_end or 3,
+		min_mask = opts.min_mask or 3,
+		bucket_size = opts.bucket_size or 8,
+		min_length = opts.min_length or 12,
+		max_length = opts.max_length or 24,
+		fingerprint_length = opts.fingerprint_length or 4,
+		kind = kind,
+		preserve_length = opts.preserve_length ~= false and 1 or 0,
+		fallback_mode = MODE_KINDS[opts.fallback_mode or "full"] or 0,
+		-- Unknown strategies are passed through so the native validator rejects them
+		length_strategy = LENGTH_STRATEGIES[opts.length_strategy or "preserve"] or 255,
+		safe_values = safe_values,
+		safe_values_len = safe_values and #safe_values or 0,
+		secret_keys = secret_keys,
+		secret_keys_len = secret_keys and #secret_keys or 0,
+		safe_shapes = safe_shapes,
+		-- Unknown leaf sets are passed through so the native validator rejects them
+		json_leaves = JSON_LEAVES[opts.json_leaves or "secret"] or 255,
+	}),
+		{ safe_values, secret_keys }
+end
+
+---Create a native mode table (freed on GC)
+---Starts with the built-in modes; entries of `modes` add or replace modes.
+---Modes with a Lua `apply` function or an unknown kind cannot run natively and are skipped.
+---@param modes? table<string, ShelterNativeModeOptions>
+---@param key? string Key for keyed mask lengths and fingerprints
+---@return ffi.cdata* table
+function M.new_mode_table(modes, key)
+	local l = ensure_lib()
+	local mode_table = ffi.gc(l.shelter_mode_table_new(), l.shelter_mode_table_free)
+	if key then
+		l.shelter_mode_table_set_key(mode_table, key, #key)
+	end
+
+	for name, opts in pairs(modes or {}) do
+		if type(opts) == "table" and not opts.apply then
+			local mask_char = opts.mask_char
+			local options, strings = mode_options(name, opts, mask_char)
+			if options and l.shelter_mode_table_set(mode_table, name, #name, options) == 0 then
+				error(string.format("shelter.nvim: Invalid native options for mode '%s'", name))
+			end
+		end
+	end
+
+	return mode_table
+end
+
+-- Reveal kinds (match ShelterRevealKind)
+local REVEAL_KINDS = { hidden = 0, edges = 1, prefix = 2, full = 3 }
+
+---@class ShelterRevealLevel
+---@field kind "hidden"|"edges"|"prefix"|"full"
+---@field leading? number Characters shown at the start (edges)
+---@field trailing? number Characters shown at the end (edges)
+
+---Convert a hex entry id (as in ShelterParsedEntry.id) to uint64
+---@param hex string
+---@return ffi.cdata*
+local function id_from_hex(hex)
+	local id = ffi.new("uint64_t", 0)
+	for i = 1, #hex do
+		id = id * 16 + tonumber(hex:sub(i, i), 16)
+	end
+	return id
+end
+
+---Fill a ShelterReveal from a reveal level
+---@param reveal ffi.cdata* ShelterReveal
+---@param level ShelterRevealLevel
+local function fill_reveal(reveal, level)
+	reveal.leading = level.leading or 0
+	reveal.trailing = level.trailing or 0
+	-- Unknown kinds are passed through so the native validator rejects them
+	reveal.kind = REVEAL_KINDS[level.kind] or 255
+end
+
+---Mask a value with a mode from a native mode table
+---@param mode_table ffi.cdata* Table from new_mode_table
+---@param mode string Mode name
+---@param value string Value to mask
+---@param reveal? ShelterRevealLevel Part of the value to show
+---@return string|nil masked nil if the mode is not in the table or the masked value holds a NUL byte
+function M.mask_value(mode_table, mode, value, reveal)
+	local l = ensure_lib()
+	local masked
+	if reveal then
+		local native_reveal = ffi.new("ShelterReveal")
+		fill_reveal(native_reveal, reveal)
+		masked = l.shelter_mask_value_revealed(mode_table, mode, #mode, value, #value, native_reveal)
+	else
+		masked = l.shelter_mask_value(mode_table, mode, #mode, value, #value)
+	end
+	if masked == nil then
+		return nil
+	end
+	local out = ffi.string(masked)
+	l.shelter_free_string(masked)
+	return out
+end
+
+---@class ShelterSecretSpan
+---@field start_offset number 0-based byte offset within the value where the credential starts
+---@field end_offset number Byte offset where it ends (exclusive)
+---@field component string What the credential is: "password", "userinfo", the parameter name or a JSON key path
+
+---Find the credentials of a URL, connection string or JSON value
+---These are the parts the structured mode masks with its default options
+---@param value string
+---@return "url"|"connection_string"|"json"|nil kind nil when the value is not structured
+---@return ShelterSecretSpan[] spans
+function M.secret_spans(value)
+	local l = ensure_lib()
+	local result = l.shelter_secret_spans(value, #value)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_secret_spans(result)
+		error("shelter.nvim: " .. err_msg)
+	end
+
+	local spans = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local span = result.spans[i]
+		spans[i + 1] = {
+			start_offset = tonumber(span.start),
+			end_offset = tonumber(span["end"]),
+			component = ffi.string(span.component, span.component_len),
+		}
+	end
+	local kind = VALUE_TYPES[result.kind]
+	l.shelter_free_secret_spans(result)
+	return kind, spans
+end
+
+---@class ShelterPayloadInfo
+---@field summary string What the value carries, e.g. "base64 → JSON with private_key"
+---@field secret? string Secret type id, JSON key path or URL component holding a credential
+---@field layers number Encoded layers decoded
+---@field payload_type? string Type of the decoded payload (nil when binary)
+---@field decoded? string Decoded payload, only when requested
+
+---Decode a base64 or base64url value in memory and report what it carries
+---@param value string
+---@param include_decoded? boolean Also return the decoded payload (off by default)
+---@return ShelterPayloadInfo|nil info nil when the value is not encoded
+function M.inspect_payload(value, include_decoded)
+	local l = ensure_lib()
+	local result = l.shelter_inspect_payload(value, #value, include_decoded and 1 or 0)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_payload(result)
+		error("shelter.nvim: " .. err_msg)
+	end
+
+	local info = nil
+	if result.summary ~= nil then
+		info = {
+			summary = ffi.string(result.summary, result.summary_len),
+			secret = result.secret ~= nil and ffi.string(result.secret, result.secret_len) or nil,
+			layers = tonumber(result.layers),
+			payload_type = VALUE_TYPES[result.payload_type],
+			decoded = result.decoded ~= nil and ffi.string(result.decoded, result.decoded_len) or nil,
+		}
+	end
+	l.shelter_free_payload(result)
+	return info
+end
+
+---Keyed fingerprint of a value
+---Equal values under the same key give equal fingerprints
+---@param value string
+---@param key string
+---@return string fingerprint 16 hex digits
+function M.fingerprint(value, key)
+	local l = ensure_lib()
+	return bit_tohex(l.shelter_fingerprint(value, #value, key, #key), 16)
+end
+
+---Sort a pattern → mode table into {pattern, mode} pairs
+---Sorting makes ties between equally specific patterns resolve deterministically
+---@param patterns? table<string, string>
+---@return string[][]
+local function sorted_patterns(patterns)
+	local list = {}
+	for pattern, mode in pairs(patterns or {}) do
+		list[#list + 1] = { pattern, mode }
+	end
+	table.sort(list, function(a, b)
+		return a[1] < b[1]
+	end)
+	return list
+end
+
+---Build a ShelterPatternRule array from {pattern, mode} pairs
+---The rules point into the pairs' strings, so the pairs must outlive the rules
+---@param list string[][]
+---@return ffi.cdata*|nil rules, number count
+local function rule_array(list)
+	if #list == 0 then
+		return nil, 0
+	end
+	local rules = ffi.new("ShelterPatternRule[?]", #list)
+	for i, item in ipairs(list) do
+		local rule = rules[i - 1]
+		rule.pattern = item[1]
+		rule.pattern_len = #item[1]
+		rule.mode = item[2]
+		rule.mode_len = #item[2]
+	end
+	return rules, #list
+end
+
+---Build a sorted ShelterPatternRule array from a pattern → mode table
+---The rules point into the table's strings, so the table must outlive the rules
+---@param patterns? table<string, string>
+---@return ffi.cdata*|nil rules, number count
+local function pattern_rules(patterns)
+	return rule_array(sorted_patterns(patterns))
+end
+
+---@class ShelterPatternSetOptions
+---@field ignore_case? boolean Match regardless of case
+---@field paths? boolean Match file paths (`*` and `?` stop at `/`, `**` crosses it)
+
+---Compile a pattern → mode table into a native pattern set
+---Glob syntax: `*`, `?`, `[a-z]`, `[!a-z]`, `**` (paths) and `\` escapes.
+---When several patterns match, the most specific wins, then the one with fewer
+---`*` wildcards, then the alphabetically first.
+---@param patterns? table<string, string>
+---@param opts? ShelterPatternSetOptions
+---@return ffi.cdata* set, string[][] rules {pattern, mode} pairs indexed like match_pattern_set
+function M.new_pattern_set(patterns, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+	local list = sorted_patterns(patterns)
+	local options = ffi.new("ShelterPatternOptions", {
+		case_insensitive = opts.ignore_case and 1 or 0,
+		paths = opts.paths and 1 or 0,
+	})
+
+	for _, item in ipairs(list) do
+		local err = l.shelter_pattern_validate(item[1], #item[1], options)
+		if err ~= nil then
+			local msg = ffi.string(err)
+			l.shelter_free_string(err)
+			error("shelter.nvim: " .. msg)
+		end
+	end
+
+	local rules, count = rule_array(list)
+	local set = l.shelter_pattern_set_new(rules, count, options)
+	if set == nil then
+		error("shelter.nvim: Failed to compile patterns")
+	end
+	return ffi.gc(set, l.shelter_pattern_set_free), list
+end
+
+---Find the winning pattern for text
+---@param set ffi.cdata* Set from new_pattern_set
+---@param text string
+---@return number|nil index 1-based index into the set's rules
+function M.match_pattern_set(set, text)
+	local l = ensure_lib()
+	local index = l.shelter_pattern_set_match(set, text, #text)
+	if index == NO_INDEX then
+		return nil
+	end
+	return tonumber(index) + 1
+end
+
+-- Quote type bits of ShelterPolicyRule.quote_types (1 << ShelterQuoteType)
+local QUOTE_BITS = { none = 1, single = 2, double = 4 }
+
+-- Flag conditions (match ShelterFlagCondition)
+local function flag_condition(value)
+	if value == nil then
+		return 0
+	end
+	return value and 1 or 2
+end
+
+---@class ShelterPolicyRuleSpec
+---@field mode string Mode to use when every condition holds
+---@field priority? number Higher runs first, ties in list order (default: 0)
+---@field key? string Key glob
+---@field key_regex? string Key regex (unanchored)
+---@field value_regex? string Value regex (unanchored)
+---@field source? string Source path glob
+---@field min_entropy? number Minimum Shannon entropy of the value, in bits per character
+---@field secret_type? string Glob over the detected token type ("*" = any recognised token)
+---@field min_confidence? "low"|"medium"|"high" Minimum classifier confidence for the value
+---@field looks_random? boolean Value does (or does not) look like a random token
+---@field quote? ("none"|"single"|"double")[] Allowed quote types
+---@field is_comment? boolean
+---@field is_exported? boolean
+---@field highlight_id? number Highlight id for the entry's overlays
+
+---Compile policy rules into a native policy
+---@param rules ShelterPolicyRuleSpec[]
+---@param opts? {ignore_case?: boolean} Match key and source globs regardless of case
+---@return ffi.cdata* policy
+function M.new_policy(rules, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+
+	local specs = ffi.new("ShelterPolicyRule[?]", math.max(#rules, 1))
+	for i, rule in ipairs(rules) do
+		local quote_types = 0
+		for _, quote in ipairs(rule.quote or {}) do
+			quote_types = bit.bor(quote_types, QUOTE_BITS[quote] or 255)
+		end
+
+		local spec = specs[i - 1]
+		for field, value in pairs({
+			key_glob = rule.key,
+			key_regex = rule.key_regex,
+			value_regex = rule.value_regex,
+			source_glob = rule.source,
+			secret_type = rule.secret_type,
+			mode = rule.mode,
+		}) do
+			spec[field] = value
+			spec[field .. "_len"] = #value
+		end
+		spec.min_entropy = rule.min_entropy or 0
+		spec.priority = rule.priority or 0
+		spec.highlight_id = rule.highlight_id or 0
+		spec.quote_types = quote_types
+		spec.is_comment = flag_condition(rule.is_comment)
+		spec.is_exported = flag_condition(rule.is_exported)
+		spec.looks_random = flag_condition(rule.looks_random)
+		spec.case_insensitive = opts.ignore_case and 1 or 0
+		spec.min_confidence = rule.min_confidence and (CONFIDENCE_VALUES[rule.min_confidence] or 255) or 0
+
+		local err = l.shelter_policy_validate(spec)
+		if err ~= nil then
+			local msg = ffi.string(err)
+			l.shelter_free_string(err)
+			error(string.format("shelter.nvim: Policy rule %d: %s", i, msg))
+		end
+	end
+
+	local policy = l.shelter_policy_new(specs, #rules)
+	if policy == nil then
+		error("shelter.nvim: Failed to compile policy")
+	end
+	return ffi.gc(policy, l.shelter_policy_free)
+end
+
+---@class ShelterRenderOptions
+---@field modes? ffi.cdata* Mode table from new_mode_table (default: built-in modes)
+---@field default_mode? string Default mode name (default: "full")
+---@field patterns? table<string, string> Key patterns → mode name
+---@field sources? table<string, string> Source file patterns → mode name
+---@field key_set? ffi.cdata* Compiled key patterns from new_pattern_set (used instead of patterns)
+---@field source_set? ffi.cdata* Compiled source patterns from new_pattern_set (used instead of sources)
+---@field policy? ffi.cdata* Policy from new_policy, tried before the patterns
+---@field source? string Source file path
+---@field tabstop? number Tab width used to measure values containing tabs (default: 8)
+---@field reveals? table<string, ShelterRevealLevel> Entry id → reveal level
+---@field highlight_id? number Highlight id for overlays
+---@field skip_comments? boolean Skip entries inside comments (default: true)
+---@field directives? boolean Honour inline `# shelter:` directives (default: true)
+---@field public_prefixes? string[] Key prefixes bundled into client code, visible unless secret (default: NEXT_PUBLIC_, VITE_, REACT_APP_, EXPO_PUBLIC_, PUBLIC_)
+
+---@class ShelterOverlayChunk
+---@field row number 0-based row
+---@field col number 0-based byte column
+---@field end_col number 0-based byte column where the covered text ends (exclusive)
+---@field text string Virtual text
+---@field hl_id number Highlight id
+---@field entry_index? number 1-based index of the entry (as in parse().entries; nil for private keys in comments)
+---@field is_continuation boolean Row after the first of a multi-line value
+
+---Build a ShelterRenderConfig from render options
+---Strings and rule arrays referenced by the config are returned as anchors;
+---keep them alive until the native call returns
+---@param opts ShelterRenderOptions
+---@return ffi.cdata* config, table anchors
+function render_config(opts)
+	local key_rules, key_count = nil, 0
+	if not opts.key_set then
+		key_rules, key_count = pattern_rules(opts.patterns)
+	end
+	local source_rules, source_count = nil, 0
+	if not opts.source_set then
+		source_rules, source_count = pattern_rules(opts.sources)
+	end
+	local default_mode = opts.default_mode or "full"
+	local source = opts.source or ""
+	local public_prefixes = opts.public_prefixes and table.concat(opts.public_prefixes, "\n")
+
+	local reveal_list = {}
+	for id, level in pairs(opts.reveals or {}) do
+		reveal_list[#reveal_list + 1] = { id, level }
+	end
+	local reveals = nil
+	if #reveal_list > 0 then
+		reveals = ffi.new("ShelterReveal[?]", #reveal_list)
+		for i, item in ipairs(reveal_list) do
+			reveals[i - 1].id = id_from_hex(item[1])
+			fill_reveal(reveals[i - 1], item[2])
+		end
+	end
+
+	local config = ffi.new("ShelterRenderConfig", {
+		modes = opts.modes,
+		default_mode = default_mode,
+		default_mode_len = #default_mode,
+		key_patterns = key_rules,
+		key_pattern_count = key_count,
+		source_patterns = source_rules,
+		source_pattern_count = source_count,
+		key_set = opts.key_set,
+		source_set = opts.source_set,
+		policy = opts.policy,
+		source = source,
+		source_len = #source,
+		public_prefixes = public_prefixes,
+		public_prefixes_len = public_prefixes and #public_prefixes or 0,
+		tabstop = opts.tabstop or 0,
+		reveals = reveals,
+		reveal_count = #reveal_list,
+		highlight_id = opts.highlight_id or 0,
+		skip_comments = opts.skip_comments ~= false and 1 or 0,
+		ignore_directives = opts.directives == false and 1 or 0,
+	})
+	return config, {
+		key_rules,
+		source_rules,
+		reveals,
+		default_mode,
+		source,
+		public_prefixes,
+		opts.patterns,
+		opts.sources,
+		opts.key_set,
+		opts.source_set,
+		opts.policy,
+	}
+end
+
+---Parse content and build ready-to-apply overlay chunks in one call
+---@param content string
+---@param opts? ShelterRenderOptions
+---@return ShelterOverlayChunk[]
+function M.render_plan(content, opts)
+	local l = ensure_lib()
+	local render_opts, anchors = render_config(opts or {})
+
+	local plan = l.shelter_render_plan(content, #content, render_opts)
+	if plan.error ~= nil then
+		local err_msg = ffi.string(plan.error)
+		l.shelter_free_render_plan(plan)
+		error("Render error: " .. err_msg)
+	end
+
+	local overlays = {}
+	for i = 0, tonumber(plan.count) - 1 do
+		local overlay = plan.overlays[i]
+		overlays[i + 1] = {
+			row = tonumber(overlay.row),
+			col = tonumber(overlay.col),
+			end_col = tonumber(overlay.end_col),
+			text = ffi.string(overlay.text, overlay.text_len),
+			hl_id = tonumber(overlay.hl_id),
+			entry_index = overlay.entry_index ~= NO_INDEX and tonumber(overlay.entry_index) + 1 or nil,
+			is_continuation = overlay.is_continuation ~= 0,
+		}
+	end
+
+	l.shelter_free_render_plan(plan)
+	local _ = anchors
+	return overlays
+end
+
+---@class ShelterOffsetSpan
+---@field original_start number 0-based byte offset of the value in the original text
+---@field original_end number End offset in the original text (exclusive)
+---@field masked_start number 0-based byte offset of the replacement in the masked text
+---@field masked_end number End offset in the masked text (exclusive)
+---@field entry_index? number 1-based index of the entry (as in parse().entries; nil for private keys in comments)
+
+---@class ShelterMaskedDocument
+---@field text string Masked copy of the document
+---@field spans ShelterOffsetSpan[] Replaced values in document order
+
+---Render a masked copy of a document
+---Comments, quotes, `export` prefixes and line structure are kept byte-for-byte;
+---only value contents are replaced
+---@param content string
+---@param opts? ShelterRenderOptions
+---@return ShelterMaskedDocument
+function M.render_masked(content, opts)
+	local l = ensure_lib()
+	local render_opts, anchors = render_config(opts or {})
+
+	local doc = l.shelter_render_masked(content, #content, render_opts)
+	if doc.error ~= nil then
+		local err_msg = ffi.string(doc.error)
+		l.shelter_free_masked_document(doc)
+		error("Render error: " .. err_msg)
+	end
+
+	local spans = {}
+	for i = 0, tonumber(doc.span_count) - 1 do
+		local span = doc.spans[i]
+		spans[i + 1] = {
+			original_start = tonumber(span.original_start),
+			original_end = tonumber(span.original_end),
+			masked_start = tonumber(span.masked_start),
+			masked_end = tonumber(span.masked_end),
+			entry_index = span.entry_index ~= NO_INDEX and tonumber(span.entry_index) + 1 or nil,
+		}
+	end
+
+	local result = { text = ffi.string(doc.text, doc.text_len), spans = spans }
+	l.shelter_free_masked_document(doc)
+	local _ = anchors
+	return result
+end
+
+---Map a 0-based byte offset in a masked document back to the original text
+---Offsets inside a replaced value map to the start of the original value
+---@param masked ShelterMaskedDocument
+---@param offset number
+---@return number
+function M.to_original_offset(masked, offset)
+	local delta = 0
+	for _, span in ipairs(masked.spans) do
+		if offset < span.masked_start then
+			break
+		end
+		if offset < span.masked_end then
+			return span.original_start
+		end
+		delta = (span.original_end - span.original_start) - (span.masked_end - span.masked_start) + delta
+	end
+	return offset + delta
+end
+
+---@class ShelterLeak
+---@field row number 0-based row
+---@field col number 0-based byte column where the visible run starts
+---@field end_col number 0-based byte column where the visible run ends (exclusive)
+---@field entry_index number 1-based index of the entry (as in parse().entries)
+
+---Check overlays for secret bytes that would remain visible
+---Simulates the displayed lines (tabs, wide characters) with the overlays drawn
+---on top. Pass the same options used to build the overlays.
+---@param content string
+---@param overlays {row: number, col: number, text: string}[] 0-based positions
+---@param opts? ShelterRenderOptions
+---@return ShelterLeak[]
+function M.verify_overlays(content, overlays, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+	local render_opts, anchors = render_config(opts)
+
+	local count = #overlays
+	local raw = count > 0 and ffi.new("ShelterOverlay[?]", count) or nil
+	for i, overlay in ipairs(overlays) do
+		local item = raw[i - 1]
+		item.text = ffi.cast("char*", ffi.cast("const char*", overlay.text))
+		item.text_len = #overlay.text
+		item.row = overlay.row
+		item.col = overlay.col
+		item.end_col = overlay.end_col or overlay.col
+	end
+
+	local report = l.shelter_verify_overlays(content, #content, raw, count, render_opts, 0)
+	if report.error ~= nil then
+		local err_msg = ffi.string(report.error)
+		l.shelter_free_leak_report(report)
+		error("Verify error: " .. err_msg)
+	end
+
+	local leaks = {}
+	for i = 0, tonumber(report.count) - 1 do
+		local leak = report.leaks[i]
+		leaks[i + 1] = {
+			row = tonumber(leak.row),
+			col = tonumber(leak.col),
+			end_col = tonumber(leak.end_col),
+			entry_index = tonumber(leak.entry_index) + 1,
+		}
+	end
+
+	l.shelter_free_leak_report(report)
+	local _ = anchors
+	return leaks
+end
+
+---@class ShelterVerifyOutcome
+---@field found boolean Whether an uncommented entry with the key exists
+---@field matched boolean Whether the candidate equals its value
+---@field entry_index number|nil 1-based index of the compared entry
+---@field prefix_length number|nil Leading characters shared with the value (only with report_prefix)
+
+---Check a candidate against a value without revealing it (constant-time)
+---Compares against the last uncommented entry with `key`
+---@param content string
+---@param key string
+---@param candidate string
+---@param opts? {report_prefix?: boolean} Disclose the shared prefix length (default: false)
+---@return ShelterVerifyOutcome
+function M.verify_value(content, key, candidate, opts)
+	local l = ensure_lib()
+	local policy = ffi.new("ShelterVerifyPolicy", {
+		report_prefix = opts and opts.report_prefix and 1 or 0,
+	})
+
+	local result = l.shelter_verify_value(content, #content, key, #key, candidate, #candidate, policy)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_verify_result(result)
+		error("Verify error: " .. err_msg)
+	end
+
+	local found = result.found ~= 0
+	local outcome = {
+		found = found,
+		matched = result.matched ~= 0,
+		entry_index = found and tonumber(result.entry_index) + 1 or nil,
+		prefix_length = result.prefix_reported ~= 0 and tonumber(result.prefix_length) or nil,
+	}
+	l.shelter_free_verify_result(result)
+	return outcome
+end
+
+---Stage names of trace steps, indexed by ShelterTraceStage + 1
+local TRACE_STAGES = { "policy", "key", "source", "default", "directive", "public" }
+
+---@class ShelterTraceStep
+---@field stage "directive"|"policy"|"key"|"source"|"public"|"default"
+---@field rule string Pattern, policy rule conditions, directive line ("line 3"), public prefix, or the default mode name
+---@field mode string Mode the rule selects
+---@field rule_index number|nil 1-based index of the rule in its stage (nil for the default)
+---@field rank number Priority for policy rules, specificity for patterns
+---@field matched boolean Whether the rule matched the entry
+---@field chosen boolean Whether this rule decided the mode
+---@field failed string|nil Condition a policy rule failed on, why a directive was ignored, or why a public prefix did not apply
+
+---@class ShelterExplanation
+---@field mode string Chosen mode name
+---@field stage "directive"|"policy"|"key"|"source"|"public"|"default" Stage of the deciding step
+---@field unknown_mode boolean Chosen mode is not registered (rendered as full)
+---@field steps ShelterTraceStep[] Every rule considered, in evaluation order
+---@field secret_type string|nil Secret type the classifier assigns to the value
+---@field secret_confidence "low"|"medium"|"high"|nil Confidence of secret_type
+---@field entropy number Shannon entropy of the value (bits per character)
+---@field randomness number Entropy relative to the value's charset and length, 0 to 1
+---@field looks_random boolean Whether the value looks like a random token
+
+---Explain why an entry gets its mode
+---Pass the same options used for rendering.
+---@param content string
+---@param entry_index number 1-based index of the entry (as in parse().entries)
+---@param opts? ShelterRenderOptions
+---@return ShelterExplanation
+function M.explain(content, entry_index, opts)
+	local l = ensure_lib()
+	local render_opts, anchors = render_config(opts or {})
+
+	local result = l.shelter_explain(content, #content, entry_index - 1, render_opts)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_explanation(result)
+		error("Explain error: " .. err_msg)
+	end
+
+	local steps = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local step = result.steps[i]
+		steps[i + 1] = {
+			stage = TRACE_STAGES[step.stage + 1],
+			rule = ffi.string(step.rule),
+			mode = ffi.string(step.mode),
+			rule_index = step.rule_index ~= NO_INDEX and tonumber(step.rule_index) + 1 or nil,
+			rank = tonumber(step.rank),
+			matched = step.matched ~= 0,
+			chosen = step.chosen ~= 0,
+			failed = step.detail ~= nil and ffi.string(step.detail) or nil,
+		}
+	end
+
+	local explanation = {
+		mode = ffi.string(result.mode),
+		stage = TRACE_STAGES[result.stage + 1],
+		unknown_mode = result.unknown_mode ~= 0,
+		steps = steps,
+		secret_type = result.secret_type ~= nil and ffi.string(result.secret_type) or nil,
+		secret_confidence = CONFIDENCE_LEVELS[result.secret_confidence],
+		entropy = tonumber(result.entropy),
+		randomness = tonumber(result.randomness),
+		looks_random = result.looks_random ~= 0,
 	}
+	l.shelter_free_explanation(result)
+	local _ = anchors
+	return explanation
 end
 
 return M
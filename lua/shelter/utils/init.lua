@@ -6,5 +6,7 @@ M.env_file = require("shelter.utils.env_file")
 M.module_validation = require("shelter.utils.module_validation")
 M.pattern_cache = require("shelter.utils.pattern_cache")
 M.debounce = require("shelter.utils.debounce")
+M.text = require("shelter.utils.text")
+M.keyfile = require("shelter.utils.keyfile")
 
 return M
@@ -0,0 +1,96 @@
+---Per-user key for keyed mask lengths and fingerprints
+---@module "shelter.utils.keyfile"
+---The key is 32 random bytes stored hex-encoded in a file readable only by the
+---user. It never leaves the machine, so fingerprints cannot be checked against
+---guessed values by anyone without it.
+
+local M = {}
+
+local KEY_BYTES = 32
+
+-- Loaded key (raw bytes)
+local cached_key = nil
+
+---Default key file location
+---@return string
+function M.default_path()
+	return vim.fn.stdpath("data") .. "/shelter/key"
+end
+
+---Decode a hex string, nil if malformed
+---@param hex string
+---@return string|nil
+local function from_hex(hex)
+	if #hex ~= KEY_BYTES * 2 or hex:find("[^%x]") then
+		return nil
+	end
+	return (hex:gsub("%x%x", function(pair)
+		return string.char(tonumber(pair, 16))
+	end))
+end
+
+---Read a key file
+---@param path string
+---@return string|nil key Raw key bytes, nil if the file does not exist
+local function read(path)
+	local file = io.open(path, "r")
+	if not file then
+		return nil
+	end
+	local contents = file:read("*a")
+	file:close()
+	local key = from_hex(vim.trim(contents))
+	if not key then
+		error(string.format("shelter.nvim: Invalid key file '%s' (expected %d hex bytes)", path, KEY_BYTES))
+	end
+	return key
+end
+
+---Generate and store a new key (file mode 0600)
+---If another instance created the file first, its key is used instead
+---@param path string
+---@return string key
+local function create(path)
+	local key = vim.uv.random(KEY_BYTES)
+	local hex = key:gsub(".", function(c)
+		return string.format("%02x", c:byte())
+	end)
+
+	vim.fn.mkdir(vim.fn.fnamemodify(path, ":h"), "p")
+	local fd, open_err, open_err_name = vim.uv.fs_open(path, "wx", 384)
+	if not fd then
+		if open_err_name == "EEXIST" then
+			return read(path) or error(string.format("shelter.nvim: Key file '%s' disappeared", path))
+		end
+		error(string.format("shelter.nvim: Cannot create key file '%s': %s", path, open_err))
+	end
+
+	local data = hex .. "\n"
+	local written, write_err = vim.uv.fs_write(fd, data)
+	local closed, close_err = vim.uv.fs_close(fd)
+	if written ~= #data or not closed then
+		-- Never leave a partial key behind: it would be rejected on every start
+		vim.uv.fs_unlink(path)
+		error(string.format("shelter.nvim: Cannot write key file '%s': %s", path, write_err or close_err or "short write"))
+	end
+	return key
+end
+
+---Get the per-user key, creating the key file on first use
+---@param path? string Key file (default: config key_file or default_path())
+---@return string key Raw key bytes
+function M.get(path)
+	if cached_key then
+		return cached_key
+	end
+	path = path or require("shelter.config").get().key_file or M.default_path()
+	cached_key = read(path) or create(path)
+	return cached_key
+end
+
+---Forget the loaded key (call when key_file changes)
+function M.reset()
+	cached_key = nil
+end
+
+return M
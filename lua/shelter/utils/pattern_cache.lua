@@ -1,17 +1,22 @@
 ---@class ShelterPatternCache
----Pre-compiled pattern matching for O(1) lookups after O(n) setup
+---Compiled key and source patterns, backed by native pattern sets
+---Each set matches a key or path against all of its patterns in one pass. See
+---native.new_pattern_set for the glob syntax and precedence rules.
 local M = {}
 
----@class CompiledPattern
----@field lua_pattern string
----@field mode string
----@field specificity number
+local native = require("shelter.native")
 
----@type table<string, CompiledPattern>
-local _key_patterns = {}
+---@type ffi.cdata*|nil
+local _key_set = nil
 
----@type table<string, string>
-local _source_patterns = {}
+---@type string[][] {pattern, mode} pairs, indexed like _key_set matches
+local _key_rules = {}
+
+---@type ffi.cdata*|nil
+local _source_set = nil
+
+---@type string[][] {pattern, mode} pairs, indexed like _source_set matches
+local _source_rules = {}
 
 ---@type string
 local _default_mode = "full"
@@ -19,89 +24,39 @@ local _default_mode = "full"
 ---@type boolean
 local _compiled = false
 
----Convert glob pattern to Lua pattern (cached at module level)
----@param glob string
----@return string
-local function glob_to_pattern(glob)
-	local pattern = glob
-	-- Escape special Lua pattern characters (except *)
-	pattern = pattern:gsub("([%.%+%-%?%^%$%(%)%[%]%%])", "%%%1")
-	-- Convert * to .*
-	pattern = pattern:gsub("%*", ".*")
-	return "^" .. pattern .. "$"
-end
-
----Calculate pattern specificity (higher = more specific)
----@param pattern string
----@return number
-local function calculate_specificity(pattern)
-	-- Longer patterns are more specific
-	-- Wildcards reduce specificity
-	local wildcards = 0
-	for _ in pattern:gmatch("%*") do
-		wildcards = wildcards + 1
-	end
-	return #pattern - (wildcards * 10)
-end
-
 ---Compile all patterns from config (call once at setup)
----@param config table The config table with patterns, sources, default_mode
+---@param config table The config table with patterns, sources, default_mode, pattern_ignore_case
 function M.compile(config)
-	_key_patterns = {}
-	_source_patterns = {}
 	_default_mode = config.default_mode or "full"
+	local ignore_case = config.pattern_ignore_case == true
 
-	-- Pre-compile key patterns
-	for pattern, mode in pairs(config.patterns or {}) do
-		_key_patterns[pattern] = {
-			lua_pattern = glob_to_pattern(pattern),
-			mode = mode,
-			specificity = calculate_specificity(pattern),
-		}
-	end
-
-	-- Pre-compile source patterns
-	for pattern, mode in pairs(config.sources or {}) do
-		_source_patterns[pattern] = glob_to_pattern(pattern)
-		-- Store mode separately since we use pattern as key
-		_source_patterns[pattern .. "_mode"] = mode
-	end
+	_key_set, _key_rules = native.new_pattern_set(config.patterns, { ignore_case = ignore_case })
+	_source_set, _source_rules = native.new_pattern_set(config.sources, { ignore_case = ignore_case, paths = true })
 
 	_compiled = true
 end
 
----Match a key against pre-compiled patterns
+---Match a key against compiled patterns
 ---@param key string
 ---@return string|nil mode
 function M.match_key(key)
-	local best_match = nil
-	local best_specificity = -math.huge -- Start with negative infinity so any match wins
-
-	for _, compiled in pairs(_key_patterns) do
-		if key:match(compiled.lua_pattern) then
-			if compiled.specificity > best_specificity then
-				best_specificity = compiled.specificity
-				best_match = compiled.mode
-			end
-		end
+	if not _key_set then
+		return nil
 	end
-
-	return best_match
+	local index = native.match_pattern_set(_key_set, key)
+	return index and _key_rules[index][2]
 end
 
----Match a source filename against pre-compiled patterns
----@param source_name string The basename of the source file
+---Match a source file against compiled patterns
+---Patterns without a `/` match the file name, others match trailing path components
+---@param source string Path (or basename) of the source file
 ---@return string|nil mode
-function M.match_source(source_name)
-	for pattern, lua_pattern in pairs(_source_patterns) do
-		-- Skip the _mode entries
-		if not pattern:match("_mode$") then
-			if source_name:match(lua_pattern) then
-				return _source_patterns[pattern .. "_mode"]
-			end
-		end
+function M.match_source(source)
+	if not _source_set then
+		return nil
 	end
-	return nil
+	local index = native.match_pattern_set(_source_set, source)
+	return index and _source_rules[index][2]
 end
 
 ---Get the default masking mode
@@ -110,20 +65,19 @@ function M.get_default_mode()
 	return _default_mode
 end
 
----Determine masking mode for a key (uses pre-compiled patterns)
+---Determine masking mode for a key (uses compiled patterns)
 ---@param key string
----@param source_basename string|nil Pre-computed basename of source file (caller should cache this)
+---@param source string|nil Path of the source file
 ---@return string mode
-function M.determine_mode(key, source_basename)
+function M.determine_mode(key, source)
 	-- Check key patterns first (most specific wins)
 	local key_mode = M.match_key(key)
 	if key_mode then
 		return key_mode
 	end
 
-	-- Check source patterns (basename already extracted by caller)
-	if source_basename then
-		local source_mode = M.match_source(source_basename)
+	if source then
+		local source_mode = M.match_source(source)
 		if source_mode then
 			return source_mode
 		end
@@ -132,6 +86,12 @@ function M.determine_mode(key, source_basename)
 	return _default_mode
 end
 
+---Compiled sets for native rendering
+---@return ffi.cdata*|nil key_set, ffi.cdata*|nil source_set
+function M.get_sets()
+	return _key_set, _source_set
+end
+
 ---Check if patterns are compiled
 ---@return boolean
 function M.is_compiled()
@@ -140,8 +100,10 @@ end
 
 ---Clear compiled patterns (for config reload)
 function M.clear()
-	_key_patterns = {}
-	_source_patterns = {}
+	_key_set = nil
+	_key_rules = {}
+	_source_set = nil
+	_source_rules = {}
 	_compiled = false
 end
 
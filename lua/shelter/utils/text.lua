@@ -0,0 +1,68 @@
+---@class ShelterText
+---Display-width and character helpers for masking multi-byte text
+---Characters are counted with their combining marks, so partial masking never
+---splits a character and masks match the on-screen width of the value
+local M = {}
+
+local fn = vim.fn
+local string_rep = string.rep
+local math_floor = math.floor
+
+---Whether text needs no multi-byte or tab handling (bytes == cells)
+---@param text string
+---@return boolean
+local function is_simple(text)
+	return not text:find("[\t\128-\255]")
+end
+
+---Display cells text occupies
+---@param text string
+---@return number
+function M.width(text)
+	if is_simple(text) then
+		return #text
+	end
+	return fn.strdisplaywidth(text)
+end
+
+---Split text into characters, keeping combining marks with their base
+---@param text string
+---@return string[]
+function M.chars(text)
+	if is_simple(text) then
+		local chars = {}
+		for i = 1, #text do
+			chars[i] = text:sub(i, i)
+		end
+		return chars
+	end
+	return fn.split(text, "\\zs")
+end
+
+---Whether text is a single visible character usable as a mask glyph
+---@param text any
+---@return boolean
+function M.is_mask_glyph(text)
+	if type(text) ~= "string" or text == "" then
+		return false
+	end
+	local width = M.width(text)
+	return #M.chars(text) == 1 and width >= 1 and width <= 2
+end
+
+---Mask covering exactly `cells` display cells
+---A double-width glyph cannot fill an odd width, so the last cell is a space
+---@param mask_char string
+---@param cells number
+---@param rep? fun(s: string, n: number): string Repeat function (default: string.rep)
+---@return string
+function M.fill(mask_char, cells, rep)
+	rep = rep or string_rep
+	local glyph_width = M.width(mask_char)
+	if glyph_width <= 1 then
+		return rep(mask_char, cells)
+	end
+	return rep(mask_char, math_floor(cells / glyph_width)) .. string_rep(" ", cells % glyph_width)
+end
+
+return M
@@ -45,6 +45,19 @@ describe("shelter.masking.engine", function()
       assert.equals(3, #result.entries)
       assert.equals(3, #result.line_offsets)
     end)
+
+    it("analyses only what the configured modes read", function()
+      local content = "TOKEN=eyJhcGlfa2V5IjoiayJ9"
+      local entry = engine.parse_content(content).entries[1]
+      assert.is_nil(entry.secret_type)
+      assert.is_nil(entry.value_type)
+      assert.is_nil(entry.payload)
+
+      config.setup({ default_mode = "partial", modes = { partial = { fallback_mode = "none", min_mask = 30 } } })
+      engine.init()
+      assert.is_true(engine.uses_payload_secrets())
+      assert.is_true(engine.parse_content(content).entries[1].payload_secret)
+    end)
   end)
 
   describe("generate_masks", function()
@@ -74,6 +87,23 @@ describe("shelter.masking.engine", function()
       assert.is_number(mask.value_end)
     end)
 
+    it("applies policy rules before key patterns", function()
+      config.setup({
+        default_mode = "full",
+        patterns = { ["*"] = "none" },
+        policy = {
+          { value_regex = "^ghp_", mode = "full", highlight = "ErrorMsg" },
+        },
+      })
+      engine.init()
+
+      local result = engine.generate_masks("TOKEN=ghp_abcdef\nNAME=shelter", "test.env")
+      assert.equals("**********", result.masks[1].mask)
+      assert.equals("ErrorMsg", result.masks[1].hl_group)
+      assert.equals("shelter", result.masks[2].mask)
+      assert.is_nil(result.masks[2].hl_group)
+    end)
+
     it("skips comments when skip_comments is true", function()
       config.setup({ skip_comments = true })
       engine.init()
@@ -150,6 +180,75 @@ describe("shelter.masking.engine", function()
       -- More specific patterns should take precedence
       assert.equals("full", engine.determine_mode("SECRET_KEY", "test.env"))
     end)
+
+    it("matches source patterns against the file path", function()
+      config.setup({
+        default_mode = "full",
+        sources = {
+          [".env.*"] = "partial",
+          ["config/**/*.env"] = "none",
+        },
+      })
+      engine.init()
+
+      assert.equals("partial", engine.determine_mode("KEY", "/app/.env.local"))
+      assert.equals("none", engine.determine_mode("KEY", "/app/config/eu/prod.env"))
+      assert.equals("full", engine.determine_mode("KEY", "/app/prod.env"))
+    end)
+
+    it("matches case-insensitively when configured", function()
+      config.setup({
+        default_mode = "full",
+        patterns = { ["*_token"] = "none" },
+        pattern_ignore_case = true,
+      })
+      engine.init()
+
+      assert.equals("none", engine.determine_mode("AUTH_TOKEN", "test.env"))
+    end)
+  end)
+
+  describe("directives", function()
+    it("override patterns unless disabled", function()
+      config.setup({ default_mode = "full", patterns = { ["*"] = "partial" } })
+      engine.init()
+      local content = "# shelter: mode=none\nPORT=3000"
+      local masks = engine.generate_masks(content, "test.env").masks
+      assert.equals("3000", masks[1].mask)
+
+      config.setup({ default_mode = "full", patterns = { ["*"] = "full" }, directives = false })
+      engine.init()
+      engine.clear_caches()
+      masks = engine.generate_masks(content, "test.env").masks
+      assert.equals("****", masks[1].mask)
+    end)
+
+    it("ignore unknown modes", function()
+      config.setup({ default_mode = "full", patterns = { ["*"] = "none" } })
+      engine.init()
+      local masks = engine.generate_masks("# shelter: mode=fulll\nPORT=3000", "test.env").masks
+      assert.equals("3000", masks[1].mask)
+    end)
+  end)
+
+  describe("explain", function()
+    it("agrees with determine_mode", function()
+      config.setup({
+        default_mode = "full",
+        patterns = { ["*_TOKEN"] = "partial" },
+        sources = { ["*.env"] = "none" },
+      })
+      engine.init()
+
+      local content = "AUTH_TOKEN=abc\nDEBUG=true"
+      local token = engine.explain(content, "/app/test.env", 1)
+      assert.equals("partial", token.mode)
+      assert.equals("key", token.stage)
+      local debug = engine.explain(content, "/app/test.env", 2)
+      assert.equals(engine.determine_mode("DEBUG", "/app/test.env"), debug.mode)
+      assert.equals("source", debug.stage)
+      assert.is_false(debug.unknown_mode)
+    end)
   end)
 
   describe("mask_value", function()
@@ -31,6 +31,9 @@ describe("shelter.modes", function()
 			assert.is_true(vim.tbl_contains(list, "full"))
 			assert.is_true(vim.tbl_contains(list, "partial"))
 			assert.is_true(vim.tbl_contains(list, "none"))
+			assert.is_true(vim.tbl_contains(list, "fingerprint"))
+			assert.is_true(vim.tbl_contains(list, "smart"))
+			assert.is_true(vim.tbl_contains(list, "structured"))
 		end)
 	end)
 
@@ -86,6 +89,13 @@ describe("shelter.modes", function()
 			assert.equals("my*********ue", result)
 		end)
 
+		it("shows no end characters when show_end is 0", function()
+			local partial = modes.create("partial", { show_start = 2, show_end = 0 })
+			local ctx = { key = "TEST", value = "abcdefgh", line_number = 1 }
+			-- Same as the native partial mode
+			assert.equals("ab******", partial:apply(ctx))
+		end)
+
 		it("can use none as fallback for short values", function()
 			local partial = modes.create("partial", { fallback_mode = "none", min_mask = 5 })
 			local ctx = { key = "TEST", value = "hi", line_number = 1 }
@@ -93,6 +103,76 @@ describe("shelter.modes", function()
 			-- Too short, fallback to none
 			assert.equals("hi", result)
 		end)
+
+		it("masks encoded secrets instead of falling back to none", function()
+			local partial = modes.create("partial", { fallback_mode = "none", min_mask = 5 })
+			local ctx = { key = "TEST", value = "e30=", line_number = 1, payload_secret = true }
+			assert.equals("****", partial:apply(ctx))
+		end)
+	end)
+
+	describe("multi-byte values", function()
+		it("masks by display width", function()
+			local ctx = { key = "TEST", value = "пароль", line_number = 1 }
+			assert.equals("******", modes.apply("full", ctx.value, ctx))
+		end)
+
+		it("never splits a character in partial mode", function()
+			local ctx = { key = "TEST", value = "пароль123456", line_number = 1 }
+			assert.equals("пар******456", modes.apply("partial", ctx.value, ctx))
+		end)
+
+		it("accepts multi-byte and double-width mask glyphs", function()
+			local bullet = modes.create("full", { mask_char = "•" })
+			assert.equals("••••••", bullet:apply({ key = "TEST", value = "secret" }))
+
+			local wide = modes.create("full", { mask_char = "＊" })
+			assert.equals("＊＊＊", wide:apply({ key = "TEST", value = "secret" }))
+			assert.equals("＊ ", wide:apply({ key = "TEST", value = "odd" }))
+		end)
+	end)
+
+	describe("length strategies", function()
+		it("rounds mask widths up to the bucket size", function()
+			local full = modes.create("full", { length_strategy = "bucket", bucket_size = 8 })
+			assert.equals("********", full:apply({ key = "TEST", value = "abc" }))
+			assert.equals("****************", full:apply({ key = "TEST", value = "123456789" }))
+
+			local partial = modes.create("partial", { length_strategy = "bucket", bucket_size = 4 })
+			assert.equals("mys********lue", partial:apply({ key = "TEST", value = "mysecretvalue" }))
+		end)
+
+		it("uses natively computed lengths when available", function()
+			local full = modes.create("full", { length_strategy = "keyed" })
+			assert.equals("*****", full:apply({ key = "TEST", value = "secret", masked_length = 5 }))
+			-- Without native metadata the keyed width falls back to max_length
+			assert.equals(string.rep("*", 24), full:apply({ key = "TEST", value = "secret" }))
+		end)
+
+		it("rejects invalid length options", function()
+			assert.has_error(function()
+				modes.create("full", { length_strategy = "random" })
+			end)
+			assert.has_error(function()
+				modes.create("full", { min_length = 20, max_length = 10 })
+			end)
+		end)
+	end)
+
+	describe("fingerprint mode", function()
+		it("shows a short fingerprint after a fixed mask", function()
+			local ctx = { key = "TEST", value = "secret", fingerprint = "a1f3b2c4d5e6f708" }
+			assert.equals("••••[a1f3]", modes.apply("fingerprint", ctx.value, ctx))
+
+			local long = modes.create("fingerprint", { mask_char = "*", fixed_length = 2, fingerprint_length = 8 })
+			assert.equals("**[a1f3b2c4]", long:apply(ctx))
+		end)
+
+		it("rejects fingerprints longer than 16 digits", function()
+			assert.has_error(function()
+				modes.create("fingerprint", { fingerprint_length = 17 })
+			end)
+		end)
 	end)
 
 	describe("none mode", function()